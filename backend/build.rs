@@ -0,0 +1,9 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Prefer a system protoc (the Dockerfile installs protobuf-compiler) and
+    // fall back to the vendored binary so plain `cargo build` works anywhere.
+    if std::env::var_os("PROTOC").is_none() {
+        std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?);
+    }
+    tonic_build::compile_protos("proto/auth.proto")?;
+    Ok(())
+}
@@ -0,0 +1,31 @@
+-- Roles form a single-parent hierarchy; a role inherits every permission of its ancestors
+CREATE TABLE IF NOT EXISTS roles (
+    name VARCHAR(50) PRIMARY KEY,
+    parent VARCHAR(50) REFERENCES roles(name) ON DELETE SET NULL,
+    description TEXT
+);
+
+CREATE TABLE IF NOT EXISTS role_permissions (
+    role VARCHAR(50) NOT NULL REFERENCES roles(name) ON DELETE CASCADE,
+    permission VARCHAR(100) NOT NULL,
+    PRIMARY KEY (role, permission)
+);
+
+INSERT INTO roles (name, parent, description) VALUES
+    ('User', NULL, 'Regular user with limited access'),
+    ('Moderator', 'User', 'Can review other users'),
+    ('Admin', 'Moderator', 'Administrator with full access')
+ON CONFLICT (name) DO NOTHING;
+
+INSERT INTO role_permissions (role, permission) VALUES
+    ('User', 'user:access'),
+    ('User', 'profile:read'),
+    ('User', 'profile:write'),
+    ('Moderator', 'users:read'),
+    ('Admin', 'admin:access'),
+    ('Admin', 'users:write'),
+    ('Admin', 'roles:write')
+ON CONFLICT DO NOTHING;
+
+ALTER TABLE users
+    ADD CONSTRAINT users_role_fkey FOREIGN KEY (role) REFERENCES roles(name) ON UPDATE CASCADE;
@@ -0,0 +1,41 @@
+-- User groups; a group's members include the members of its subgroups
+CREATE TABLE IF NOT EXISTS groups (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR(100) NOT NULL UNIQUE,
+    description TEXT,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS group_members (
+    group_id INTEGER NOT NULL REFERENCES groups(id) ON DELETE CASCADE,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    PRIMARY KEY (group_id, user_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_group_members_user_id ON group_members(user_id);
+
+CREATE TABLE IF NOT EXISTS group_subgroups (
+    parent_group_id INTEGER NOT NULL REFERENCES groups(id) ON DELETE CASCADE,
+    child_group_id INTEGER NOT NULL REFERENCES groups(id) ON DELETE CASCADE,
+    PRIMARY KEY (parent_group_id, child_group_id),
+    CHECK (parent_group_id <> child_group_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_group_subgroups_child ON group_subgroups(child_group_id);
+
+-- Grants that apply to every transitive member of the group
+CREATE TABLE IF NOT EXISTS group_roles (
+    group_id INTEGER NOT NULL REFERENCES groups(id) ON DELETE CASCADE,
+    role VARCHAR(50) NOT NULL REFERENCES roles(name) ON DELETE CASCADE ON UPDATE CASCADE,
+    PRIMARY KEY (group_id, role)
+);
+
+CREATE TABLE IF NOT EXISTS group_permissions (
+    group_id INTEGER NOT NULL REFERENCES groups(id) ON DELETE CASCADE,
+    permission VARCHAR(100) NOT NULL,
+    PRIMARY KEY (group_id, permission)
+);
+
+INSERT INTO role_permissions (role, permission) VALUES
+    ('Admin', 'groups:write')
+ON CONFLICT DO NOTHING;
@@ -0,0 +1,20 @@
+-- Relationship tuples: object_type:object_id#relation@subject_type:subject_id[#subject_relation]
+-- An empty subject_relation means the subject is a concrete object rather than a userset
+CREATE TABLE IF NOT EXISTS relation_tuples (
+    object_type VARCHAR(64) NOT NULL,
+    object_id VARCHAR(128) NOT NULL,
+    relation VARCHAR(64) NOT NULL,
+    subject_type VARCHAR(64) NOT NULL,
+    subject_id VARCHAR(128) NOT NULL,
+    subject_relation VARCHAR(64) NOT NULL DEFAULT '',
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (object_type, object_id, relation, subject_type, subject_id, subject_relation)
+);
+
+CREATE INDEX IF NOT EXISTS idx_relation_tuples_subject
+    ON relation_tuples(subject_type, subject_id, subject_relation);
+
+INSERT INTO role_permissions (role, permission) VALUES
+    ('Admin', 'relations:read'),
+    ('Admin', 'relations:write')
+ON CONFLICT DO NOTHING;
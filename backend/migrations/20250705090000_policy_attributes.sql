@@ -0,0 +1,6 @@
+-- Verified email status, exposed to authorization policies as user.email_verified
+ALTER TABLE users ADD COLUMN IF NOT EXISTS email_verified BOOLEAN NOT NULL DEFAULT FALSE;
+
+INSERT INTO role_permissions (role, permission) VALUES
+    ('Admin', 'policies:read')
+ON CONFLICT DO NOTHING;
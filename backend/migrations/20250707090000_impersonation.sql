@@ -0,0 +1,5 @@
+-- Minting impersonation tokens; every impersonated request is recorded in the
+-- audit log as an `impersonated_request` admin action on the impersonated user
+INSERT INTO role_permissions (role, permission) VALUES
+    ('Admin', 'users:impersonate')
+ON CONFLICT DO NOTHING;
@@ -0,0 +1,16 @@
+-- Security notifications sent for unusual logins; the token backs the
+-- "this wasn't me" link and can be used once
+CREATE TABLE IF NOT EXISTS login_alerts (
+    id BIGSERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    token UUID NOT NULL UNIQUE,
+    session_id UUID REFERENCES sessions(id) ON DELETE SET NULL,
+    reasons TEXT[] NOT NULL,
+    ip VARCHAR(45),
+    user_agent TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    used_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX IF NOT EXISTS idx_login_alerts_user_id ON login_alerts(user_id);
@@ -0,0 +1,21 @@
+-- Resumable photo uploads; chunks are staged in blob storage until completion
+CREATE TABLE IF NOT EXISTS photo_uploads (
+    id UUID PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    size BIGINT NOT NULL CHECK (size > 0),
+    sha256 CHAR(64) NOT NULL,
+    received BIGINT NOT NULL DEFAULT 0 CHECK (received <= size),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_photo_uploads_user_id ON photo_uploads(user_id);
+CREATE INDEX IF NOT EXISTS idx_photo_uploads_expires_at ON photo_uploads(expires_at);
+
+CREATE TABLE IF NOT EXISTS photo_upload_chunks (
+    upload_id UUID NOT NULL REFERENCES photo_uploads(id) ON DELETE CASCADE,
+    "offset" BIGINT NOT NULL,
+    length BIGINT NOT NULL,
+    blob_key TEXT NOT NULL,
+    PRIMARY KEY (upload_id, "offset")
+);
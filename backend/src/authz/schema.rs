@@ -0,0 +1,129 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use crate::config::config::app_config;
+
+/// Built-in schema used when `AUTHZ_SCHEMA_PATH` is not set
+const DEFAULT_SCHEMA: &str = include_str!("../../authz_schema.json");
+
+/// Namespace configuration for relationship-based access control.
+///
+/// Every relation accepts directly stored tuples. A relation can additionally
+/// be implied by other relations on the same object (`implied_by`, e.g. owners
+/// are editors) or inherited from related objects (`from_parent`, e.g. editors
+/// of a folder are editors of its documents).
+#[derive(Debug, Deserialize)]
+pub struct AuthzSchema {
+    pub namespaces: HashMap<String, NamespaceConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NamespaceConfig {
+    #[serde(default)]
+    pub relations: HashMap<String, RelationConfig>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct RelationConfig {
+    /// Relations on the same object whose subjects also have this relation
+    #[serde(default)]
+    pub implied_by: Vec<String>,
+    /// Relations inherited from the objects referenced by a tupleset relation
+    #[serde(default)]
+    pub from_parent: Vec<TupleToUserset>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TupleToUserset {
+    /// Relation on this object whose subjects are the parent objects
+    pub tupleset: String,
+    /// Relation checked on each parent object
+    pub relation: String,
+}
+
+impl AuthzSchema {
+    /// Loads the schema from `authz.schema_path`, or the built-in default
+    pub fn load() -> Result<Self, String> {
+        let source = match &app_config().authz.schema_path {
+            Some(path) => std::fs::read_to_string(path)
+                .map_err(|e| format!("Failed to read authz schema {}: {}", path, e))?,
+            None => DEFAULT_SCHEMA.to_string(),
+        };
+
+        Self::parse(&source)
+    }
+
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let schema: AuthzSchema =
+            serde_json::from_str(source).map_err(|e| format!("Invalid authz schema: {}", e))?;
+        schema.validate()?;
+        Ok(schema)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let mut errors = Vec::new();
+
+        for (namespace, config) in &self.namespaces {
+            for (relation, rewrite) in &config.relations {
+                for implied in &rewrite.implied_by {
+                    if !config.relations.contains_key(implied) {
+                        errors.push(format!(
+                            "{}#{} is implied by unknown relation {}",
+                            namespace, relation, implied
+                        ));
+                    }
+                }
+                for parent in &rewrite.from_parent {
+                    if !config.relations.contains_key(&parent.tupleset) {
+                        errors.push(format!(
+                            "{}#{} uses unknown tupleset relation {}",
+                            namespace, relation, parent.tupleset
+                        ));
+                    }
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("Invalid authz schema: {}", errors.join("; ")))
+        }
+    }
+
+    pub fn has_namespace(&self, namespace: &str) -> bool {
+        self.namespaces.contains_key(namespace)
+    }
+
+    pub fn relation(&self, namespace: &str, relation: &str) -> Option<&RelationConfig> {
+        self.namespaces.get(namespace)?.relations.get(relation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_schema_is_valid() {
+        let schema = AuthzSchema::parse(DEFAULT_SCHEMA).unwrap();
+        let editor = schema.relation("doc", "editor").unwrap();
+        assert_eq!(editor.implied_by, ["owner"]);
+        assert_eq!(editor.from_parent[0].tupleset, "parent");
+        assert!(schema.relation("doc", "unknown").is_none());
+        assert!(schema.has_namespace("team"));
+    }
+
+    #[test]
+    fn unknown_rewrite_relations_are_reported_together() {
+        let error = AuthzSchema::parse(
+            r#"{ "namespaces": { "doc": { "relations": { "viewer": {
+                "implied_by": ["editor"],
+                "from_parent": [{ "tupleset": "parent", "relation": "viewer" }]
+            } } } } }"#,
+        )
+        .unwrap_err();
+        assert!(error.contains("doc#viewer is implied by unknown relation editor"), "{}", error);
+        assert!(error.contains("doc#viewer uses unknown tupleset relation parent"), "{}", error);
+    }
+}
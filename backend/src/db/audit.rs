@@ -0,0 +1,346 @@
+use chrono::{DateTime, DurationRound, SecondsFormat, Utc};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use sqlx::{Pool, Postgres, QueryBuilder, Transaction};
+
+use crate::models::audit::{AuditContext, AuditEvent, AuditEventType, AuditFilter, ChainVerification};
+
+/// Advisory lock serialising appends, so every event links to the latest one
+const CHAIN_LOCK_KEY: i64 = 0x61_7564_6974;
+
+/// `prev_hash` of the first event
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Upper bound on the number of events in a single export
+pub const MAX_EXPORT_EVENTS: i64 = 100_000;
+
+/// Events verified per query while walking the chain
+const VERIFY_BATCH_SIZE: i64 = 1_000;
+
+/// SHA-256 over a canonical JSON array of the event's fields and the previous hash.
+/// Timestamps are hashed at microsecond precision, which is what Postgres stores.
+#[allow(clippy::too_many_arguments)]
+fn chain_hash(
+    prev_hash: &str,
+    event_type: &str,
+    actor_id: Option<i32>,
+    target_user_id: Option<i32>,
+    ip: Option<&str>,
+    user_agent: Option<&str>,
+    request_id: Option<&str>,
+    details: &Value,
+    created_at: DateTime<Utc>,
+) -> String {
+    let canonical = json!([
+        prev_hash,
+        event_type,
+        actor_id,
+        target_user_id,
+        ip,
+        user_agent,
+        request_id,
+        details,
+        created_at.to_rfc3339_opts(SecondsFormat::Micros, true),
+    ]);
+    hex::encode(Sha256::digest(canonical.to_string().as_bytes()))
+}
+
+fn event_hash(prev_hash: &str, event: &AuditEvent) -> String {
+    chain_hash(
+        prev_hash,
+        &event.event_type,
+        event.actor_id,
+        event.target_user_id,
+        event.ip.as_deref(),
+        event.user_agent.as_deref(),
+        event.request_id.as_deref(),
+        &event.details,
+        event.created_at,
+    )
+}
+
+/// Appends an event inside `tx`, so it commits or rolls back with the change it records
+pub async fn append_event(
+    tx: &mut Transaction<'_, Postgres>,
+    context: &AuditContext,
+    event_type: AuditEventType,
+    target_user_id: Option<i32>,
+    details: Value,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("SELECT pg_advisory_xact_lock($1)")
+        .bind(CHAIN_LOCK_KEY)
+        .execute(&mut **tx)
+        .await?;
+
+    let prev_hash = sqlx::query_scalar::<_, String>(
+        "SELECT hash FROM audit_events ORDER BY id DESC LIMIT 1",
+    )
+    .fetch_optional(&mut **tx)
+    .await?
+    .unwrap_or_else(|| GENESIS_HASH.to_string());
+
+    let created_at = Utc::now()
+        .duration_trunc(chrono::Duration::microseconds(1))
+        .unwrap_or_else(|_| Utc::now());
+    let hash = chain_hash(
+        &prev_hash,
+        event_type.as_str(),
+        context.actor_id,
+        target_user_id,
+        context.ip.as_deref(),
+        context.user_agent.as_deref(),
+        context.request_id.as_deref(),
+        &details,
+        created_at,
+    );
+
+    sqlx::query(
+        r#"
+        INSERT INTO audit_events
+            (event_type, actor_id, target_user_id, ip, user_agent, request_id, details,
+             created_at, prev_hash, hash)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        "#,
+    )
+    .bind(event_type.as_str())
+    .bind(context.actor_id)
+    .bind(target_user_id)
+    .bind(context.ip.as_deref())
+    .bind(context.user_agent.as_deref())
+    .bind(context.request_id.as_deref())
+    .bind(details)
+    .bind(created_at)
+    .bind(prev_hash)
+    .bind(hash)
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
+/// Records a standalone event. Failures are logged rather than returned, so
+/// the audit log never turns a completed action into an error response.
+pub async fn record_event(
+    pool: &Pool<Postgres>,
+    context: &AuditContext,
+    event_type: AuditEventType,
+    target_user_id: Option<i32>,
+    details: Value,
+) {
+    let result = async {
+        let mut tx = pool.begin().await?;
+        append_event(&mut tx, context, event_type, target_user_id, details).await?;
+        tx.commit().await
+    }
+    .await;
+
+    if let Err(e) = result {
+        tracing::error!(event_type = event_type.as_str(), error = %e, "Failed to record audit event");
+    }
+}
+
+fn push_filters(builder: &mut QueryBuilder<'_, Postgres>, filter: &AuditFilter) {
+    builder.push(" WHERE TRUE");
+    if let Some(event_type) = filter.event_type {
+        builder.push(" AND event_type = ").push_bind(event_type.as_str());
+    }
+    if let Some(actor_id) = filter.actor_id {
+        builder.push(" AND actor_id = ").push_bind(actor_id);
+    }
+    if let Some(target_user_id) = filter.target_user_id {
+        builder.push(" AND target_user_id = ").push_bind(target_user_id);
+    }
+    if let Some(from) = filter.from {
+        builder.push(" AND created_at >= ").push_bind(from);
+    }
+    if let Some(to) = filter.to {
+        builder.push(" AND created_at < ").push_bind(to);
+    }
+}
+
+/// Lists events matching `filter`, newest first, older than `before_id`
+pub async fn list_events(
+    pool: &Pool<Postgres>,
+    filter: &AuditFilter,
+    before_id: Option<i64>,
+    limit: i64,
+) -> Result<Vec<AuditEvent>, sqlx::Error> {
+    let mut builder = QueryBuilder::new("SELECT * FROM audit_events");
+    push_filters(&mut builder, filter);
+    if let Some(before_id) = before_id {
+        builder.push(" AND id < ").push_bind(before_id);
+    }
+    builder.push(" ORDER BY id DESC LIMIT ").push_bind(limit);
+    builder.build_query_as::<AuditEvent>().fetch_all(pool).await
+}
+
+/// Events matching `filter` in chain order, for export
+pub async fn export_events(
+    pool: &Pool<Postgres>,
+    filter: &AuditFilter,
+) -> Result<Vec<AuditEvent>, sqlx::Error> {
+    let mut builder = QueryBuilder::new("SELECT * FROM audit_events");
+    push_filters(&mut builder, filter);
+    builder
+        .push(" ORDER BY id ASC LIMIT ")
+        .push_bind(MAX_EXPORT_EVENTS);
+    builder.build_query_as::<AuditEvent>().fetch_all(pool).await
+}
+
+/// Walks the whole chain, re-computing every hash and link
+pub async fn verify_chain(pool: &Pool<Postgres>) -> Result<ChainVerification, sqlx::Error> {
+    let mut expected_prev = GENESIS_HASH.to_string();
+    let mut last_id = 0i64;
+    let mut checked = 0i64;
+
+    loop {
+        let batch = sqlx::query_as::<_, AuditEvent>(
+            "SELECT * FROM audit_events WHERE id > $1 ORDER BY id ASC LIMIT $2",
+        )
+        .bind(last_id)
+        .bind(VERIFY_BATCH_SIZE)
+        .fetch_all(pool)
+        .await?;
+        if batch.is_empty() {
+            break;
+        }
+
+        for event in batch {
+            let failure = if event.prev_hash != expected_prev {
+                Some("Event does not link to its predecessor")
+            } else if event.hash != event_hash(&event.prev_hash, &event) {
+                Some("Event does not match its hash")
+            } else {
+                None
+            };
+            if let Some(reason) = failure {
+                tracing::error!(event_id = event.id, %reason, "Audit chain broken");
+                return Ok(ChainVerification {
+                    valid: false,
+                    events_checked: checked,
+                    first_invalid_id: Some(event.id),
+                    reason: Some(reason.to_string()),
+                });
+            }
+            checked += 1;
+            last_id = event.id;
+            expected_prev = event.hash;
+        }
+    }
+
+    Ok(ChainVerification {
+        valid: true,
+        events_checked: checked,
+        first_invalid_id: None,
+        reason: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> AuditContext {
+        AuditContext {
+            actor_id: Some(7),
+            ip: Some("203.0.113.9".to_string()),
+            user_agent: Some("Mozilla/5.0".to_string()),
+            request_id: Some("req-1".to_string()),
+        }
+    }
+
+    /// Appends events whose details JSONB stores differently from how they
+    /// are serialized: key order, nesting, escapes and non-integer numbers
+    async fn append_events(pool: &Pool<Postgres>) {
+        record_event(pool, &context(), AuditEventType::LoginSucceeded, Some(7), json!({})).await;
+        record_event(
+            pool,
+            &context(),
+            AuditEventType::ProfileUpdated,
+            Some(7),
+            json!({ "zeta": 1.5, "a": { "nested": [1, "two", null] }, "note": "café, \"quoted\"\nline" }),
+        )
+        .await;
+        record_event(pool, &AuditContext::default(), AuditEventType::LoginFailed, None, json!({ "email": "x@example.com" }))
+            .await;
+    }
+
+    async fn tamper(pool: &Pool<Postgres>, statement: &str) {
+        let mut tx = pool.begin().await.unwrap();
+        sqlx::query("ALTER TABLE audit_events DISABLE TRIGGER audit_events_append_only")
+            .execute(&mut *tx)
+            .await
+            .unwrap();
+        sqlx::query(statement).execute(&mut *tx).await.unwrap();
+        sqlx::query("ALTER TABLE audit_events ENABLE TRIGGER audit_events_append_only")
+            .execute(&mut *tx)
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+    }
+
+    async fn event_ids(pool: &Pool<Postgres>) -> Vec<i64> {
+        sqlx::query_scalar("SELECT id FROM audit_events ORDER BY id")
+            .fetch_all(pool)
+            .await
+            .unwrap()
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn appended_events_form_a_valid_chain(pool: Pool<Postgres>) {
+        append_events(&pool).await;
+
+        let verification = verify_chain(&pool).await.unwrap();
+        assert!(verification.valid, "{:?}", verification);
+        assert_eq!(verification.events_checked, 3);
+        assert_eq!(verification.first_invalid_id, None);
+
+        let events = export_events(&pool, &AuditFilter::default()).await.unwrap();
+        assert_eq!(events[0].prev_hash, GENESIS_HASH);
+        assert_eq!(events[1].prev_hash, events[0].hash);
+        assert_eq!(events[1].details["note"], "café, \"quoted\"\nline");
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn events_cannot_be_changed(pool: Pool<Postgres>) {
+        append_events(&pool).await;
+
+        let result = sqlx::query("UPDATE audit_events SET details = '{}'").execute(&pool).await;
+        assert!(result.is_err());
+        let result = sqlx::query("DELETE FROM audit_events").execute(&pool).await;
+        assert!(result.is_err());
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn edited_events_break_the_chain(pool: Pool<Postgres>) {
+        append_events(&pool).await;
+        let ids = event_ids(&pool).await;
+        tamper(
+            &pool,
+            &format!("UPDATE audit_events SET details = details || '{{\"zeta\": 2}}' WHERE id = {}", ids[1]),
+        )
+        .await;
+
+        let verification = verify_chain(&pool).await.unwrap();
+        assert!(!verification.valid);
+        assert_eq!(verification.events_checked, 1);
+        assert_eq!(verification.first_invalid_id, Some(ids[1]));
+        assert_eq!(verification.reason.as_deref(), Some("Event does not match its hash"));
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn removed_events_break_the_chain(pool: Pool<Postgres>) {
+        append_events(&pool).await;
+        let ids = event_ids(&pool).await;
+        tamper(&pool, &format!("DELETE FROM audit_events WHERE id = {}", ids[1])).await;
+
+        let verification = verify_chain(&pool).await.unwrap();
+        assert!(!verification.valid);
+        assert_eq!(verification.first_invalid_id, Some(ids[2]));
+        assert_eq!(verification.reason.as_deref(), Some("Event does not link to its predecessor"));
+    }
+}
@@ -0,0 +1,133 @@
+use chrono::{Duration, Utc};
+use serde_json::json;
+use sqlx::{Pool, Postgres};
+use uuid::Uuid;
+
+use crate::{
+    db::audit::append_event,
+    models::audit::{AuditContext, AuditEventType},
+};
+
+/// How long a verification link stays valid
+pub const EMAIL_VERIFICATION_TTL_HOURS: i64 = 24;
+
+/// Issues a verification token for the user's current email address
+pub async fn create_email_verification(pool: &Pool<Postgres>, user_id: i32) -> Result<Uuid, sqlx::Error> {
+    tracing::info!(user_id, "Issuing email verification");
+    sqlx::query_scalar::<_, Uuid>(
+        r#"
+        INSERT INTO email_verifications (token, user_id, email, expires_at)
+        SELECT $1, id, email, $3 FROM users WHERE id = $2
+        RETURNING token
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(user_id)
+    .bind(Utc::now() + Duration::hours(EMAIL_VERIFICATION_TTL_HOURS))
+    .fetch_one(pool)
+    .await
+}
+
+/// Redeems a verification token, marking the user's email verified.
+///
+/// Returns the user id, or `None` when the token is unknown, expired, already
+/// used or was mailed to an address the user has since changed.
+pub async fn complete_email_verification(
+    pool: &Pool<Postgres>,
+    context: &AuditContext,
+    token: Uuid,
+) -> Result<Option<i32>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let verification = sqlx::query_as::<_, (i32, String)>(
+        r#"
+        UPDATE email_verifications SET used_at = CURRENT_TIMESTAMP
+        WHERE token = $1 AND used_at IS NULL AND expires_at > CURRENT_TIMESTAMP
+        RETURNING user_id, email
+        "#,
+    )
+    .bind(token)
+    .fetch_optional(&mut *tx)
+    .await?;
+    let Some((user_id, email)) = verification else {
+        return Ok(None);
+    };
+
+    let verified = sqlx::query("UPDATE users SET email_verified = TRUE WHERE id = $1 AND email = $2")
+        .bind(user_id)
+        .bind(&email)
+        .execute(&mut *tx)
+        .await?;
+    if verified.rows_affected() == 0 {
+        return Ok(None);
+    }
+    tracing::info!(user_id, "Email verified");
+
+    let context = AuditContext {
+        actor_id: Some(user_id),
+        ..context.clone()
+    };
+    append_event(
+        &mut tx,
+        &context,
+        AuditEventType::EmailVerified,
+        Some(user_id),
+        json!({ "email": email }),
+    )
+    .await?;
+
+    tx.commit().await?;
+    Ok(Some(user_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::queries::{get_user_by_id, update_user_profile},
+        test_support::insert_user,
+    };
+
+    #[sqlx::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn changing_the_email_voids_its_verification(pool: Pool<Postgres>) {
+        let user = insert_user(&pool, "user@example.com", "User").await;
+        let stale = create_email_verification(&pool, user.id).await.unwrap();
+        let token = create_email_verification(&pool, user.id).await.unwrap();
+        let context = AuditContext::default();
+
+        assert_eq!(complete_email_verification(&pool, &context, token).await.unwrap(), Some(user.id));
+        assert!(get_user_by_id(&pool, user.id).await.unwrap().email_verified);
+
+        // Other profile changes keep the address verified
+        let updated = update_user_profile(&pool, user.id, Some("Ada"), None, Some("user@example.com"), None, None)
+            .await
+            .unwrap();
+        assert!(updated.email_verified);
+
+        let updated = update_user_profile(&pool, user.id, None, None, Some("new@example.com"), None, None)
+            .await
+            .unwrap();
+        assert!(!updated.email_verified);
+        // A link mailed to the old address does not verify the new one
+        assert_eq!(complete_email_verification(&pool, &context, stale).await.unwrap(), None);
+        assert!(!get_user_by_id(&pool, user.id).await.unwrap().email_verified);
+
+        let token = create_email_verification(&pool, user.id).await.unwrap();
+        assert_eq!(complete_email_verification(&pool, &context, token).await.unwrap(), Some(user.id));
+        assert!(get_user_by_id(&pool, user.id).await.unwrap().email_verified);
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn expired_links_do_not_verify(pool: Pool<Postgres>) {
+        let user = insert_user(&pool, "user@example.com", "User").await;
+        let token = create_email_verification(&pool, user.id).await.unwrap();
+        sqlx::query("UPDATE email_verifications SET expires_at = CURRENT_TIMESTAMP - INTERVAL '1 minute'")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        assert_eq!(complete_email_verification(&pool, &AuditContext::default(), token).await.unwrap(), None);
+        assert!(!get_user_by_id(&pool, user.id).await.unwrap().email_verified);
+    }
+}
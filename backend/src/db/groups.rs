@@ -0,0 +1,327 @@
+use sqlx::{Pool, Postgres};
+
+use crate::models::group::{Group, GroupMember};
+
+/// Upper bound on nesting depth, in levels below the outermost group; also
+/// guards the recursive queries against cycles
+pub const MAX_GROUP_DEPTH: i32 = 32;
+
+/// Outcome of nesting a group inside another
+#[derive(Debug, PartialEq, Eq)]
+pub enum SubgroupChange {
+    Applied,
+    /// Refused, as the parent is the child or nested inside it
+    Cycle,
+    /// Refused, as some chain of nested groups would exceed `MAX_GROUP_DEPTH`
+    TooDeep,
+}
+
+pub async fn list_groups(pool: &Pool<Postgres>) -> Result<Vec<Group>, sqlx::Error> {
+    sqlx::query_as::<_, Group>("SELECT * FROM groups ORDER BY name")
+        .fetch_all(pool)
+        .await
+}
+
+pub async fn get_group(pool: &Pool<Postgres>, group_id: i32) -> Result<Option<Group>, sqlx::Error> {
+    sqlx::query_as::<_, Group>("SELECT * FROM groups WHERE id = $1")
+        .bind(group_id)
+        .fetch_optional(pool)
+        .await
+}
+
+pub async fn create_group(
+    pool: &Pool<Postgres>,
+    name: &str,
+    description: Option<&str>,
+) -> Result<Group, sqlx::Error> {
+    tracing::info!(%name, "Creating group");
+    sqlx::query_as::<_, Group>(
+        "INSERT INTO groups (name, description) VALUES ($1, $2) RETURNING *",
+    )
+    .bind(name)
+    .bind(description)
+    .fetch_one(pool)
+    .await
+}
+
+pub async fn delete_group(pool: &Pool<Postgres>, group_id: i32) -> Result<bool, sqlx::Error> {
+    tracing::info!(group_id, "Deleting group");
+    let result = sqlx::query("DELETE FROM groups WHERE id = $1")
+        .bind(group_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn list_subgroups(pool: &Pool<Postgres>, group_id: i32) -> Result<Vec<Group>, sqlx::Error> {
+    sqlx::query_as::<_, Group>(
+        r#"
+        SELECT g.* FROM groups g
+        JOIN group_subgroups s ON s.child_group_id = g.id
+        WHERE s.parent_group_id = $1
+        ORDER BY g.name
+        "#,
+    )
+    .bind(group_id)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn list_group_roles(pool: &Pool<Postgres>, group_id: i32) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar::<_, String>("SELECT role FROM group_roles WHERE group_id = $1 ORDER BY role")
+        .bind(group_id)
+        .fetch_all(pool)
+        .await
+}
+
+pub async fn list_group_permissions(
+    pool: &Pool<Postgres>,
+    group_id: i32,
+) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar::<_, String>(
+        "SELECT permission FROM group_permissions WHERE group_id = $1 ORDER BY permission",
+    )
+    .bind(group_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// Users in the group or any group nested inside it
+pub async fn list_transitive_members(
+    pool: &Pool<Postgres>,
+    group_id: i32,
+) -> Result<Vec<GroupMember>, sqlx::Error> {
+    sqlx::query_as::<_, GroupMember>(
+        r#"
+        WITH RECURSIVE descendants AS (
+            SELECT $1::INTEGER AS group_id, 0 AS depth
+            UNION
+            SELECT s.child_group_id, d.depth + 1
+            FROM group_subgroups s
+            JOIN descendants d ON s.parent_group_id = d.group_id
+            WHERE d.depth < $2
+        )
+        SELECT u.id, u.firstname, u.lastname, u.email,
+               BOOL_OR(gm.group_id = $1) AS direct
+        FROM group_members gm
+        JOIN descendants d ON d.group_id = gm.group_id
+        JOIN users u ON u.id = gm.user_id
+        GROUP BY u.id, u.firstname, u.lastname, u.email
+        ORDER BY u.lastname, u.firstname
+        "#,
+    )
+    .bind(group_id)
+    .bind(MAX_GROUP_DEPTH)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn add_group_user(pool: &Pool<Postgres>, group_id: i32, user_id: i32) -> Result<(), sqlx::Error> {
+    tracing::info!(user_id, group_id, "Adding user to group");
+    sqlx::query("INSERT INTO group_members (group_id, user_id) VALUES ($1, $2) ON CONFLICT DO NOTHING")
+        .bind(group_id)
+        .bind(user_id)
+        .execute(pool)
+        .await
+        .map(|_| ())
+}
+
+pub async fn remove_group_user(pool: &Pool<Postgres>, group_id: i32, user_id: i32) -> Result<bool, sqlx::Error> {
+    tracing::info!(user_id, group_id, "Removing user from group");
+    let result = sqlx::query("DELETE FROM group_members WHERE group_id = $1 AND user_id = $2")
+        .bind(group_id)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Nests `child_id` inside `parent_id` unless that would create a cycle or
+/// exceed `MAX_GROUP_DEPTH`. Nesting changes are serialized, so concurrent
+/// changes cannot combine into a cycle either check would have refused.
+pub async fn add_subgroup(
+    pool: &Pool<Postgres>,
+    parent_id: i32,
+    child_id: i32,
+) -> Result<SubgroupChange, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("LOCK TABLE group_subgroups IN SHARE ROW EXCLUSIVE MODE")
+        .execute(&mut *tx)
+        .await?;
+
+    // Levels above the parent, plus the new one, plus levels below the child
+    let (cycle, depth) = sqlx::query_as::<_, (bool, i32)>(
+        r#"
+        WITH RECURSIVE descendants AS (
+            SELECT $2::INTEGER AS group_id, 0 AS depth
+            UNION
+            SELECT s.child_group_id, d.depth + 1
+            FROM group_subgroups s
+            JOIN descendants d ON s.parent_group_id = d.group_id
+            WHERE d.depth < $3
+        ),
+        ancestors AS (
+            SELECT $1::INTEGER AS group_id, 0 AS depth
+            UNION
+            SELECT s.parent_group_id, a.depth + 1
+            FROM group_subgroups s
+            JOIN ancestors a ON s.child_group_id = a.group_id
+            WHERE a.depth < $3
+        )
+        SELECT EXISTS(SELECT 1 FROM descendants WHERE group_id = $1),
+               (SELECT MAX(depth) FROM ancestors) + 1 + (SELECT MAX(depth) FROM descendants)
+        "#,
+    )
+    .bind(parent_id)
+    .bind(child_id)
+    .bind(MAX_GROUP_DEPTH)
+    .fetch_one(&mut *tx)
+    .await?;
+    if cycle {
+        return Ok(SubgroupChange::Cycle);
+    }
+    if depth > MAX_GROUP_DEPTH {
+        return Ok(SubgroupChange::TooDeep);
+    }
+
+    tracing::info!(child_id, parent_id, "Nesting group");
+    sqlx::query(
+        "INSERT INTO group_subgroups (parent_group_id, child_group_id) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+    )
+    .bind(parent_id)
+    .bind(child_id)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(SubgroupChange::Applied)
+}
+
+pub async fn remove_subgroup(pool: &Pool<Postgres>, parent_id: i32, child_id: i32) -> Result<bool, sqlx::Error> {
+    tracing::info!(child_id, parent_id, "Removing nested group");
+    let result = sqlx::query(
+        "DELETE FROM group_subgroups WHERE parent_group_id = $1 AND child_group_id = $2",
+    )
+    .bind(parent_id)
+    .bind(child_id)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn grant_group_role(pool: &Pool<Postgres>, group_id: i32, role: &str) -> Result<(), sqlx::Error> {
+    tracing::info!(%role, group_id, "Granting role to group");
+    sqlx::query("INSERT INTO group_roles (group_id, role) VALUES ($1, $2) ON CONFLICT DO NOTHING")
+        .bind(group_id)
+        .bind(role)
+        .execute(pool)
+        .await
+        .map(|_| ())
+}
+
+pub async fn revoke_group_role(pool: &Pool<Postgres>, group_id: i32, role: &str) -> Result<bool, sqlx::Error> {
+    tracing::info!(%role, group_id, "Revoking role from group");
+    let result = sqlx::query("DELETE FROM group_roles WHERE group_id = $1 AND role = $2")
+        .bind(group_id)
+        .bind(role)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn grant_group_permission(
+    pool: &Pool<Postgres>,
+    group_id: i32,
+    permission: &str,
+) -> Result<(), sqlx::Error> {
+    tracing::info!(%permission, group_id, "Granting permission to group");
+    sqlx::query(
+        "INSERT INTO group_permissions (group_id, permission) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+    )
+    .bind(group_id)
+    .bind(permission)
+    .execute(pool)
+    .await
+    .map(|_| ())
+}
+
+pub async fn revoke_group_permission(
+    pool: &Pool<Postgres>,
+    group_id: i32,
+    permission: &str,
+) -> Result<bool, sqlx::Error> {
+    tracing::info!(%permission, group_id, "Revoking permission from group");
+    let result = sqlx::query("DELETE FROM group_permissions WHERE group_id = $1 AND permission = $2")
+        .bind(group_id)
+        .bind(permission)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{db::roles::get_user_permissions, test_support::insert_user};
+
+    /// Creates `len` groups, each nested inside the one before it
+    async fn chain(pool: &Pool<Postgres>, prefix: &str, len: usize) -> Vec<Group> {
+        let mut groups: Vec<Group> = Vec::with_capacity(len);
+        for i in 0..len {
+            let group = create_group(pool, &format!("{}-{}", prefix, i), None).await.unwrap();
+            if let Some(parent) = groups.last() {
+                assert_eq!(add_subgroup(pool, parent.id, group.id).await.unwrap(), SubgroupChange::Applied);
+            }
+            groups.push(group);
+        }
+        groups
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn permissions_are_inherited_through_nested_groups(pool: Pool<Postgres>) {
+        let user = insert_user(&pool, "user@example.com", "User").await;
+        let groups = chain(&pool, "group", 3).await;
+        add_group_user(&pool, groups[2].id, user.id).await.unwrap();
+        grant_group_permission(&pool, groups[0].id, "reports:read").await.unwrap();
+        grant_group_role(&pool, groups[1].id, "Moderator").await.unwrap();
+
+        let permissions = get_user_permissions(&pool, user.id).await.unwrap();
+        assert!(permissions.contains("reports:read"));
+        assert!(permissions.contains("users:read"));
+        assert!(!permissions.contains("admin:access"));
+
+        remove_subgroup(&pool, groups[1].id, groups[2].id).await.unwrap();
+        let permissions = get_user_permissions(&pool, user.id).await.unwrap();
+        assert!(!permissions.contains("reports:read"));
+        assert!(!permissions.contains("users:read"));
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn nesting_cycles_are_refused(pool: Pool<Postgres>) {
+        let groups = chain(&pool, "group", 3).await;
+
+        assert_eq!(add_subgroup(&pool, groups[2].id, groups[0].id).await.unwrap(), SubgroupChange::Cycle);
+        assert_eq!(add_subgroup(&pool, groups[1].id, groups[1].id).await.unwrap(), SubgroupChange::Cycle);
+        let members = list_subgroups(&pool, groups[2].id).await.unwrap();
+        assert!(members.is_empty());
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn nesting_depth_is_bounded(pool: Pool<Postgres>) {
+        let groups = chain(&pool, "group", MAX_GROUP_DEPTH as usize + 1).await;
+        let extra = create_group(&pool, "extra", None).await.unwrap();
+
+        let last = groups.last().unwrap();
+        assert_eq!(add_subgroup(&pool, last.id, extra.id).await.unwrap(), SubgroupChange::TooDeep);
+        assert_eq!(add_subgroup(&pool, extra.id, groups[0].id).await.unwrap(), SubgroupChange::TooDeep);
+        // Joining two shorter chains is refused once their combined depth is too large
+        let tail = chain(&pool, "tail", 2).await;
+        assert_eq!(add_subgroup(&pool, groups[1].id, tail[0].id).await.unwrap(), SubgroupChange::Applied);
+        assert_eq!(
+            add_subgroup(&pool, groups[MAX_GROUP_DEPTH as usize - 1].id, tail[0].id).await.unwrap(),
+            SubgroupChange::TooDeep
+        );
+    }
+}
@@ -1 +1,11 @@
-pub mod queries;
\ No newline at end of file
+pub mod audit;
+pub mod email_verifications;
+pub mod groups;
+pub mod organizations;
+pub mod password_resets;
+pub mod photo_uploads;
+pub mod queries;
+pub mod relations;
+pub mod roles;
+pub mod sessions;
+pub mod users;
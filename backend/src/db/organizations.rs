@@ -0,0 +1,409 @@
+use sqlx::{Pool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::models::organization::{
+    Invitation, Member, Organization, OrganizationMembership, OrgRole,
+};
+
+/// How long an invitation stays valid
+pub const INVITATION_TTL_DAYS: i32 = 7;
+
+/// Creates an organization and makes `owner_id` its first Owner
+pub async fn create_organization(
+    pool: &Pool<Postgres>,
+    name: &str,
+    slug: &str,
+    owner_id: i32,
+) -> Result<Organization, sqlx::Error> {
+    tracing::debug!(%slug, "Creating organization");
+    let mut tx = pool.begin().await?;
+
+    let organization = sqlx::query_as::<_, Organization>(
+        r#"
+        INSERT INTO organizations (name, slug)
+        VALUES ($1, $2)
+        RETURNING id, name, slug, created_at
+        "#,
+    )
+    .bind(name)
+    .bind(slug)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        "INSERT INTO organization_members (organization_id, user_id, role) VALUES ($1, $2, $3)",
+    )
+    .bind(organization.id)
+    .bind(owner_id)
+    .bind(OrgRole::Owner.to_string())
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    tracing::info!(organization_id = organization.id, slug = %organization.slug, "Organization created");
+    Ok(organization)
+}
+
+pub async fn list_user_organizations(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+) -> Result<Vec<OrganizationMembership>, sqlx::Error> {
+    sqlx::query_as::<_, OrganizationMembership>(
+        r#"
+        SELECT o.id AS organization_id, o.name, o.slug, m.role, m.joined_at
+        FROM organization_members m
+        JOIN organizations o ON o.id = m.organization_id
+        WHERE m.user_id = $1
+        ORDER BY o.name
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// The user's role in the organization, or `None` if they are not a member
+pub async fn get_membership_role(
+    pool: &Pool<Postgres>,
+    organization_id: i32,
+    user_id: i32,
+) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query_scalar::<_, String>(
+        "SELECT role FROM organization_members WHERE organization_id = $1 AND user_id = $2",
+    )
+    .bind(organization_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await
+}
+
+pub async fn list_members(
+    pool: &Pool<Postgres>,
+    organization_id: i32,
+) -> Result<Vec<Member>, sqlx::Error> {
+    sqlx::query_as::<_, Member>(
+        r#"
+        SELECT u.id AS user_id, u.firstname, u.lastname, u.email, m.role, m.joined_at
+        FROM organization_members m
+        JOIN users u ON u.id = m.user_id
+        WHERE m.organization_id = $1
+        ORDER BY u.lastname, u.firstname
+        "#,
+    )
+    .bind(organization_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// Outcome of changing or removing a membership
+#[derive(Debug, PartialEq, Eq)]
+pub enum MembershipChange {
+    /// Carries the member's role before the change
+    Applied(OrgRole),
+    NotMember,
+    /// Refused, as only owners may grant or revoke the Owner role
+    OwnerRequired,
+    /// Refused, as the organization would be left without an Owner
+    LastOwner,
+}
+
+/// Locks the Owner memberships of the organizations for the rest of `tx` and
+/// returns them as `(organization_id, user_id)`. Concurrent owner changes queue
+/// on these locks, so each one counts the owners the previous one left.
+pub async fn lock_owners(
+    tx: &mut Transaction<'_, Postgres>,
+    organization_ids: &[i32],
+) -> Result<Vec<(i32, i32)>, sqlx::Error> {
+    sqlx::query_as::<_, (i32, i32)>(
+        r#"
+        SELECT organization_id, user_id FROM organization_members
+        WHERE organization_id = ANY($1) AND role = 'Owner'
+        ORDER BY organization_id, user_id
+        FOR UPDATE
+        "#,
+    )
+    .bind(organization_ids)
+    .fetch_all(&mut **tx)
+    .await
+}
+
+/// Sets the member's role, or removes them when `role` is `None`, on behalf
+/// of `actor_id`. Only owners may change a role to or from Owner, and no
+/// change may leave the organization without an Owner. Both are checked
+/// against the memberships locked in the same transaction.
+async fn change_membership(
+    pool: &Pool<Postgres>,
+    organization_id: i32,
+    actor_id: i32,
+    user_id: i32,
+    role: Option<OrgRole>,
+) -> Result<MembershipChange, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let owners = lock_owners(&mut tx, &[organization_id]).await?;
+
+    let current = sqlx::query_scalar::<_, String>(
+        "SELECT role FROM organization_members WHERE organization_id = $1 AND user_id = $2 FOR UPDATE",
+    )
+    .bind(organization_id)
+    .bind(user_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+    let Some(current) = current.and_then(|role| role.parse::<OrgRole>().ok()) else {
+        return Ok(MembershipChange::NotMember);
+    };
+
+    let touches_owner = current == OrgRole::Owner || role == Some(OrgRole::Owner);
+    let actor_is_owner = owners.iter().any(|(_, owner)| *owner == actor_id);
+    if touches_owner && !actor_is_owner {
+        return Ok(MembershipChange::OwnerRequired);
+    }
+    let demotes_owner = current == OrgRole::Owner && role != Some(OrgRole::Owner);
+    if demotes_owner && owners.len() <= 1 {
+        return Ok(MembershipChange::LastOwner);
+    }
+
+    match role {
+        Some(role) => {
+            tracing::info!(user_id, organization_id, %role, "Changing organization role");
+            sqlx::query(
+                "UPDATE organization_members SET role = $3 WHERE organization_id = $1 AND user_id = $2",
+            )
+            .bind(organization_id)
+            .bind(user_id)
+            .bind(role.to_string())
+            .execute(&mut *tx)
+            .await?;
+        }
+        None => {
+            tracing::info!(user_id, organization_id, "Removing user from organization");
+            sqlx::query("DELETE FROM organization_members WHERE organization_id = $1 AND user_id = $2")
+                .bind(organization_id)
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+    }
+
+    tx.commit().await?;
+    Ok(MembershipChange::Applied(current))
+}
+
+pub async fn update_member_role(
+    pool: &Pool<Postgres>,
+    organization_id: i32,
+    actor_id: i32,
+    user_id: i32,
+    role: OrgRole,
+) -> Result<MembershipChange, sqlx::Error> {
+    change_membership(pool, organization_id, actor_id, user_id, Some(role)).await
+}
+
+pub async fn remove_member(
+    pool: &Pool<Postgres>,
+    organization_id: i32,
+    actor_id: i32,
+    user_id: i32,
+) -> Result<MembershipChange, sqlx::Error> {
+    change_membership(pool, organization_id, actor_id, user_id, None).await
+}
+
+pub async fn get_organization_name(
+    pool: &Pool<Postgres>,
+    organization_id: i32,
+) -> Result<String, sqlx::Error> {
+    sqlx::query_scalar::<_, String>("SELECT name FROM organizations WHERE id = $1")
+        .bind(organization_id)
+        .fetch_one(pool)
+        .await
+}
+
+pub async fn create_invitation(
+    pool: &Pool<Postgres>,
+    organization_id: i32,
+    email: &str,
+    role: OrgRole,
+    invited_by: i32,
+) -> Result<Invitation, sqlx::Error> {
+    tracing::info!(organization_id, "Creating invitation");
+    sqlx::query_as::<_, Invitation>(
+        r#"
+        INSERT INTO organization_invitations (organization_id, email, role, token, invited_by, expires_at)
+        VALUES ($1, $2, $3, $4, $5, CURRENT_TIMESTAMP + make_interval(days => $6))
+        RETURNING *
+        "#,
+    )
+    .bind(organization_id)
+    .bind(email)
+    .bind(role.to_string())
+    .bind(Uuid::new_v4())
+    .bind(invited_by)
+    .bind(INVITATION_TTL_DAYS)
+    .fetch_one(pool)
+    .await
+}
+
+pub async fn list_pending_invitations(
+    pool: &Pool<Postgres>,
+    organization_id: i32,
+) -> Result<Vec<Invitation>, sqlx::Error> {
+    sqlx::query_as::<_, Invitation>(
+        r#"
+        SELECT * FROM organization_invitations
+        WHERE organization_id = $1 AND accepted_at IS NULL AND expires_at > CURRENT_TIMESTAMP
+        ORDER BY created_at DESC
+        "#,
+    )
+    .bind(organization_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// Accepts a pending invitation addressed to `email`, adding `user_id` as a member.
+///
+/// Returns the joined organization's id, or `None` if no matching valid invitation exists.
+pub async fn accept_invitation(
+    pool: &Pool<Postgres>,
+    token: Uuid,
+    user_id: i32,
+    email: &str,
+) -> Result<Option<i32>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let invitation = sqlx::query_as::<_, Invitation>(
+        r#"
+        UPDATE organization_invitations
+        SET accepted_at = CURRENT_TIMESTAMP
+        WHERE token = $1
+          AND LOWER(email) = LOWER($2)
+          AND accepted_at IS NULL
+          AND expires_at > CURRENT_TIMESTAMP
+        RETURNING *
+        "#,
+    )
+    .bind(token)
+    .bind(email)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some(invitation) = invitation else {
+        return Ok(None);
+    };
+
+    sqlx::query(
+        r#"
+        INSERT INTO organization_members (organization_id, user_id, role)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (organization_id, user_id) DO NOTHING
+        "#,
+    )
+    .bind(invitation.organization_id)
+    .bind(user_id)
+    .bind(&invitation.role)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    tracing::info!(user_id, organization_id = invitation.organization_id, "User joined organization");
+    Ok(Some(invitation.organization_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::users::{delete_user, UserDeletion},
+        models::audit::AuditContext,
+        test_support::insert_user,
+    };
+
+    #[sqlx::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn concurrent_demotions_keep_an_owner(pool: Pool<Postgres>) {
+        let first = insert_user(&pool, "first@example.com", "User").await;
+        let second = insert_user(&pool, "second@example.com", "User").await;
+        let organization = create_organization(&pool, "Acme", "acme", first.id).await.unwrap();
+        sqlx::query("INSERT INTO organization_members (organization_id, user_id, role) VALUES ($1, $2, 'Owner')")
+            .bind(organization.id)
+            .bind(second.id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let (a, b) = tokio::join!(
+            update_member_role(&pool, organization.id, first.id, first.id, OrgRole::Admin),
+            remove_member(&pool, organization.id, second.id, second.id),
+        );
+        let mut outcomes = [a.unwrap(), b.unwrap()];
+        outcomes.sort_by_key(|outcome| *outcome != MembershipChange::LastOwner);
+        assert_eq!(
+            outcomes,
+            [MembershipChange::LastOwner, MembershipChange::Applied(OrgRole::Owner)]
+        );
+
+        let owners: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM organization_members WHERE organization_id = $1 AND role = 'Owner'",
+        )
+        .bind(organization.id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(owners, 1);
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn only_owners_change_owner_memberships(pool: Pool<Postgres>) {
+        let owner = insert_user(&pool, "owner@example.com", "User").await;
+        let admin = insert_user(&pool, "admin@example.com", "User").await;
+        let member = insert_user(&pool, "member@example.com", "User").await;
+        let organization = create_organization(&pool, "Acme", "acme", owner.id).await.unwrap();
+        for (user, role) in [(&admin, "Admin"), (&member, "Member")] {
+            sqlx::query("INSERT INTO organization_members (organization_id, user_id, role) VALUES ($1, $2, $3)")
+                .bind(organization.id)
+                .bind(user.id)
+                .bind(role)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+
+        // The admin looked the member up before an owner promoted them
+        let change = update_member_role(&pool, organization.id, owner.id, member.id, OrgRole::Owner).await.unwrap();
+        assert_eq!(change, MembershipChange::Applied(OrgRole::Member));
+        let change = remove_member(&pool, organization.id, admin.id, member.id).await.unwrap();
+        assert_eq!(change, MembershipChange::OwnerRequired);
+        let change = update_member_role(&pool, organization.id, admin.id, member.id, OrgRole::Member).await.unwrap();
+        assert_eq!(change, MembershipChange::OwnerRequired);
+        let change = update_member_role(&pool, organization.id, admin.id, admin.id, OrgRole::Owner).await.unwrap();
+        assert_eq!(change, MembershipChange::OwnerRequired);
+        assert_eq!(
+            get_membership_role(&pool, organization.id, member.id).await.unwrap().as_deref(),
+            Some("Owner")
+        );
+
+        let change = update_member_role(&pool, organization.id, owner.id, member.id, OrgRole::Admin).await.unwrap();
+        assert_eq!(change, MembershipChange::Applied(OrgRole::Owner));
+        let change = remove_member(&pool, organization.id, admin.id, member.id).await.unwrap();
+        assert_eq!(change, MembershipChange::Applied(OrgRole::Admin));
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn only_owner_cannot_be_deleted(pool: Pool<Postgres>) {
+        let owner = insert_user(&pool, "owner@example.com", "User").await;
+        let organization = create_organization(&pool, "Acme", "acme", owner.id).await.unwrap();
+
+        let deletion = delete_user(&pool, &AuditContext::default(), owner.id).await.unwrap();
+        assert_eq!(deletion, UserDeletion::LastOwner(vec![organization.id]));
+
+        let member = insert_user(&pool, "member@example.com", "User").await;
+        sqlx::query("INSERT INTO organization_members (organization_id, user_id, role) VALUES ($1, $2, 'Owner')")
+            .bind(organization.id)
+            .bind(member.id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let deletion = delete_user(&pool, &AuditContext::default(), owner.id).await.unwrap();
+        assert_eq!(deletion, UserDeletion::Deleted);
+    }
+}
@@ -0,0 +1,124 @@
+use chrono::{Duration, Utc};
+use serde_json::json;
+use sqlx::{Pool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::{
+    db::audit::append_event,
+    models::audit::{AuditContext, AuditEventType},
+    telemetry::metrics::time_password,
+};
+
+/// How long a reset link stays valid
+pub const PASSWORD_RESET_TTL_MINUTES: i64 = 60;
+
+/// Issues a reset token for the user inside `tx`
+pub async fn create_password_reset(
+    tx: &mut Transaction<'_, Postgres>,
+    user_id: i32,
+) -> Result<Uuid, sqlx::Error> {
+    let token = Uuid::new_v4();
+    tracing::info!(user_id, "Issuing password reset");
+    sqlx::query("INSERT INTO password_resets (token, user_id, expires_at) VALUES ($1, $2, $3)")
+        .bind(token)
+        .bind(user_id)
+        .bind(Utc::now() + Duration::minutes(PASSWORD_RESET_TTL_MINUTES))
+        .execute(&mut **tx)
+        .await?;
+    Ok(token)
+}
+
+/// Issues a reset token unless the user still holds an unused, unexpired one
+pub async fn renew_password_reset(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+) -> Result<Option<Uuid>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    // Serialises concurrent renewals for the same user
+    sqlx::query("SELECT id FROM users WHERE id = $1 FOR UPDATE")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    let pending = sqlx::query_scalar::<_, bool>(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM password_resets
+            WHERE user_id = $1 AND used_at IS NULL AND expires_at > CURRENT_TIMESTAMP
+        )
+        "#,
+    )
+    .bind(user_id)
+    .fetch_one(&mut *tx)
+    .await?;
+    if pending {
+        return Ok(None);
+    }
+
+    let token = create_password_reset(&mut tx, user_id).await?;
+    tx.commit().await?;
+    Ok(Some(token))
+}
+
+/// Redeems a reset token: sets the new password, lifts the login block and
+/// voids the user's other reset tokens.
+///
+/// Returns the user id, or `None` when the token is unknown, expired or already used.
+pub async fn complete_password_reset(
+    pool: &Pool<Postgres>,
+    context: &AuditContext,
+    token: Uuid,
+    password: &str,
+) -> Result<Option<i32>, sqlx::Error> {
+    let hashed = time_password("hash", || bcrypt::hash(password.as_bytes(), bcrypt::DEFAULT_COST))
+        .map_err(|e| sqlx::Error::Protocol(format!("Failed to hash password: {}", e)))?;
+
+    let mut tx = pool.begin().await?;
+    let user_id = sqlx::query_scalar::<_, i32>(
+        r#"
+        UPDATE password_resets SET used_at = CURRENT_TIMESTAMP
+        WHERE token = $1 AND used_at IS NULL AND expires_at > CURRENT_TIMESTAMP
+        RETURNING user_id
+        "#,
+    )
+    .bind(token)
+    .fetch_optional(&mut *tx)
+    .await?;
+    let Some(user_id) = user_id else {
+        return Ok(None);
+    };
+    tracing::info!(user_id, "Completing password reset");
+
+    sqlx::query(
+        r#"
+        UPDATE users
+        SET password = $2, password_reset_required = FALSE, must_change_password = FALSE
+        WHERE id = $1
+        "#,
+    )
+    .bind(user_id)
+    .bind(hashed)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        "UPDATE password_resets SET used_at = CURRENT_TIMESTAMP WHERE user_id = $1 AND used_at IS NULL",
+    )
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await?;
+
+    let context = AuditContext {
+        actor_id: Some(user_id),
+        ..context.clone()
+    };
+    append_event(
+        &mut tx,
+        &context,
+        AuditEventType::PasswordChanged,
+        Some(user_id),
+        json!({ "method": "reset" }),
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(Some(user_id))
+}
@@ -0,0 +1,168 @@
+use chrono::{DateTime, Utc};
+use sqlx::{Pool, Postgres};
+use uuid::Uuid;
+
+use crate::models::photo_upload::PhotoUpload;
+
+pub async fn create_photo_upload(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    size: i64,
+    sha256: &str,
+    expires_at: DateTime<Utc>,
+) -> Result<PhotoUpload, sqlx::Error> {
+    let upload_id = Uuid::new_v4();
+    tracing::info!(user_id, %upload_id, "Starting photo upload");
+    sqlx::query_as::<_, PhotoUpload>(
+        r#"
+        INSERT INTO photo_uploads (id, user_id, size, sha256, expires_at)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING *
+        "#,
+    )
+    .bind(upload_id)
+    .bind(user_id)
+    .bind(size)
+    .bind(sha256)
+    .bind(expires_at)
+    .fetch_one(pool)
+    .await
+}
+
+/// Number of the user's uploads that are still open
+pub async fn count_pending_photo_uploads(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM photo_uploads WHERE user_id = $1 AND expires_at > CURRENT_TIMESTAMP",
+    )
+    .bind(user_id)
+    .fetch_one(pool)
+    .await
+}
+
+/// The user's upload, unless it expired
+pub async fn get_photo_upload(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    upload_id: Uuid,
+) -> Result<Option<PhotoUpload>, sqlx::Error> {
+    sqlx::query_as::<_, PhotoUpload>(
+        r#"
+        SELECT * FROM photo_uploads
+        WHERE id = $1 AND user_id = $2 AND expires_at > CURRENT_TIMESTAMP
+        "#,
+    )
+    .bind(upload_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Records a chunk stored under `blob_key` if it starts exactly at the bytes
+/// received so far and fits the declared size.
+///
+/// Returns the updated upload, or `None` when the offset no longer matches
+/// (e.g. a concurrent retry won) and the chunk must be discarded.
+pub async fn append_photo_upload_chunk(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    upload_id: Uuid,
+    offset: i64,
+    length: i64,
+    blob_key: &str,
+) -> Result<Option<PhotoUpload>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let upload = sqlx::query_as::<_, PhotoUpload>(
+        r#"
+        UPDATE photo_uploads SET received = received + $4
+        WHERE id = $1 AND user_id = $2 AND received = $3 AND received + $4 <= size
+          AND expires_at > CURRENT_TIMESTAMP
+        RETURNING *
+        "#,
+    )
+    .bind(upload_id)
+    .bind(user_id)
+    .bind(offset)
+    .bind(length)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    if upload.is_some() {
+        sqlx::query(
+            r#"
+            INSERT INTO photo_upload_chunks (upload_id, "offset", length, blob_key)
+            VALUES ($1, $2, $3, $4)
+            "#,
+        )
+        .bind(upload_id)
+        .bind(offset)
+        .bind(length)
+        .bind(blob_key)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(upload)
+}
+
+/// Deletes the upload, returning the blob keys of its chunks in order.
+///
+/// With `complete_only`, only an upload that received every byte is taken.
+/// Returns `None` when there is no such upload.
+pub async fn take_photo_upload(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    upload_id: Uuid,
+    complete_only: bool,
+) -> Result<Option<(PhotoUpload, Vec<String>)>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let keys = sqlx::query_scalar::<_, String>(
+        r#"SELECT blob_key FROM photo_upload_chunks WHERE upload_id = $1 ORDER BY "offset""#,
+    )
+    .bind(upload_id)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let upload = sqlx::query_as::<_, PhotoUpload>(
+        r#"
+        DELETE FROM photo_uploads
+        WHERE id = $1 AND user_id = $2 AND (NOT $3 OR received = size)
+        RETURNING *
+        "#,
+    )
+    .bind(upload_id)
+    .bind(user_id)
+    .bind(complete_only)
+    .fetch_optional(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(upload.map(|upload| (upload, keys)))
+}
+
+/// Blob keys of every chunk an upload still holds
+pub async fn list_photo_upload_chunk_keys(pool: &Pool<Postgres>) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar::<_, String>("SELECT blob_key FROM photo_upload_chunks")
+        .fetch_all(pool)
+        .await
+}
+
+/// Deletes expired uploads, returning the blob keys of their chunks
+pub async fn delete_expired_photo_uploads(pool: &Pool<Postgres>) -> Result<Vec<String>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let keys = sqlx::query_scalar::<_, String>(
+        r#"
+        SELECT c.blob_key FROM photo_upload_chunks c
+        JOIN photo_uploads u ON u.id = c.upload_id
+        WHERE u.expires_at <= CURRENT_TIMESTAMP
+        "#,
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    sqlx::query("DELETE FROM photo_uploads WHERE expires_at <= CURRENT_TIMESTAMP")
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(keys)
+}
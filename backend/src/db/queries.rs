@@ -1,38 +1,57 @@
 use bcrypt::{hash, verify, DEFAULT_COST};
-use sqlx::{Pool, Postgres};
+use sqlx::{migrate::Migrator, Pool, Postgres};
 
-use crate::models::user::User;
-use std::env;
+use crate::{
+    config::config::{AdminConfig, DatabaseConfig},
+    models::user::User,
+    telemetry::{self, metrics::time_password},
+};
 
-pub async fn init_db(database_url: &str) -> Pool<Postgres> {
-    println!("🔌 Connecting to database...");
+/// Migrations embedded at build time
+static MIGRATOR: Migrator = sqlx::migrate!();
+
+pub async fn init_db(config: &DatabaseConfig, admin: &AdminConfig) -> Pool<Postgres> {
+    tracing::info!("Connecting to database");
     let pool = sqlx::postgres::PgPoolOptions::new()
-        .max_connections(5)
-        .connect(database_url)
+        .max_connections(config.max_connections)
+        .connect(config.url.expose())
         .await
         .expect("Failed to connect to database");
-    println!("✅ Database connection established!");
+    tracing::info!("Database connection established");
 
     // Run migrations
-    println!("🔄 Running migrations...");
-    sqlx::migrate!()
+    tracing::info!("Running migrations");
+    MIGRATOR
         .run(&pool)
         .await
         .expect("Failed to run migrations");
-    println!("✅ Migrations completed successfully!");
+    tracing::info!("Migrations completed");
 
-    create_admin_if_not_exists(&pool).await;
+    create_admin_if_not_exists(&pool, admin).await;
 
     pool
 }
 
-async fn create_admin_if_not_exists(pool: &Pool<Postgres>) {
-    println!("👤 Checking for admin user...");
+/// Versions of embedded migrations the database has not applied
+pub async fn pending_migrations(pool: &Pool<Postgres>) -> Result<Vec<i64>, sqlx::Error> {
+    let applied = sqlx::query_scalar::<_, i64>("SELECT version FROM _sqlx_migrations WHERE success")
+        .fetch_all(pool)
+        .await?;
+    Ok(MIGRATOR
+        .iter()
+        .filter(|migration| !migration.migration_type.is_down_migration())
+        .map(|migration| migration.version)
+        .filter(|version| !applied.contains(version))
+        .collect())
+}
+
+async fn create_admin_if_not_exists(pool: &Pool<Postgres>, admin: &AdminConfig) {
+    tracing::debug!("Checking for admin user");
     let (firstname, lastname, email, password) = (
-        env::var("ADMIN_FIRSTNAME").expect("ADMIN_FIRSTNAME must be set"),
-        env::var("ADMIN_LASTNAME").expect("ADMIN_LASTNAME must be set"),
-        env::var("ADMIN_EMAIL").expect("ADMIN_EMAIL must be set"),
-        env::var("ADMIN_PASSWORD").expect("ADMIN_PASSWORD must be set"),
+        admin.firstname.clone(),
+        admin.lastname.clone(),
+        admin.email.clone(),
+        admin.password.expose(),
     );
 
     let admin_exists = sqlx::query_as::<_, (bool,)>(
@@ -45,8 +64,8 @@ async fn create_admin_if_not_exists(pool: &Pool<Postgres>) {
     .unwrap_or(false);
 
     if !admin_exists {
-        println!("👥 Creating default admin user...");
-        let hashed_password = hash(password.as_bytes(), DEFAULT_COST).unwrap();
+        tracing::info!("Creating default admin user");
+        let hashed_password = time_password("hash", || hash(password.as_bytes(), DEFAULT_COST)).unwrap();
         
         sqlx::query_as::<_, User>(
             r#"
@@ -62,13 +81,14 @@ async fn create_admin_if_not_exists(pool: &Pool<Postgres>) {
         .bind("Admin")
         .fetch_one(pool)
         .await
-        .map(|user| println!("✅ Default admin user created with email: {}", user.email))
-        .unwrap_or_else(|e| eprintln!("❌ Failed to create admin user: {}", e));
+        .map(|user| tracing::info!(user_id = user.id, "Default admin user created"))
+        .unwrap_or_else(|e| tracing::error!(error = %e, "Failed to create admin user"));
     } else {
-        println!("✅ Admin user already exists!");
+        tracing::debug!("Admin user already exists");
     }
 }
 
+#[tracing::instrument(name = "db.create_user", skip_all, fields(db.system = "postgresql", otel.kind = "client"))]
 pub async fn create_user(
     pool: &Pool<Postgres>,
     firstname: &str,
@@ -76,8 +96,8 @@ pub async fn create_user(
     email: &str,
     password: &str,
 ) -> Result<User, sqlx::Error> {
-    println!("👤 Creating new user with email: {}", email);
-    let hashed_password = hash(password.as_bytes(), DEFAULT_COST).unwrap();
+    tracing::debug!(email = %telemetry::email(email), "Creating user");
+    let hashed_password = time_password("hash", || hash(password.as_bytes(), DEFAULT_COST)).unwrap();
 
     let result = sqlx::query_as::<_, User>(
         r#"
@@ -95,18 +115,18 @@ pub async fn create_user(
     .await;
 
     match &result {
-        Ok(user) => println!("✅ User created successfully: {} {}", user.firstname, user.lastname),
-        Err(e) => println!("❌ Failed to create user: {}", e),
+        Ok(user) => tracing::info!(user_id = user.id, "User created"),
+        Err(e) => tracing::error!(error = %e, "Failed to create user"),
     }
 
     result
 }
 
+#[tracing::instrument(name = "db.get_user_by_email", skip_all, fields(db.system = "postgresql", otel.kind = "client"))]
 pub async fn get_user_by_email(
     pool: &Pool<Postgres>,
     email: &str,
 ) -> Result<User, sqlx::Error> {
-    println!("🔍 Looking up user by email: {}", email);
     let result = sqlx::query_as::<_, User>(
         r#"
         SELECT * FROM users WHERE email = $1
@@ -116,14 +136,29 @@ pub async fn get_user_by_email(
     .fetch_one(pool)
     .await;
 
-    match &result {
-        Ok(user) => println!("✅ User found: {} {}", user.firstname, user.lastname),
-        Err(e) => println!("❌ User lookup failed: {}", e),
+    if let Err(e) = &result {
+        tracing::debug!(email = %telemetry::email(email), error = %e, "User lookup by email failed");
     }
 
     result
 }
 
+#[tracing::instrument(name = "db.get_user_by_id", skip_all, fields(db.system = "postgresql", otel.kind = "client", user_id))]
+pub async fn get_user_by_id(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+) -> Result<User, sqlx::Error> {
+    sqlx::query_as::<_, User>(
+        r#"
+        SELECT * FROM users WHERE id = $1
+        "#
+    )
+    .bind(user_id)
+    .fetch_one(pool)
+    .await
+}
+
+#[tracing::instrument(name = "db.update_user_profile", skip_all, fields(db.system = "postgresql", otel.kind = "client", user_id))]
 pub async fn update_user_profile(
     pool: &Pool<Postgres>,
     user_id: i32,
@@ -133,26 +168,25 @@ pub async fn update_user_profile(
     password: Option<&str>,
     profile_picture: Option<&str>,
 ) -> Result<User, sqlx::Error> {
-    println!("✏️ Updating profile for user ID: {}", user_id);
     let mut query = String::from(
         "UPDATE users SET 
         firstname = COALESCE($1, firstname),
         lastname = COALESCE($2, lastname),
         email = COALESCE($3, email),
+        email_verified = email_verified AND ($3 IS NULL OR $3 = email),
         profile_picture = COALESCE($4, profile_picture)"
     );
 
-    let hashed_password = if let Some(pass) = password {
-        Some(hash(pass.as_bytes(), DEFAULT_COST).unwrap())
-    } else {
-        None
-    };
+    let hashed_password =
+        password.map(|pass| time_password("hash", || hash(pass.as_bytes(), DEFAULT_COST)).unwrap());
 
-    if hashed_password.is_some() {
-        query.push_str(", password = COALESCE($5, password)");
-    }
+    // Setting a new password satisfies a pending forced password change
+    query.push_str(
+        ", password = COALESCE($5, password),
+        must_change_password = must_change_password AND $5 IS NULL",
+    );
 
-    query.push_str(" WHERE id = $6 RETURNING id, firstname, lastname, email, password, role, created_at, last_login, login_count, profile_picture");
+    query.push_str(" WHERE id = $6 RETURNING *");
 
     let result = sqlx::query_as::<_, User>(&query)
         .bind(firstname)
@@ -165,18 +199,50 @@ pub async fn update_user_profile(
         .await;
 
     match &result {
-        Ok(user) => println!("✅ Profile updated successfully for: {} {}", user.firstname, user.lastname),
-        Err(e) => println!("❌ Profile update failed: {}", e),
+        Ok(user) => tracing::info!(user_id = user.id, "Profile updated"),
+        Err(e) => tracing::error!(user_id, error = %e, "Profile update failed"),
     }
 
     result
 }
 
+/// Removes the user's profile picture, returning the updated user
+#[tracing::instrument(name = "db.clear_profile_picture", skip_all, fields(db.system = "postgresql", otel.kind = "client", user_id))]
+pub async fn clear_profile_picture(pool: &Pool<Postgres>, user_id: i32) -> Result<User, sqlx::Error> {
+    tracing::info!(user_id, "Removing profile picture");
+    sqlx::query_as::<_, User>("UPDATE users SET profile_picture = NULL WHERE id = $1 RETURNING *")
+        .bind(user_id)
+        .fetch_one(pool)
+        .await
+}
+
+/// Number of users whose profile picture is `url`
+#[tracing::instrument(name = "db.count_profile_picture_references", skip_all, fields(db.system = "postgresql", otel.kind = "client"))]
+pub async fn count_profile_picture_references(
+    pool: &Pool<Postgres>,
+    url: &str,
+) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM users WHERE profile_picture = $1")
+        .bind(url)
+        .fetch_one(pool)
+        .await
+}
+
+/// Every distinct profile picture hosted in our own storage
+#[tracing::instrument(name = "db.list_profile_pictures", skip_all, fields(db.system = "postgresql", otel.kind = "client"))]
+pub async fn list_profile_pictures(pool: &Pool<Postgres>) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar::<_, String>(
+        "SELECT DISTINCT profile_picture FROM users WHERE profile_picture LIKE '/uploads/%'",
+    )
+    .fetch_all(pool)
+    .await
+}
+
+#[tracing::instrument(name = "db.update_login_activity", skip_all, fields(db.system = "postgresql", otel.kind = "client", user_id))]
 pub async fn update_login_activity(
     pool: &Pool<Postgres>,
     user_id: i32,
 ) -> Result<(), sqlx::Error> {
-    println!("🔄 Updating login activity for user ID: {}", user_id);
     let result = sqlx::query(
         r#"
         UPDATE users 
@@ -189,20 +255,13 @@ pub async fn update_login_activity(
     .execute(pool)
     .await;
 
-    match &result {
-        Ok(_) => println!("✅ Login activity updated successfully"),
-        Err(e) => println!("❌ Failed to update login activity: {}", e),
+    if let Err(e) = &result {
+        tracing::error!(user_id, error = %e, "Failed to update login activity");
     }
 
     result.map(|_| ())
 }
 
 pub fn verify_password(password: &str, hash: &str) -> bool {
-    let result = verify(password.as_bytes(), hash).unwrap_or(false);
-    if result {
-        println!("✅ Password verification successful");
-    } else {
-        println!("❌ Password verification failed");
-    }
-    result
+    time_password("verify", || verify(password.as_bytes(), hash)).unwrap_or(false)
 }
\ No newline at end of file
@@ -0,0 +1,107 @@
+use sqlx::{Pool, Postgres};
+
+use crate::models::relation::{ObjectRef, RelationTuple, SubjectRef};
+
+pub async fn write_tuple(
+    pool: &Pool<Postgres>,
+    object: &ObjectRef,
+    relation: &str,
+    subject: &SubjectRef,
+) -> Result<(), sqlx::Error> {
+    tracing::info!(%object, %relation, %subject, "Writing tuple");
+    sqlx::query(
+        r#"
+        INSERT INTO relation_tuples
+            (object_type, object_id, relation, subject_type, subject_id, subject_relation)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT DO NOTHING
+        "#,
+    )
+    .bind(&object.object_type)
+    .bind(&object.object_id)
+    .bind(relation)
+    .bind(&subject.object.object_type)
+    .bind(&subject.object.object_id)
+    .bind(subject.relation.as_deref().unwrap_or(""))
+    .execute(pool)
+    .await
+    .map(|_| ())
+}
+
+pub async fn delete_tuple(
+    pool: &Pool<Postgres>,
+    object: &ObjectRef,
+    relation: &str,
+    subject: &SubjectRef,
+) -> Result<bool, sqlx::Error> {
+    tracing::info!(%object, %relation, %subject, "Deleting tuple");
+    let result = sqlx::query(
+        r#"
+        DELETE FROM relation_tuples
+        WHERE object_type = $1 AND object_id = $2 AND relation = $3
+          AND subject_type = $4 AND subject_id = $5 AND subject_relation = $6
+        "#,
+    )
+    .bind(&object.object_type)
+    .bind(&object.object_id)
+    .bind(relation)
+    .bind(&subject.object.object_type)
+    .bind(&subject.object.object_id)
+    .bind(subject.relation.as_deref().unwrap_or(""))
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Tuples stored directly for `object#relation`
+pub async fn read_tuples(
+    pool: &Pool<Postgres>,
+    object: &ObjectRef,
+    relation: &str,
+) -> Result<Vec<RelationTuple>, sqlx::Error> {
+    sqlx::query_as::<_, RelationTuple>(
+        r#"
+        SELECT object_type, object_id, relation, subject_type, subject_id, subject_relation
+        FROM relation_tuples
+        WHERE object_type = $1 AND object_id = $2 AND relation = $3
+        "#,
+    )
+    .bind(&object.object_type)
+    .bind(&object.object_id)
+    .bind(relation)
+    .fetch_all(pool)
+    .await
+}
+
+/// Every object id of the given type that appears in at least one tuple
+pub async fn list_object_ids(
+    pool: &Pool<Postgres>,
+    object_type: &str,
+) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar::<_, String>(
+        "SELECT DISTINCT object_id FROM relation_tuples WHERE object_type = $1 ORDER BY object_id",
+    )
+    .bind(object_type)
+    .fetch_all(pool)
+    .await
+}
+
+/// Every tuple stored on an object, across all relations
+pub async fn list_object_tuples(
+    pool: &Pool<Postgres>,
+    object: &ObjectRef,
+) -> Result<Vec<RelationTuple>, sqlx::Error> {
+    sqlx::query_as::<_, RelationTuple>(
+        r#"
+        SELECT object_type, object_id, relation, subject_type, subject_id, subject_relation
+        FROM relation_tuples
+        WHERE object_type = $1 AND object_id = $2
+        ORDER BY relation, subject_type, subject_id, subject_relation
+        "#,
+    )
+    .bind(&object.object_type)
+    .bind(&object.object_id)
+    .fetch_all(pool)
+    .await
+}
@@ -0,0 +1,267 @@
+use sqlx::{Pool, Postgres};
+use std::collections::HashSet;
+
+use crate::models::role::RoleDefinition;
+
+/// Upper bound on hierarchy depth, guards the recursive queries against cycles
+const MAX_ROLE_DEPTH: i32 = 32;
+
+pub async fn list_roles(pool: &Pool<Postgres>) -> Result<Vec<RoleDefinition>, sqlx::Error> {
+    sqlx::query_as::<_, RoleDefinition>("SELECT name, parent, description FROM roles ORDER BY name")
+        .fetch_all(pool)
+        .await
+}
+
+pub async fn get_role_permissions(
+    pool: &Pool<Postgres>,
+    role: &str,
+) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar::<_, String>(
+        "SELECT permission FROM role_permissions WHERE role = $1 ORDER BY permission",
+    )
+    .bind(role)
+    .fetch_all(pool)
+    .await
+}
+
+/// Returns the role itself followed by its ancestors, closest first
+pub async fn get_role_lineage(
+    pool: &Pool<Postgres>,
+    role: &str,
+) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar::<_, String>(
+        r#"
+        WITH RECURSIVE lineage AS (
+            SELECT name, parent, 0 AS depth FROM roles WHERE name = $1
+            UNION ALL
+            SELECT r.name, r.parent, l.depth + 1
+            FROM roles r
+            JOIN lineage l ON r.name = l.parent
+            WHERE l.depth < $2
+        )
+        SELECT name FROM lineage ORDER BY depth
+        "#,
+    )
+    .bind(role)
+    .bind(MAX_ROLE_DEPTH)
+    .fetch_all(pool)
+    .await
+}
+
+/// Permissions granted to the role or any of its ancestors
+pub async fn get_effective_permissions(
+    pool: &Pool<Postgres>,
+    role: &str,
+) -> Result<HashSet<String>, sqlx::Error> {
+    let permissions = sqlx::query_scalar::<_, String>(
+        r#"
+        WITH RECURSIVE lineage AS (
+            SELECT name, parent, 0 AS depth FROM roles WHERE name = $1
+            UNION ALL
+            SELECT r.name, r.parent, l.depth + 1
+            FROM roles r
+            JOIN lineage l ON r.name = l.parent
+            WHERE l.depth < $2
+        )
+        SELECT DISTINCT rp.permission
+        FROM role_permissions rp
+        JOIN lineage l ON rp.role = l.name
+        "#,
+    )
+    .bind(role)
+    .bind(MAX_ROLE_DEPTH)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(permissions.into_iter().collect())
+}
+
+/// Creates or replaces a role and its direct permissions
+pub async fn upsert_role(
+    pool: &Pool<Postgres>,
+    name: &str,
+    parent: Option<&str>,
+    description: Option<&str>,
+    permissions: &[String],
+) -> Result<RoleDefinition, sqlx::Error> {
+    tracing::debug!(%name, "Saving role");
+    let mut tx = pool.begin().await?;
+
+    let role = sqlx::query_as::<_, RoleDefinition>(
+        r#"
+        INSERT INTO roles (name, parent, description)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (name) DO UPDATE
+        SET parent = EXCLUDED.parent, description = EXCLUDED.description
+        RETURNING name, parent, description
+        "#,
+    )
+    .bind(name)
+    .bind(parent)
+    .bind(description)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    sqlx::query("DELETE FROM role_permissions WHERE role = $1")
+        .bind(name)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO role_permissions (role, permission)
+        SELECT $1, UNNEST($2::VARCHAR[])
+        ON CONFLICT DO NOTHING
+        "#,
+    )
+    .bind(name)
+    .bind(permissions)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    tracing::info!(%name, "Role saved");
+    Ok(role)
+}
+
+/// Permissions of a user: those of their own role and of every role or
+/// permission granted to a group they belong to, directly or through subgroups
+pub async fn get_user_permissions(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+) -> Result<HashSet<String>, sqlx::Error> {
+    let permissions = sqlx::query_scalar::<_, String>(
+        r#"
+        WITH RECURSIVE user_groups AS (
+            SELECT group_id, 0 AS depth FROM group_members WHERE user_id = $1
+            UNION
+            SELECT s.parent_group_id, ug.depth + 1
+            FROM group_subgroups s
+            JOIN user_groups ug ON s.child_group_id = ug.group_id
+            WHERE ug.depth < $2
+        ),
+        granted_roles AS (
+            SELECT role AS name FROM users WHERE id = $1
+            UNION
+            SELECT gr.role FROM group_roles gr JOIN user_groups ug ON ug.group_id = gr.group_id
+        ),
+        lineage AS (
+            SELECT r.name, r.parent, 0 AS depth
+            FROM roles r
+            JOIN granted_roles g ON g.name = r.name
+            UNION ALL
+            SELECT r.name, r.parent, l.depth + 1
+            FROM roles r
+            JOIN lineage l ON r.name = l.parent
+            WHERE l.depth < $2
+        )
+        SELECT rp.permission FROM role_permissions rp JOIN lineage l ON rp.role = l.name
+        UNION
+        SELECT gp.permission FROM group_permissions gp JOIN user_groups ug ON ug.group_id = gp.group_id
+        "#,
+    )
+    .bind(user_id)
+    .bind(MAX_ROLE_DEPTH)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(permissions.into_iter().collect())
+}
+
+/// Roles a user holds: their own role, roles granted to their groups (directly
+/// or through subgroups), and every ancestor of those roles
+pub async fn get_user_roles(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+) -> Result<HashSet<String>, sqlx::Error> {
+    let roles = sqlx::query_scalar::<_, String>(
+        r#"
+        WITH RECURSIVE user_groups AS (
+            SELECT group_id, 0 AS depth FROM group_members WHERE user_id = $1
+            UNION
+            SELECT s.parent_group_id, ug.depth + 1
+            FROM group_subgroups s
+            JOIN user_groups ug ON s.child_group_id = ug.group_id
+            WHERE ug.depth < $2
+        ),
+        granted_roles AS (
+            SELECT role AS name FROM users WHERE id = $1
+            UNION
+            SELECT gr.role FROM group_roles gr JOIN user_groups ug ON ug.group_id = gr.group_id
+        ),
+        lineage AS (
+            SELECT r.name, r.parent, 0 AS depth
+            FROM roles r
+            JOIN granted_roles g ON g.name = r.name
+            UNION ALL
+            SELECT r.name, r.parent, l.depth + 1
+            FROM roles r
+            JOIN lineage l ON r.name = l.parent
+            WHERE l.depth < $2
+        )
+        SELECT DISTINCT name FROM lineage
+        "#,
+    )
+    .bind(user_id)
+    .bind(MAX_ROLE_DEPTH)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(roles.into_iter().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::insert_user;
+
+    #[sqlx::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn lineage_lists_ancestors_closest_first(pool: Pool<Postgres>) {
+        let lineage = get_role_lineage(&pool, "Admin").await.unwrap();
+        assert_eq!(lineage, ["Admin", "Moderator", "User"]);
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn roles_inherit_ancestor_permissions(pool: Pool<Postgres>) {
+        upsert_role(&pool, "Support", Some("Moderator"), None, &["tickets:read".to_string()])
+            .await
+            .unwrap();
+
+        let permissions = get_effective_permissions(&pool, "Support").await.unwrap();
+        assert!(permissions.contains("tickets:read"));
+        assert!(permissions.contains("users:read"));
+        assert!(permissions.contains("profile:read"));
+        assert!(!permissions.contains("admin:access"));
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn user_permissions_follow_role_changes(pool: Pool<Postgres>) {
+        let user = insert_user(&pool, "user@example.com", "User").await;
+        assert!(!get_user_permissions(&pool, user.id).await.unwrap().contains("users:read"));
+
+        sqlx::query("UPDATE users SET role = 'Moderator' WHERE id = $1")
+            .bind(user.id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert!(get_user_permissions(&pool, user.id).await.unwrap().contains("users:read"));
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn depth_limit_stops_cycles(pool: Pool<Postgres>) {
+        // The API refuses cycles; the depth limit covers ones written directly
+        sqlx::query("UPDATE roles SET parent = 'Admin' WHERE name = 'User'")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let lineage = get_role_lineage(&pool, "Admin").await.unwrap();
+        assert_eq!(lineage.len(), MAX_ROLE_DEPTH as usize + 1);
+        let permissions = get_effective_permissions(&pool, "User").await.unwrap();
+        assert!(permissions.contains("admin:access"));
+    }
+}
@@ -0,0 +1,336 @@
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use sqlx::{Pool, Postgres};
+use uuid::Uuid;
+
+use crate::{
+    db::{audit::append_event, password_resets::create_password_reset},
+    models::{
+        audit::{AuditContext, AuditEventType},
+        session::{DeviceInfo, LoginAssessment, LoginRecord, Session},
+    },
+};
+
+/// `last_seen_at` is only refreshed once it is this stale, sparing a write per request
+const LAST_SEEN_RESOLUTION_SECONDS: i64 = 60;
+
+pub async fn record_login(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    ip: Option<&str>,
+    user_agent: Option<&str>,
+    auth_method: &str,
+    failure_reason: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    let device = DeviceInfo::from_user_agent(user_agent);
+    sqlx::query(
+        r#"
+        INSERT INTO login_history
+            (user_id, ip, user_agent, device, browser, os, auth_method, success, failure_reason)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        "#,
+    )
+    .bind(user_id)
+    .bind(ip)
+    .bind(user_agent)
+    .bind(&device.device)
+    .bind(&device.browser)
+    .bind(&device.os)
+    .bind(auth_method)
+    .bind(failure_reason.is_none())
+    .bind(failure_reason)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn list_login_history(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    limit: i64,
+) -> Result<Vec<LoginRecord>, sqlx::Error> {
+    sqlx::query_as::<_, LoginRecord>(
+        r#"
+        SELECT id, ip, user_agent, device, browser, os, auth_method, success, failure_reason, created_at
+        FROM login_history
+        WHERE user_id = $1
+        ORDER BY created_at DESC, id DESC
+        LIMIT $2
+        "#,
+    )
+    .bind(user_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn create_session(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    ip: Option<&str>,
+    user_agent: Option<&str>,
+    auth_method: &str,
+    expires_at: DateTime<Utc>,
+) -> Result<Uuid, sqlx::Error> {
+    let device = DeviceInfo::from_user_agent(user_agent);
+    let session_id = Uuid::new_v4();
+    tracing::info!(user_id, %session_id, "Starting session");
+    sqlx::query(
+        r#"
+        INSERT INTO sessions
+            (id, user_id, ip, user_agent, device, browser, os, auth_method, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        "#,
+    )
+    .bind(session_id)
+    .bind(user_id)
+    .bind(ip)
+    .bind(user_agent)
+    .bind(&device.device)
+    .bind(&device.browser)
+    .bind(&device.os)
+    .bind(auth_method)
+    .bind(expires_at)
+    .execute(pool)
+    .await?;
+    Ok(session_id)
+}
+
+/// Whether the session is live (not revoked or expired), refreshing `last_seen_at`
+pub async fn touch_session(
+    pool: &Pool<Postgres>,
+    session_id: Uuid,
+    user_id: i32,
+) -> Result<bool, sqlx::Error> {
+    let last_seen = sqlx::query_scalar::<_, DateTime<Utc>>(
+        r#"
+        SELECT last_seen_at FROM sessions
+        WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL AND expires_at > CURRENT_TIMESTAMP
+        "#,
+    )
+    .bind(session_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+
+    let Some(last_seen) = last_seen else {
+        return Ok(false);
+    };
+    if (Utc::now() - last_seen).num_seconds() >= LAST_SEEN_RESOLUTION_SECONDS {
+        sqlx::query("UPDATE sessions SET last_seen_at = CURRENT_TIMESTAMP WHERE id = $1")
+            .bind(session_id)
+            .execute(pool)
+            .await?;
+    }
+    Ok(true)
+}
+
+pub async fn list_active_sessions(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+) -> Result<Vec<Session>, sqlx::Error> {
+    sqlx::query_as::<_, Session>(
+        r#"
+        SELECT * FROM sessions
+        WHERE user_id = $1 AND revoked_at IS NULL AND expires_at > CURRENT_TIMESTAMP
+        ORDER BY last_seen_at DESC
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// Revokes one of the user's sessions; false when it is not theirs or already ended
+pub async fn revoke_session(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    session_id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    tracing::info!(user_id, %session_id, "Revoking session");
+    let result = sqlx::query(
+        r#"
+        UPDATE sessions SET revoked_at = CURRENT_TIMESTAMP
+        WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL AND expires_at > CURRENT_TIMESTAMP
+        "#,
+    )
+    .bind(session_id)
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Revokes every live session of the user except `keep`
+pub async fn revoke_other_sessions(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    keep: Option<Uuid>,
+) -> Result<u64, sqlx::Error> {
+    tracing::info!(user_id, "Revoking other sessions");
+    let result = sqlx::query(
+        r#"
+        UPDATE sessions SET revoked_at = CURRENT_TIMESTAMP
+        WHERE user_id = $1 AND revoked_at IS NULL AND expires_at > CURRENT_TIMESTAMP
+          AND id IS DISTINCT FROM $2
+        "#,
+    )
+    .bind(user_id)
+    .bind(keep)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+/// Compares a login from `ip` and `user_agent` with the user's recorded history.
+/// Failures only count from the last successful login and within `window_minutes`.
+///
+/// `ip` must be the address `ClientInfo` resolves through `server.trusted_proxies`:
+/// the known-device match relies on it, and a client-supplied address would
+/// let an attacker pass as a device the user has signed in from.
+pub async fn assess_login(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    ip: Option<&str>,
+    user_agent: Option<&str>,
+    window_minutes: i32,
+) -> Result<LoginAssessment, sqlx::Error> {
+    let device = DeviceInfo::from_user_agent(user_agent);
+    sqlx::query_as::<_, LoginAssessment>(
+        r#"
+        SELECT
+            COUNT(*) FILTER (WHERE success) AS previous_logins,
+            COALESCE(BOOL_OR(
+                success
+                AND ip IS NOT DISTINCT FROM $2
+                AND device IS NOT DISTINCT FROM $3
+                AND browser IS NOT DISTINCT FROM $4
+                AND os IS NOT DISTINCT FROM $5
+            ), FALSE) AS known_device,
+            COUNT(*) FILTER (
+                WHERE NOT success
+                  AND created_at > CURRENT_TIMESTAMP - make_interval(mins => $6)
+                  AND created_at > COALESCE(
+                      (SELECT MAX(created_at) FROM login_history WHERE user_id = $1 AND success),
+                      '-infinity'
+                  )
+            ) AS recent_failures
+        FROM login_history
+        WHERE user_id = $1
+        "#,
+    )
+    .bind(user_id)
+    .bind(ip)
+    .bind(&device.device)
+    .bind(&device.browser)
+    .bind(&device.os)
+    .bind(window_minutes)
+    .fetch_one(pool)
+    .await
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn create_login_alert(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+    token: Uuid,
+    session_id: Uuid,
+    reasons: &[&str],
+    ip: Option<&str>,
+    user_agent: Option<&str>,
+    expires_at: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+    tracing::warn!(user_id, reasons = %reasons.join(", "), "Raising login alert");
+    sqlx::query(
+        r#"
+        INSERT INTO login_alerts (user_id, token, session_id, reasons, ip, user_agent, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        "#,
+    )
+    .bind(user_id)
+    .bind(token)
+    .bind(session_id)
+    .bind(reasons)
+    .bind(ip)
+    .bind(user_agent)
+    .bind(expires_at)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Outcome of disowning a login
+pub struct DisownedLogin {
+    pub user_id: i32,
+    pub sessions_revoked: u64,
+    /// Reset token to mail to the user
+    pub reset_token: Uuid,
+}
+
+/// Redeems a login alert's "this wasn't me" token: signs out every session of
+/// the user and blocks password login until they choose a new password
+/// through the returned reset token, which only reaches their mailbox.
+///
+/// Returns `None` when the token is unknown, expired or already used.
+pub async fn disown_login(
+    pool: &Pool<Postgres>,
+    context: &AuditContext,
+    token: Uuid,
+) -> Result<Option<DisownedLogin>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let alert = sqlx::query_as::<_, (i64, i32, Option<Uuid>)>(
+        r#"
+        UPDATE login_alerts SET used_at = CURRENT_TIMESTAMP
+        WHERE token = $1 AND used_at IS NULL AND expires_at > CURRENT_TIMESTAMP
+        RETURNING id, user_id, session_id
+        "#,
+    )
+    .bind(token)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some((alert_id, user_id, session_id)) = alert else {
+        return Ok(None);
+    };
+    tracing::warn!(user_id, %alert_id, "User disowned login alert");
+
+    let revoked = sqlx::query(
+        r#"
+        UPDATE sessions SET revoked_at = CURRENT_TIMESTAMP
+        WHERE user_id = $1 AND revoked_at IS NULL AND expires_at > CURRENT_TIMESTAMP
+        "#,
+    )
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+
+    // The attacker may know the password, so neither it nor a session can
+    // be trusted to pick the next one
+    sqlx::query(
+        "UPDATE users SET must_change_password = TRUE, password_reset_required = TRUE WHERE id = $1",
+    )
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await?;
+    let reset_token = create_password_reset(&mut tx, user_id).await?;
+
+    let context = AuditContext {
+        actor_id: Some(user_id),
+        ..context.clone()
+    };
+    append_event(
+        &mut tx,
+        &context,
+        AuditEventType::LoginDisowned,
+        Some(user_id),
+        json!({ "alert_id": alert_id, "session_id": session_id, "sessions_revoked": revoked }),
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(Some(DisownedLogin {
+        user_id,
+        sessions_revoked: revoked,
+        reset_token,
+    }))
+}
@@ -0,0 +1,621 @@
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+use sqlx::{Pool, Postgres, QueryBuilder, Transaction};
+
+use crate::{
+    db::{audit::append_event, organizations::lock_owners},
+    models::{
+        admin::{RoleCount, SortOrder, UserFilter, UserPage, UserSort, UserSummary},
+        audit::{AuditContext, AuditEvent, AuditEventType},
+    },
+};
+
+const USER_COLUMNS: &str = "id, firstname, lastname, email, role, created_at, last_login, \
+                            login_count, profile_picture, email_verified, disabled_at, \
+                            disabled_reason, must_change_password";
+
+/// Upper bound on the page size of the user list
+pub const MAX_PAGE_SIZE: i64 = 200;
+
+/// Sort key of the last row of a page; nullable columns sort as their COALESCE default
+enum CursorKey {
+    Int(i32),
+    Text(String),
+    Time(DateTime<Utc>),
+}
+
+/// Keyset pagination cursor, hex-encoded as `sort|id|key` so it stays URL safe
+pub struct UserCursor {
+    id: i32,
+    key: CursorKey,
+}
+
+fn sort_name(sort: UserSort) -> &'static str {
+    match sort {
+        UserSort::Id => "id",
+        UserSort::Email => "email",
+        UserSort::CreatedAt => "created_at",
+        UserSort::LastLogin => "last_login",
+        UserSort::LoginCount => "login_count",
+    }
+}
+
+fn sort_expression(sort: UserSort) -> &'static str {
+    match sort {
+        UserSort::Id => "id",
+        UserSort::Email => "lower(email)",
+        UserSort::CreatedAt => "COALESCE(created_at, 'epoch'::timestamptz)",
+        UserSort::LastLogin => "COALESCE(last_login, 'epoch'::timestamptz)",
+        UserSort::LoginCount => "COALESCE(login_count, 0)",
+    }
+}
+
+impl UserCursor {
+    fn after(sort: UserSort, user: &UserSummary) -> Self {
+        let epoch = DateTime::<Utc>::UNIX_EPOCH;
+        let key = match sort {
+            UserSort::Id => CursorKey::Int(user.id),
+            UserSort::Email => CursorKey::Text(user.email.to_lowercase()),
+            UserSort::CreatedAt => CursorKey::Time(user.created_at.unwrap_or(epoch)),
+            UserSort::LastLogin => CursorKey::Time(user.last_login.unwrap_or(epoch)),
+            UserSort::LoginCount => CursorKey::Int(user.login_count.unwrap_or(0)),
+        };
+        Self { id: user.id, key }
+    }
+
+    fn encode(&self, sort: UserSort) -> String {
+        let key = match &self.key {
+            CursorKey::Int(value) => value.to_string(),
+            CursorKey::Text(value) => value.clone(),
+            CursorKey::Time(value) => value.to_rfc3339(),
+        };
+        format!("{}|{}|{}", sort_name(sort), self.id, key)
+            .bytes()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    /// Decodes a cursor, rejecting cursors issued for a different sort column
+    pub fn decode(cursor: &str, sort: UserSort) -> Result<Self, String> {
+        let invalid = || "Invalid cursor".to_string();
+        if !cursor.len().is_multiple_of(2) {
+            return Err(invalid());
+        }
+        let bytes = (0..cursor.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(cursor.get(i..i + 2)?, 16).ok())
+            .collect::<Option<Vec<u8>>>()
+            .ok_or_else(invalid)?;
+        let decoded = String::from_utf8(bytes).map_err(|_| invalid())?;
+
+        let mut parts = decoded.splitn(3, '|');
+        let (Some(name), Some(id), Some(key)) = (parts.next(), parts.next(), parts.next()) else {
+            return Err(invalid());
+        };
+        if name != sort_name(sort) {
+            return Err("Cursor was issued for a different sort".to_string());
+        }
+        let id = id.parse().map_err(|_| invalid())?;
+        let key = match sort {
+            UserSort::Id | UserSort::LoginCount => {
+                CursorKey::Int(key.parse().map_err(|_| invalid())?)
+            }
+            UserSort::Email => CursorKey::Text(key.to_string()),
+            UserSort::CreatedAt | UserSort::LastLogin => CursorKey::Time(
+                DateTime::parse_from_rfc3339(key)
+                    .map_err(|_| invalid())?
+                    .with_timezone(&Utc),
+            ),
+        };
+        Ok(Self { id, key })
+    }
+}
+
+/// Escapes `%`, `_` and `\` so user input matches literally inside LIKE patterns
+fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+fn push_filters(builder: &mut QueryBuilder<'_, Postgres>, filter: &UserFilter) {
+    builder.push(" WHERE TRUE");
+    if let Some(role) = &filter.role {
+        builder.push(" AND role = ").push_bind(role.clone());
+    }
+    if let Some(email) = &filter.email {
+        builder
+            .push(" AND email ILIKE ")
+            .push_bind(format!("%{}%", escape_like(email)));
+    }
+    if let Some(after) = filter.created_after {
+        builder.push(" AND created_at >= ").push_bind(after);
+    }
+    if let Some(before) = filter.created_before {
+        builder.push(" AND created_at < ").push_bind(before);
+    }
+    if let Some(after) = filter.last_login_after {
+        builder.push(" AND last_login >= ").push_bind(after);
+    }
+    if let Some(before) = filter.last_login_before {
+        builder.push(" AND last_login < ").push_bind(before);
+    }
+    match filter.has_logged_in {
+        Some(true) => {
+            builder.push(" AND last_login IS NOT NULL");
+        }
+        Some(false) => {
+            builder.push(" AND last_login IS NULL");
+        }
+        None => {}
+    }
+    match filter.disabled {
+        Some(true) => {
+            builder.push(" AND disabled_at IS NOT NULL");
+        }
+        Some(false) => {
+            builder.push(" AND disabled_at IS NULL");
+        }
+        None => {}
+    }
+}
+
+/// Lists users matching `filter`, `limit` at a time, starting after `after`
+pub async fn list_users(
+    pool: &Pool<Postgres>,
+    filter: &UserFilter,
+    sort: UserSort,
+    order: SortOrder,
+    limit: i64,
+    after: Option<&UserCursor>,
+) -> Result<UserPage, sqlx::Error> {
+    let expression = sort_expression(sort);
+    let (direction, comparison) = match order {
+        SortOrder::Asc => ("ASC", ">"),
+        SortOrder::Desc => ("DESC", "<"),
+    };
+
+    let mut builder = QueryBuilder::new(format!("SELECT {} FROM users", USER_COLUMNS));
+    push_filters(&mut builder, filter);
+    if let Some(cursor) = after {
+        builder.push(format!(" AND ({}, id) {} (", expression, comparison));
+        match &cursor.key {
+            CursorKey::Int(value) => builder.push_bind(*value),
+            CursorKey::Text(value) => builder.push_bind(value.clone()),
+            CursorKey::Time(value) => builder.push_bind(*value),
+        };
+        builder.push(", ").push_bind(cursor.id).push(")");
+    }
+    builder
+        .push(format!(" ORDER BY {} {}, id {} LIMIT ", expression, direction, direction))
+        .push_bind(limit + 1);
+
+    let mut users = builder
+        .build_query_as::<UserSummary>()
+        .fetch_all(pool)
+        .await?;
+
+    let next_cursor = if users.len() as i64 > limit {
+        users.truncate(limit as usize);
+        users
+            .last()
+            .map(|last| UserCursor::after(sort, last).encode(sort))
+    } else {
+        None
+    };
+
+    Ok(UserPage { users, next_cursor })
+}
+
+/// Counts users matching `filter`, per role
+pub async fn count_users(
+    pool: &Pool<Postgres>,
+    filter: &UserFilter,
+) -> Result<Vec<RoleCount>, sqlx::Error> {
+    let mut builder = QueryBuilder::new("SELECT role, COUNT(*) AS count FROM users");
+    push_filters(&mut builder, filter);
+    builder.push(" GROUP BY role ORDER BY role");
+    builder.build_query_as::<RoleCount>().fetch_all(pool).await
+}
+
+pub async fn get_user_summary(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+) -> Result<Option<UserSummary>, sqlx::Error> {
+    sqlx::query_as::<_, UserSummary>(&format!("SELECT {} FROM users WHERE id = $1", USER_COLUMNS))
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await
+}
+
+/// Records an action outside of a transaction, e.g. a request made while impersonating
+pub async fn log_admin_action(
+    pool: &Pool<Postgres>,
+    context: &AuditContext,
+    target_user_id: i32,
+    action: &str,
+    details: Value,
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    record_action(&mut tx, context, target_user_id, action, details).await?;
+    tx.commit().await
+}
+
+/// Records an administrative action on a user in the audit log; role changes
+/// are recorded as `RoleChanged`, everything else as `AdminAction`
+async fn record_action(
+    tx: &mut Transaction<'_, Postgres>,
+    context: &AuditContext,
+    target_user_id: i32,
+    action: &str,
+    details: Value,
+) -> Result<(), sqlx::Error> {
+    let event_type = match action {
+        "change_role" => AuditEventType::RoleChanged,
+        _ => AuditEventType::AdminAction,
+    };
+    let mut details = details;
+    if let Value::Object(fields) = &mut details {
+        fields.insert("action".to_string(), json!(action));
+    }
+    append_event(tx, context, event_type, Some(target_user_id), details).await
+}
+
+/// Applies `assignments` to a user, with `reason` bound as `$2`, and records
+/// the action in the same transaction
+async fn update_user_status(
+    pool: &Pool<Postgres>,
+    context: &AuditContext,
+    user_id: i32,
+    action: &str,
+    assignments: &str,
+    reason: Option<&str>,
+) -> Result<Option<UserSummary>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let user = sqlx::query_as::<_, UserSummary>(&format!(
+        "UPDATE users SET {} WHERE id = $1 RETURNING {}",
+        assignments, USER_COLUMNS
+    ))
+    .bind(user_id)
+    .bind(reason)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    if user.is_some() {
+        record_action(&mut tx, context, user_id, action, json!({ "reason": reason })).await?;
+    }
+    tx.commit().await?;
+    Ok(user)
+}
+
+pub async fn disable_user(
+    pool: &Pool<Postgres>,
+    context: &AuditContext,
+    user_id: i32,
+    reason: Option<&str>,
+) -> Result<Option<UserSummary>, sqlx::Error> {
+    tracing::info!(user_id, "Disabling user");
+    update_user_status(
+        pool,
+        context,
+        user_id,
+        "disable",
+        "disabled_at = COALESCE(disabled_at, CURRENT_TIMESTAMP), disabled_reason = $2",
+        reason,
+    )
+    .await
+}
+
+pub async fn enable_user(
+    pool: &Pool<Postgres>,
+    context: &AuditContext,
+    user_id: i32,
+    reason: Option<&str>,
+) -> Result<Option<UserSummary>, sqlx::Error> {
+    tracing::info!(user_id, "Enabling user");
+    update_user_status(
+        pool,
+        context,
+        user_id,
+        "enable",
+        "disabled_at = NULL, disabled_reason = NULL",
+        reason,
+    )
+    .await
+}
+
+pub async fn require_password_change(
+    pool: &Pool<Postgres>,
+    context: &AuditContext,
+    user_id: i32,
+    reason: Option<&str>,
+) -> Result<Option<UserSummary>, sqlx::Error> {
+    tracing::info!(user_id, "Forcing password reset");
+    update_user_status(
+        pool,
+        context,
+        user_id,
+        "force_password_reset",
+        "must_change_password = TRUE",
+        reason,
+    )
+    .await
+}
+
+/// Changes a user's role, recording the previous and new role
+pub async fn change_user_role(
+    pool: &Pool<Postgres>,
+    context: &AuditContext,
+    user_id: i32,
+    role: &str,
+    reason: Option<&str>,
+) -> Result<Option<UserSummary>, sqlx::Error> {
+    tracing::info!(user_id, %role, "Changing role");
+    let mut tx = pool.begin().await?;
+    let previous = sqlx::query_scalar::<_, String>("SELECT role FROM users WHERE id = $1 FOR UPDATE")
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+    let Some(previous) = previous else {
+        return Ok(None);
+    };
+
+    let user = sqlx::query_as::<_, UserSummary>(&format!(
+        "UPDATE users SET role = $2 WHERE id = $1 RETURNING {}",
+        USER_COLUMNS
+    ))
+    .bind(user_id)
+    .bind(role)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    record_action(
+        &mut tx,
+        context,
+        user_id,
+        "change_role",
+        json!({ "from": previous, "to": role, "reason": reason }),
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(Some(user))
+}
+
+/// Outcome of deleting a user
+#[derive(Debug, PartialEq, Eq)]
+pub enum UserDeletion {
+    Deleted,
+    NotFound,
+    /// Refused, as the user is the only Owner of these organizations
+    LastOwner(Vec<i32>),
+}
+
+/// Deletes a user and the relationship tuples naming them; memberships cascade.
+/// Users who are the only Owner of an organization are kept.
+pub async fn delete_user(
+    pool: &Pool<Postgres>,
+    context: &AuditContext,
+    user_id: i32,
+) -> Result<UserDeletion, sqlx::Error> {
+    tracing::info!(user_id, "Deleting user");
+    let mut tx = pool.begin().await?;
+
+    let owned = sqlx::query_scalar::<_, i32>(
+        "SELECT organization_id FROM organization_members WHERE user_id = $1 AND role = 'Owner'",
+    )
+    .bind(user_id)
+    .fetch_all(&mut *tx)
+    .await?;
+    let owners = lock_owners(&mut tx, &owned).await?;
+    let sole: Vec<i32> = owned
+        .into_iter()
+        .filter(|organization_id| {
+            owners
+                .iter()
+                .filter(|(owner_org, _)| owner_org == organization_id)
+                .all(|(_, owner)| *owner == user_id)
+        })
+        .collect();
+    if !sole.is_empty() {
+        return Ok(UserDeletion::LastOwner(sole));
+    }
+
+    let deleted = sqlx::query_as::<_, (String, String)>(
+        "DELETE FROM users WHERE id = $1 RETURNING email, role",
+    )
+    .bind(user_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+    let Some((email, role)) = deleted else {
+        return Ok(UserDeletion::NotFound);
+    };
+
+    sqlx::query(
+        "DELETE FROM relation_tuples WHERE subject_type = 'user' AND subject_id = $1 AND subject_relation = ''",
+    )
+    .bind(user_id.to_string())
+    .execute(&mut *tx)
+    .await?;
+
+    record_action(
+        &mut tx,
+        context,
+        user_id,
+        "delete",
+        json!({ "email": email, "role": role }),
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(UserDeletion::Deleted)
+}
+
+/// Administrative actions and role changes applied to a user, newest first
+pub async fn list_admin_actions(
+    pool: &Pool<Postgres>,
+    user_id: i32,
+) -> Result<Vec<AuditEvent>, sqlx::Error> {
+    sqlx::query_as::<_, AuditEvent>(
+        "SELECT * FROM audit_events WHERE target_user_id = $1 AND event_type = ANY($2) ORDER BY id DESC",
+    )
+    .bind(user_id)
+    .bind([
+        AuditEventType::AdminAction.as_str(),
+        AuditEventType::RoleChanged.as_str(),
+    ])
+    .fetch_all(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{db::audit::record_event, test_support::insert_user};
+
+    fn cursor(id: i32, key: CursorKey) -> UserCursor {
+        UserCursor { id, key }
+    }
+
+    fn hex(text: &str) -> String {
+        text.bytes().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn cursors_round_trip_for_every_sort() {
+        let time = DateTime::parse_from_rfc3339("2025-07-01T12:30:00.123456Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let cases = [
+            (UserSort::Id, cursor(42, CursorKey::Int(42))),
+            (UserSort::LoginCount, cursor(7, CursorKey::Int(0))),
+            // The key is everything after the second separator, so it may contain one
+            (UserSort::Email, cursor(3, CursorKey::Text("a|b@example.com".to_string()))),
+            (UserSort::CreatedAt, cursor(9, CursorKey::Time(time))),
+            (UserSort::LastLogin, cursor(9, CursorKey::Time(DateTime::<Utc>::UNIX_EPOCH))),
+        ];
+        for (sort, cursor) in cases {
+            let encoded = cursor.encode(sort);
+            assert!(encoded.bytes().all(|b| b.is_ascii_hexdigit()));
+            let decoded = UserCursor::decode(&encoded, sort).unwrap();
+            assert_eq!(decoded.id, cursor.id);
+            assert_eq!(decoded.encode(sort), encoded);
+        }
+    }
+
+    #[test]
+    fn cursors_carry_the_sort_key_of_the_last_row() {
+        let user = UserSummary {
+            id: 5,
+            firstname: "Ada".to_string(),
+            lastname: "Lovelace".to_string(),
+            email: "Ada@Example.com".to_string(),
+            role: "User".to_string(),
+            created_at: None,
+            last_login: None,
+            login_count: None,
+            profile_picture: None,
+            email_verified: false,
+            disabled_at: None,
+            disabled_reason: None,
+            must_change_password: false,
+        };
+
+        assert_eq!(UserCursor::after(UserSort::Email, &user).encode(UserSort::Email), hex("email|5|ada@example.com"));
+        assert_eq!(UserCursor::after(UserSort::LoginCount, &user).encode(UserSort::LoginCount), hex("login_count|5|0"));
+        assert_eq!(
+            UserCursor::after(UserSort::LastLogin, &user).encode(UserSort::LastLogin),
+            hex("last_login|5|1970-01-01T00:00:00+00:00")
+        );
+    }
+
+    #[test]
+    fn cursors_are_bound_to_their_sort() {
+        let encoded = cursor(1, CursorKey::Int(1)).encode(UserSort::Id);
+        assert_eq!(
+            UserCursor::decode(&encoded, UserSort::LoginCount).err().unwrap(),
+            "Cursor was issued for a different sort"
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_cursors() {
+        for cursor in [
+            "".to_string(),
+            "abc".to_string(),
+            "zz".to_string(),
+            "ff".to_string(),
+            hex("id|1"),
+            hex("id|x|1"),
+            hex("id|1|x"),
+            hex("created_at|1|yesterday"),
+        ] {
+            let sort = if cursor.starts_with(&hex("created_at")) { UserSort::CreatedAt } else { UserSort::Id };
+            assert_eq!(UserCursor::decode(&cursor, sort).err().unwrap(), "Invalid cursor", "{}", cursor);
+        }
+    }
+
+    #[test]
+    fn escapes_like_wildcards() {
+        assert_eq!(escape_like(r"50%_off\"), r"50\%\_off\\");
+        assert_eq!(escape_like("plain"), "plain");
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn cursors_page_through_ties(pool: Pool<Postgres>) {
+        for n in 0..5 {
+            let user = insert_user(&pool, &format!("user{}@example.com", n), "User").await;
+            sqlx::query("UPDATE users SET login_count = $1 WHERE id = $2")
+                .bind(n % 2)
+                .bind(user.id)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+
+        let mut seen = Vec::new();
+        let mut after = None;
+        loop {
+            let page = list_users(&pool, &UserFilter::default(), UserSort::LoginCount, SortOrder::Desc, 2, after.as_ref())
+                .await
+                .unwrap();
+            seen.extend(page.users.iter().map(|user| (user.login_count.unwrap(), user.id)));
+            match page.next_cursor {
+                Some(cursor) => after = Some(UserCursor::decode(&cursor, UserSort::LoginCount).unwrap()),
+                None => break,
+            }
+        }
+
+        let mut expected = seen.clone();
+        expected.sort_by(|a, b| b.cmp(a));
+        assert_eq!(seen.len(), 5);
+        assert_eq!(seen, expected);
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn admin_history_comes_from_the_audit_log(pool: Pool<Postgres>) {
+        let admin = insert_user(&pool, "admin@example.com", "Admin").await;
+        let user = insert_user(&pool, "user@example.com", "User").await;
+        let context = AuditContext {
+            actor_id: Some(admin.id),
+            ..Default::default()
+        };
+
+        change_user_role(&pool, &context, user.id, "Moderator", None).await.unwrap();
+        disable_user(&pool, &context, user.id, Some("spam")).await.unwrap();
+        record_event(&pool, &context, AuditEventType::LoginSucceeded, Some(user.id), json!({})).await;
+
+        let history = list_admin_actions(&pool, user.id).await.unwrap();
+        let actions: Vec<(&str, &Value)> = history
+            .iter()
+            .map(|event| (event.event_type.as_str(), &event.details["action"]))
+            .collect();
+        assert_eq!(
+            actions,
+            [("admin_action", &json!("disable")), ("role_changed", &json!("change_role"))]
+        );
+        assert_eq!(history[1].details["from"], "User");
+        assert_eq!(history[1].actor_id, Some(admin.id));
+    }
+}
@@ -0,0 +1,5 @@
+pub mod service;
+
+pub mod proto {
+    tonic::include_proto!("auth.v1");
+}
@@ -0,0 +1,396 @@
+use axum::http::StatusCode;
+use serde_json::json;
+use sqlx::{Pool, Postgres};
+use std::future::Future;
+use std::net::SocketAddr;
+use tonic::{transport::Server, Request, Response, Status};
+
+use crate::{
+    db::{
+        queries::{get_user_by_email, get_user_by_id},
+        roles::get_user_roles,
+    },
+    grpc::proto::{
+        auth_service_server::{AuthService, AuthServiceServer},
+        get_user_request::Lookup,
+        CheckPermissionRequest, CheckPermissionResponse, GetUserRequest, UserReply,
+        ValidateTokenRequest, ValidateTokenResponse,
+    },
+    middleware::{
+        auth::{authenticate, record_impersonated_request, Claims},
+        client::ClientInfo,
+        permissions::PermissionCache,
+    },
+    models::{role::EffectivePermissions, user::User},
+    state::AppState,
+};
+
+/// gRPC counterpart of the HTTP auth middleware, sharing its pool and permission cache
+pub struct AuthGrpc {
+    pool: Pool<Postgres>,
+    permissions: PermissionCache,
+}
+
+impl AuthGrpc {
+    pub fn new(state: &AppState) -> Self {
+        Self {
+            pool: state.pool.clone(),
+            permissions: state.permissions.clone(),
+        }
+    }
+
+    /// Authenticates `token` for the named `rpc`; calls made with an
+    /// impersonation token are audited as the HTTP middleware does
+    async fn authenticate(
+        &self,
+        client: &ClientInfo,
+        rpc: &str,
+        token: &str,
+    ) -> Result<(Claims, User, EffectivePermissions), Status> {
+        let (claims, user, permissions) = authenticate(&self.pool, &self.permissions, token)
+            .await
+            .map_err(|(status, message)| match status {
+                StatusCode::UNAUTHORIZED => Status::unauthenticated(message),
+                _ => Status::internal(message),
+            })?;
+        record_impersonated_request(&self.pool, client, &claims, user.id, json!({ "rpc": rpc })).await;
+        Ok((claims, user, permissions))
+    }
+}
+
+/// Network details of a gRPC caller, for the audit log; the peer address is
+/// taken as is
+fn client_info<T>(request: &Request<T>) -> ClientInfo {
+    let metadata = |name: &str| {
+        request
+            .metadata()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string())
+    };
+    ClientInfo {
+        ip: request.remote_addr().map(|addr| addr.ip()),
+        user_agent: metadata("user-agent"),
+        request_id: metadata("x-request-id"),
+    }
+}
+
+impl From<User> for UserReply {
+    fn from(user: User) -> Self {
+        Self {
+            id: user.id,
+            firstname: user.firstname,
+            lastname: user.lastname,
+            email: user.email,
+            role: user.role,
+            email_verified: user.email_verified,
+            profile_picture: user.profile_picture,
+            created_at: user.created_at.map(|t| t.to_rfc3339()),
+            last_login: user.last_login.map(|t| t.to_rfc3339()),
+            login_count: user.login_count.unwrap_or_default(),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl AuthService for AuthGrpc {
+    async fn validate_token(
+        &self,
+        request: Request<ValidateTokenRequest>,
+    ) -> Result<Response<ValidateTokenResponse>, Status> {
+        let client = client_info(&request);
+        let request = request.into_inner();
+        let (claims, user, permissions) = match self.authenticate(&client, "ValidateToken", &request.token).await {
+            Ok(authenticated) => authenticated,
+            Err(status) if status.code() == tonic::Code::Unauthenticated => {
+                return Ok(Response::new(ValidateTokenResponse {
+                    valid: false,
+                    error: status.message().to_string(),
+                    ..Default::default()
+                }))
+            }
+            Err(status) => return Err(status),
+        };
+
+        let mut permissions: Vec<String> = permissions.iter().cloned().collect();
+        permissions.sort();
+
+        Ok(Response::new(ValidateTokenResponse {
+            valid: true,
+            error: String::new(),
+            user: Some(user.into()),
+            permissions,
+            organization: claims.org,
+            scope: claims.scope,
+            expires_at: claims.exp as i64,
+            impersonated_by: claims.act.map(|actor| actor.sub),
+        }))
+    }
+
+    async fn check_permission(
+        &self,
+        request: Request<CheckPermissionRequest>,
+    ) -> Result<Response<CheckPermissionResponse>, Status> {
+        let client = client_info(&request);
+        let request = request.into_inner();
+        let (_, user, permissions) = self.authenticate(&client, "CheckPermission", &request.token).await?;
+
+        let deny = |reason: String| {
+            Response::new(CheckPermissionResponse {
+                allowed: false,
+                reason,
+            })
+        };
+
+        if !permissions.contains(&request.permission) {
+            return Ok(deny(format!("Missing permission: {}", request.permission)));
+        }
+
+        if let Some(role) = request.role {
+            let roles = get_user_roles(&self.pool, user.id)
+                .await
+                .map_err(|_| Status::internal("Failed to resolve roles"))?;
+            if !roles.contains(&role) {
+                return Ok(deny(format!("Requires role: {}", role)));
+            }
+        }
+
+        Ok(Response::new(CheckPermissionResponse {
+            allowed: true,
+            reason: format!("Granted {}", request.permission),
+        }))
+    }
+
+    async fn get_user(
+        &self,
+        request: Request<GetUserRequest>,
+    ) -> Result<Response<UserReply>, Status> {
+        let client = client_info(&request);
+        let request = request.into_inner();
+        let (_, caller, permissions) = self.authenticate(&client, "GetUser", &request.token).await?;
+
+        let user = match request.lookup {
+            Some(Lookup::Id(id)) if id == caller.id => caller,
+            Some(Lookup::Email(email)) if email == caller.email => caller,
+            Some(lookup) => {
+                if !permissions.contains("users:read") {
+                    return Err(Status::permission_denied("Missing permission: users:read"));
+                }
+                match lookup {
+                    Lookup::Id(id) => get_user_by_id(&self.pool, id).await,
+                    Lookup::Email(email) => get_user_by_email(&self.pool, &email).await,
+                }
+                .map_err(|e| match e {
+                    sqlx::Error::RowNotFound => Status::not_found("User not found"),
+                    _ => Status::internal("Failed to load user"),
+                })?
+            }
+            None => caller,
+        };
+
+        Ok(Response::new(user.into()))
+    }
+}
+
+/// Serves the gRPC API on `addr` until `shutdown` resolves, then lets
+/// in-flight calls finish
+pub async fn serve(
+    state: AppState,
+    addr: SocketAddr,
+    shutdown: impl Future<Output = ()>,
+) -> Result<(), tonic::transport::Error> {
+    tracing::info!(%addr, "gRPC server listening");
+    Server::builder()
+        .add_service(AuthServiceServer::new(AuthGrpc::new(&state)))
+        .serve_with_shutdown(addr, shutdown)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::config::{app_config, init_for_tests},
+        db::sessions::{create_session, revoke_session},
+        grpc::proto::auth_service_client::AuthServiceClient,
+        middleware::auth::{create_impersonation_token, create_token, token_ttl},
+        models::audit::AuditEvent,
+        test_support::insert_user,
+    };
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use sqlx::postgres::PgPoolOptions;
+    use tokio::net::TcpListener;
+    use tonic::{transport::{server::TcpIncoming, Channel}, Code};
+
+    /// Serves the API on a local port and connects a client to it
+    async fn start(pool: Pool<Postgres>) -> AuthServiceClient<Channel> {
+        init_for_tests();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let incoming = TcpIncoming::from_listener(listener, true, None).unwrap();
+        let service = AuthServiceServer::new(AuthGrpc {
+            pool,
+            permissions: PermissionCache::default(),
+        });
+        tokio::spawn(Server::builder().add_service(service).serve_with_incoming(incoming));
+        AuthServiceClient::connect(format!("http://{}", addr)).await.unwrap()
+    }
+
+    /// Opens a session for `user` and mints a token bound to it
+    async fn session_token(pool: &Pool<Postgres>, user: &User) -> (String, uuid::Uuid) {
+        let expires_at = chrono::Utc::now() + token_ttl();
+        let sid = create_session(pool, user.id, None, None, "password", expires_at)
+            .await
+            .unwrap();
+        (create_token(&user.email, &user.role, None, None, Some(sid)), sid)
+    }
+
+    fn check(token: &str, permission: &str, role: Option<&str>) -> CheckPermissionRequest {
+        CheckPermissionRequest {
+            token: token.to_string(),
+            permission: permission.to_string(),
+            role: role.map(|role| role.to_string()),
+        }
+    }
+
+    fn get_user(token: &str, lookup: Option<Lookup>) -> GetUserRequest {
+        GetUserRequest {
+            token: token.to_string(),
+            lookup,
+        }
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn valid_tokens_resolve_user_and_permissions(pool: Pool<Postgres>) {
+        let mut client = start(pool.clone()).await;
+        let user = insert_user(&pool, "user@example.com", "User").await;
+        let (token, _) = session_token(&pool, &user).await;
+
+        let validated = client
+            .validate_token(ValidateTokenRequest { token: token.clone() })
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(validated.valid, "{}", validated.error);
+        assert_eq!(validated.user.unwrap().email, "user@example.com");
+        assert!(validated.permissions.contains(&"profile:read".to_string()));
+        assert!(!validated.permissions.contains(&"users:read".to_string()));
+
+        let granted = client.check_permission(check(&token, "profile:read", Some("User"))).await.unwrap();
+        assert!(granted.into_inner().allowed);
+        let missing = client.check_permission(check(&token, "users:read", None)).await.unwrap();
+        assert!(!missing.into_inner().allowed);
+        let wrong_role = client.check_permission(check(&token, "profile:read", Some("Admin"))).await.unwrap();
+        assert!(!wrong_role.into_inner().allowed);
+
+        let own = client.get_user(get_user(&token, None)).await.unwrap().into_inner();
+        assert_eq!(own.id, user.id);
+        let other = client.get_user(get_user(&token, Some(Lookup::Id(user.id + 1)))).await;
+        assert_eq!(other.unwrap_err().code(), Code::PermissionDenied);
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn impersonated_calls_are_audited(pool: Pool<Postgres>) {
+        let mut client = start(pool.clone()).await;
+        let admin = insert_user(&pool, "admin@example.com", "Admin").await;
+        let user = insert_user(&pool, "user@example.com", "User").await;
+        let (token, _) = create_impersonation_token(&user, &admin, "profile:read", chrono::Duration::minutes(5));
+
+        let validated = client
+            .validate_token(ValidateTokenRequest { token: token.clone() })
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(validated.impersonated_by.as_deref(), Some("admin@example.com"));
+        client.check_permission(check(&token, "profile:read", None)).await.unwrap();
+        client.get_user(get_user(&token, None)).await.unwrap();
+        let (own, _) = session_token(&pool, &user).await;
+        client.get_user(get_user(&own, None)).await.unwrap();
+
+        let events: Vec<AuditEvent> = sqlx::query_as(
+            "SELECT * FROM audit_events WHERE details->>'action' = 'impersonated_request' ORDER BY id",
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        let rpcs: Vec<_> = events.iter().map(|event| event.details["rpc"].clone()).collect();
+        assert_eq!(rpcs, ["ValidateToken", "CheckPermission", "GetUser"]);
+        for event in events {
+            assert_eq!((event.actor_id, event.target_user_id), (Some(admin.id), Some(user.id)));
+            assert_eq!(event.ip.as_deref(), Some("127.0.0.1"));
+        }
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn users_read_allows_looking_up_other_users(pool: Pool<Postgres>) {
+        let mut client = start(pool.clone()).await;
+        let moderator = insert_user(&pool, "moderator@example.com", "Moderator").await;
+        let user = insert_user(&pool, "user@example.com", "User").await;
+        let (token, _) = session_token(&pool, &moderator).await;
+
+        let by_email = client
+            .get_user(get_user(&token, Some(Lookup::Email(user.email.clone()))))
+            .await
+            .unwrap();
+        assert_eq!(by_email.into_inner().id, user.id);
+        let unknown = client.get_user(get_user(&token, Some(Lookup::Id(user.id + 100)))).await;
+        assert_eq!(unknown.unwrap_err().code(), Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn expired_tokens_are_rejected() {
+        // The token is refused before any query, so the pool never connects
+        let pool = PgPoolOptions::new().connect_lazy("postgres://localhost/unused").unwrap();
+        let mut client = start(pool).await;
+        let claims = Claims {
+            sub: "user@example.com".to_string(),
+            role: "User".to_string(),
+            org: None,
+            scope: None,
+            sid: None,
+            act: None,
+            exp: (chrono::Utc::now() - chrono::Duration::hours(1)).timestamp() as usize,
+        };
+        let key = EncodingKey::from_secret(app_config().auth.jwt_secret.expose().as_bytes());
+        let token = encode(&Header::default(), &claims, &key).unwrap();
+
+        let validated = client
+            .validate_token(ValidateTokenRequest { token: token.clone() })
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(!validated.valid);
+        assert_eq!(validated.error, "Invalid token");
+        assert!(validated.user.is_none());
+
+        let checked = client.check_permission(check(&token, "profile:read", None)).await;
+        assert_eq!(checked.unwrap_err().code(), Code::Unauthenticated);
+        let user = client.get_user(get_user(&token, None)).await;
+        assert_eq!(user.unwrap_err().code(), Code::Unauthenticated);
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn revoked_sessions_are_rejected(pool: Pool<Postgres>) {
+        let mut client = start(pool.clone()).await;
+        let user = insert_user(&pool, "user@example.com", "User").await;
+        let (token, sid) = session_token(&pool, &user).await;
+        assert!(revoke_session(&pool, user.id, sid).await.unwrap());
+
+        let validated = client
+            .validate_token(ValidateTokenRequest { token: token.clone() })
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(!validated.valid);
+        assert_eq!(validated.error, "Session revoked");
+
+        let checked = client.check_permission(check(&token, "profile:read", None)).await;
+        assert_eq!(checked.unwrap_err().code(), Code::Unauthenticated);
+        let fetched = client.get_user(get_user(&token, None)).await;
+        assert_eq!(fetched.unwrap_err().code(), Code::Unauthenticated);
+    }
+}
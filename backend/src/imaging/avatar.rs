@@ -0,0 +1,341 @@
+use image::{codecs::png::PngEncoder, ExtendedColorType, ImageEncoder, Rgb, RgbImage};
+use sha2::{Digest, Sha256};
+
+/// Avatars are laid out on a 100x100 canvas, then scaled to the requested size
+const CANVAS: f32 = 100.0;
+/// Glyphs are drawn on a 4x6 grid
+const GLYPH_WIDTH: f32 = 4.0;
+const GLYPH_HEIGHT: f32 = 6.0;
+const GLYPH_GAP: f32 = 1.6;
+/// Height of the initials, on the canvas
+const TEXT_HEIGHT: f32 = 38.0;
+const STROKE_WIDTH: f32 = 6.5;
+/// Cells of the identicon grid, plus a half-cell margin on each side
+const IDENTICON_CELLS: usize = 5;
+
+/// A glyph as polylines of grid points
+type Glyph = &'static [&'static [(f32, f32)]];
+
+/// Stroke font for A-Z. Drawing initials from strokes rather than a font keeps
+/// SVG and PNG output identical on every client, whatever fonts it has.
+const GLYPHS: [Glyph; 26] = [
+    &[&[(0.0, 6.0), (2.0, 0.0), (4.0, 6.0)], &[(0.7, 4.0), (3.3, 4.0)]],
+    &[
+        &[(0.0, 3.0), (3.0, 3.0), (4.0, 4.0), (4.0, 5.0), (3.0, 6.0), (0.0, 6.0), (0.0, 0.0), (2.8, 0.0), (3.8, 1.0), (3.8, 2.0), (2.8, 3.0)],
+    ],
+    &[&[(4.0, 1.0), (3.0, 0.0), (1.0, 0.0), (0.0, 1.0), (0.0, 5.0), (1.0, 6.0), (3.0, 6.0), (4.0, 5.0)]],
+    &[&[(0.0, 0.0), (0.0, 6.0), (2.5, 6.0), (4.0, 4.5), (4.0, 1.5), (2.5, 0.0), (0.0, 0.0)]],
+    &[&[(4.0, 0.0), (0.0, 0.0), (0.0, 6.0), (4.0, 6.0)], &[(0.0, 3.0), (3.0, 3.0)]],
+    &[&[(4.0, 0.0), (0.0, 0.0), (0.0, 6.0)], &[(0.0, 3.0), (3.0, 3.0)]],
+    &[
+        &[(4.0, 1.0), (3.0, 0.0), (1.0, 0.0), (0.0, 1.0), (0.0, 5.0), (1.0, 6.0), (3.0, 6.0), (4.0, 5.0), (4.0, 3.5), (2.5, 3.5)],
+    ],
+    &[&[(0.0, 0.0), (0.0, 6.0)], &[(4.0, 0.0), (4.0, 6.0)], &[(0.0, 3.0), (4.0, 3.0)]],
+    &[&[(1.0, 0.0), (3.0, 0.0)], &[(2.0, 0.0), (2.0, 6.0)], &[(1.0, 6.0), (3.0, 6.0)]],
+    &[&[(4.0, 0.0), (4.0, 5.0), (3.0, 6.0), (1.0, 6.0), (0.0, 5.0)]],
+    &[&[(0.0, 0.0), (0.0, 6.0)], &[(4.0, 0.0), (0.0, 4.0)], &[(1.5, 2.5), (4.0, 6.0)]],
+    &[&[(0.0, 0.0), (0.0, 6.0), (4.0, 6.0)]],
+    &[&[(0.0, 6.0), (0.0, 0.0), (2.0, 3.5), (4.0, 0.0), (4.0, 6.0)]],
+    &[&[(0.0, 6.0), (0.0, 0.0), (4.0, 6.0), (4.0, 0.0)]],
+    &[&[(1.0, 0.0), (3.0, 0.0), (4.0, 1.0), (4.0, 5.0), (3.0, 6.0), (1.0, 6.0), (0.0, 5.0), (0.0, 1.0), (1.0, 0.0)]],
+    &[&[(0.0, 6.0), (0.0, 0.0), (3.0, 0.0), (4.0, 1.0), (4.0, 2.0), (3.0, 3.0), (0.0, 3.0)]],
+    &[
+        &[(1.0, 0.0), (3.0, 0.0), (4.0, 1.0), (4.0, 5.0), (3.0, 6.0), (1.0, 6.0), (0.0, 5.0), (0.0, 1.0), (1.0, 0.0)],
+        &[(2.5, 4.5), (4.0, 6.0)],
+    ],
+    &[&[(0.0, 6.0), (0.0, 0.0), (3.0, 0.0), (4.0, 1.0), (4.0, 2.0), (3.0, 3.0), (0.0, 3.0)], &[(2.0, 3.0), (4.0, 6.0)]],
+    &[
+        &[(4.0, 1.0), (3.0, 0.0), (1.0, 0.0), (0.0, 1.0), (0.0, 2.0), (1.0, 3.0), (3.0, 3.0), (4.0, 4.0), (4.0, 5.0), (3.0, 6.0), (1.0, 6.0), (0.0, 5.0)],
+    ],
+    &[&[(0.0, 0.0), (4.0, 0.0)], &[(2.0, 0.0), (2.0, 6.0)]],
+    &[&[(0.0, 0.0), (0.0, 5.0), (1.0, 6.0), (3.0, 6.0), (4.0, 5.0), (4.0, 0.0)]],
+    &[&[(0.0, 0.0), (2.0, 6.0), (4.0, 0.0)]],
+    &[&[(0.0, 0.0), (1.0, 6.0), (2.0, 2.5), (3.0, 6.0), (4.0, 0.0)]],
+    &[&[(0.0, 0.0), (4.0, 6.0)], &[(4.0, 0.0), (0.0, 6.0)]],
+    &[&[(0.0, 0.0), (2.0, 3.0), (4.0, 0.0)], &[(2.0, 3.0), (2.0, 6.0)]],
+    &[&[(0.0, 0.0), (4.0, 0.0), (0.0, 6.0), (4.0, 6.0)]],
+];
+
+/// The drawable shapes of an avatar, on the 100x100 canvas
+enum Shape {
+    /// Round-capped strokes, in white
+    Strokes(Vec<Vec<(f32, f32)>>),
+    /// Filled squares of the given edge, in white
+    Cells { cells: Vec<(f32, f32)>, edge: f32 },
+}
+
+/// A deterministic generated avatar
+pub struct Avatar {
+    background: Rgb<u8>,
+    shape: Shape,
+}
+
+/// Maps a letter to its unaccented capital, for the common Latin-1 letters
+fn fold_letter(c: char) -> Option<char> {
+    const FOLDS: [(&str, char); 9] = [
+        ("ÀÁÂÃÄÅàáâãäå", 'A'),
+        ("Çç", 'C'),
+        ("ÈÉÊËèéêë", 'E'),
+        ("ÌÍÎÏìíîï", 'I'),
+        ("Ññ", 'N'),
+        ("ÒÓÔÕÖØòóôõöø", 'O'),
+        ("ÙÚÛÜùúûü", 'U'),
+        ("Ýýÿ", 'Y'),
+        ("ß", 'S'),
+    ];
+    if c.is_ascii_alphabetic() {
+        return Some(c.to_ascii_uppercase());
+    }
+    FOLDS.iter().find(|(from, _)| from.contains(c)).map(|(_, to)| *to)
+}
+
+/// Initials of the name, or `None` if a part does not start with a drawable letter
+pub fn initials(firstname: &str, lastname: &str) -> Option<String> {
+    [firstname, lastname]
+        .iter()
+        .filter_map(|part| part.trim().chars().next())
+        .map(fold_letter)
+        .collect::<Option<String>>()
+        .filter(|initials| !initials.is_empty())
+}
+
+fn hsl_to_rgb(hue: f32, saturation: f32, lightness: f32) -> Rgb<u8> {
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let x = chroma * (1.0 - ((hue / 60.0) % 2.0 - 1.0).abs());
+    let (r, g, b) = match hue as u32 / 60 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = lightness - chroma / 2.0;
+    let channel = |v: f32| ((v + m) * 255.0).round() as u8;
+    Rgb([channel(r), channel(g), channel(b)])
+}
+
+impl Avatar {
+    /// Initials over a background hashed from the user id; the colour stays
+    /// the same when the user renames themselves
+    pub fn initials(user_id: i32, initials: &str) -> Self {
+        let hash = Sha256::digest(format!("avatar:{}", user_id));
+        let advance = GLYPH_WIDTH + GLYPH_GAP;
+        let letters: Vec<usize> = initials
+            .bytes()
+            .filter(u8::is_ascii_uppercase)
+            .map(|b| (b - b'A') as usize)
+            .collect();
+        let width = letters.len() as f32 * advance - GLYPH_GAP;
+        let scale = TEXT_HEIGHT / GLYPH_HEIGHT;
+        let (left, top) = ((CANVAS - width * scale) / 2.0, (CANVAS - TEXT_HEIGHT) / 2.0);
+
+        let strokes = letters
+            .iter()
+            .enumerate()
+            .flat_map(|(i, letter)| {
+                GLYPHS[*letter].iter().map(move |line| {
+                    line.iter()
+                        .map(|(x, y)| (left + (i as f32 * advance + x) * scale, top + y * scale))
+                        .collect()
+                })
+            })
+            .collect();
+
+        Avatar {
+            background: background(&hash),
+            shape: Shape::Strokes(strokes),
+        }
+    }
+
+    /// A horizontally symmetric 5x5 pattern hashed from the user id
+    pub fn identicon(user_id: i32) -> Self {
+        let hash = Sha256::digest(format!("identicon:{}", user_id));
+        let edge = CANVAS / (IDENTICON_CELLS + 1) as f32;
+        let margin = edge / 2.0;
+        let mut cells = Vec::new();
+        for row in 0..IDENTICON_CELLS {
+            for column in 0..IDENTICON_CELLS.div_ceil(2) {
+                // One bit per cell of the left half, skipping the colour bytes
+                let bit = row * 3 + column;
+                if hash[4 + bit / 8] & (1 << (bit % 8)) == 0 {
+                    continue;
+                }
+                for column in [column, IDENTICON_CELLS - 1 - column] {
+                    let cell = (margin + column as f32 * edge, margin + row as f32 * edge);
+                    if !cells.contains(&cell) {
+                        cells.push(cell);
+                    }
+                }
+            }
+        }
+
+        Avatar {
+            background: background(&hash),
+            shape: Shape::Cells { cells, edge },
+        }
+    }
+
+    pub fn to_svg(&self, size: u32) -> String {
+        let [r, g, b] = self.background.0;
+        let content = match &self.shape {
+            Shape::Strokes(strokes) => {
+                let path: String = strokes
+                    .iter()
+                    .map(|line| {
+                        line.iter()
+                            .enumerate()
+                            .map(|(i, (x, y))| format!("{}{:.2} {:.2}", if i == 0 { "M" } else { "L" }, x, y))
+                            .collect::<String>()
+                    })
+                    .collect();
+                format!(
+                    r##"<path d="{}" fill="none" stroke="#ffffff" stroke-width="{}" stroke-linecap="round" stroke-linejoin="round"/>"##,
+                    path, STROKE_WIDTH
+                )
+            }
+            Shape::Cells { cells, edge } => cells
+                .iter()
+                .map(|(x, y)| {
+                    format!(
+                        r##"<rect x="{:.2}" y="{:.2}" width="{:.2}" height="{:.2}" fill="#ffffff"/>"##,
+                        x, y, edge, edge
+                    )
+                })
+                .collect(),
+        };
+        format!(
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="{size}" height="{size}" viewBox="0 0 {canvas} {canvas}"><rect width="{canvas}" height="{canvas}" fill="#{r:02x}{g:02x}{b:02x}"/>{content}</svg>"##,
+            size = size,
+            canvas = CANVAS,
+            r = r,
+            g = g,
+            b = b,
+            content = content,
+        )
+    }
+
+    /// Rasterizes the same geometry as `to_svg`, anti-aliased
+    pub fn to_png(&self, size: u32) -> Result<Vec<u8>, String> {
+        let scale = size as f32 / CANVAS;
+        let mut image = RgbImage::from_pixel(size, size, self.background);
+        for (px, py, pixel) in image.enumerate_pixels_mut() {
+            // Pixel centre, on the canvas
+            let (x, y) = ((px as f32 + 0.5) / scale, (py as f32 + 0.5) / scale);
+            let coverage = match &self.shape {
+                Shape::Strokes(strokes) => {
+                    let distance = strokes
+                        .iter()
+                        .flat_map(|line| line.windows(2))
+                        .map(|segment| segment_distance((x, y), segment[0], segment[1]))
+                        .fold(f32::MAX, f32::min);
+                    // Within half a pixel of the stroke edge, blend proportionally
+                    ((STROKE_WIDTH / 2.0 - distance) * scale + 0.5).clamp(0.0, 1.0)
+                }
+                Shape::Cells { cells, edge } => {
+                    let inside = cells
+                        .iter()
+                        .any(|(cx, cy)| x >= *cx && x < cx + edge && y >= *cy && y < cy + edge);
+                    if inside { 1.0 } else { 0.0 }
+                }
+            };
+            for channel in &mut pixel.0 {
+                *channel = (*channel as f32 + (255.0 - *channel as f32) * coverage).round() as u8;
+            }
+        }
+
+        let mut out = Vec::new();
+        PngEncoder::new(&mut out)
+            .write_image(&image, size, size, ExtendedColorType::Rgb8)
+            .map_err(|e| format!("Failed to encode PNG: {}", e))?;
+        Ok(out)
+    }
+}
+
+/// Mid-saturation background with a hue taken from the hash, dark enough for white
+fn background(hash: &[u8]) -> Rgb<u8> {
+    let hue = u16::from_be_bytes([hash[0], hash[1]]) % 360;
+    hsl_to_rgb(hue as f32, 0.55, 0.45)
+}
+
+fn segment_distance(p: (f32, f32), a: (f32, f32), b: (f32, f32)) -> f32 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let length = dx * dx + dy * dy;
+    let t = if length == 0.0 {
+        0.0
+    } else {
+        (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / length).clamp(0.0, 1.0)
+    };
+    let (cx, cy) = (a.0 + t * dx, a.1 + t * dy);
+    ((p.0 - cx).powi(2) + (p.1 - cy).powi(2)).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn initials_take_the_first_letter_of_each_part() {
+        assert_eq!(initials("Ada", "Lovelace").as_deref(), Some("AL"));
+        assert_eq!(initials("  grace ", "hopper").as_deref(), Some("GH"));
+        assert_eq!(initials("Cher", "").as_deref(), Some("C"));
+        assert_eq!(initials("", "  ").as_deref(), None);
+    }
+
+    #[test]
+    fn initials_fold_accented_letters() {
+        assert_eq!(initials("Émile", "Zola").as_deref(), Some("EZ"));
+        assert_eq!(initials("Øystein", "Åberg").as_deref(), Some("OA"));
+        assert_eq!(initials("ßen", "Müller").as_deref(), Some("SM"));
+    }
+
+    #[test]
+    fn initials_reject_undrawable_letters() {
+        assert_eq!(initials("Лев", "Толстой"), None);
+        assert_eq!(initials("Ada", "李"), None);
+        assert_eq!(initials("1st", "User"), None);
+    }
+
+    #[test]
+    fn avatars_are_deterministic() {
+        assert_eq!(Avatar::initials(7, "AL").to_svg(64), Avatar::initials(7, "AL").to_svg(64));
+        assert_eq!(Avatar::identicon(7).to_png(32).unwrap(), Avatar::identicon(7).to_png(32).unwrap());
+        assert_ne!(Avatar::identicon(7).to_svg(64), Avatar::identicon(8).to_svg(64));
+    }
+
+    #[test]
+    fn background_follows_the_id_not_the_name() {
+        let background = |svg: String| svg.split("fill=\"#").nth(1).unwrap()[..6].to_string();
+        assert_eq!(
+            background(Avatar::initials(7, "AL").to_svg(64)),
+            background(Avatar::initials(7, "GH").to_svg(64))
+        );
+        assert_ne!(
+            background(Avatar::initials(7, "AL").to_svg(64)),
+            background(Avatar::initials(8, "AL").to_svg(64))
+        );
+    }
+
+    #[test]
+    fn identicons_are_symmetric() {
+        let Shape::Cells { cells, edge } = Avatar::identicon(42).shape else {
+            panic!("identicon draws cells");
+        };
+        for (x, y) in &cells {
+            let mirrored = (CANVAS - edge - x, *y);
+            assert!(
+                cells.iter().any(|(mx, my)| (mx - mirrored.0).abs() < 0.01 && *my == mirrored.1),
+                "cell at ({}, {}) has no mirror",
+                x,
+                y
+            );
+        }
+    }
+
+    #[test]
+    fn png_has_the_requested_size() {
+        let png = Avatar::initials(1, "AB").to_png(48).unwrap();
+        let image = image::load_from_memory(&png).unwrap();
+        assert_eq!((image.width(), image.height()), (48, 48));
+    }
+}
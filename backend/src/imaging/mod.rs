@@ -0,0 +1,284 @@
+pub mod avatar;
+
+use image::{
+    codecs::{jpeg::JpegEncoder, webp::WebPEncoder},
+    imageops::FilterType,
+    DynamicImage, ExtendedColorType, ImageDecoder, ImageEncoder, ImageFormat, ImageReader, Limits,
+};
+use std::fmt;
+use std::io::Cursor;
+
+/// Largest accepted upload
+pub const MAX_PHOTO_BYTES: usize = 10 * 1024 * 1024;
+/// Edge lengths of the square variants generated for every photo
+pub const PHOTO_SIZES: [u32; 3] = [64, 256, 512];
+/// Variant served when a photo set is requested without picking one
+pub const DEFAULT_PHOTO_SIZE: u32 = 256;
+
+const MIN_DIMENSION: u32 = 32;
+const MAX_DIMENSION: u32 = 8192;
+/// Caps decoder allocations, so a small file cannot expand into gigabytes of pixels
+const MAX_DECODE_ALLOC: u64 = 256 * 1024 * 1024;
+const JPEG_QUALITY: u8 = 85;
+
+/// Why a photo could not be processed
+#[derive(Debug)]
+pub enum PhotoError {
+    /// The upload is not an acceptable image; the message is safe to show the user
+    Rejected(String),
+    /// Processing failed on our side
+    Failed(String),
+}
+
+impl fmt::Display for PhotoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PhotoError::Rejected(message) | PhotoError::Failed(message) => f.write_str(message),
+        }
+    }
+}
+
+/// An encoded, square variant of a photo
+pub struct PhotoVariant {
+    pub size: u32,
+    /// `jpg` or `webp`
+    pub extension: &'static str,
+    pub content_type: &'static str,
+    pub data: Vec<u8>,
+}
+
+impl PhotoVariant {
+    /// Name of the variant within its set, e.g. `256.webp`
+    pub fn file_name(&self) -> String {
+        format!("{}.{}", self.size, self.extension)
+    }
+}
+
+/// Validates an uploaded photo and re-encodes it into every size, as JPEG and WebP.
+///
+/// The type is detected from the content, never the file name. Decoding to
+/// pixels and encoding afresh drops all metadata, EXIF GPS tags included,
+/// after the EXIF orientation has been applied. CPU heavy: call it from
+/// `spawn_blocking`.
+pub fn process_photo(data: &[u8]) -> Result<Vec<PhotoVariant>, PhotoError> {
+    if data.len() > MAX_PHOTO_BYTES {
+        return Err(PhotoError::Rejected(format!(
+            "Photo is larger than {} MB",
+            MAX_PHOTO_BYTES / (1024 * 1024)
+        )));
+    }
+
+    let format = image::guess_format(data)
+        .ok()
+        .filter(|format| {
+            matches!(
+                format,
+                ImageFormat::Jpeg | ImageFormat::Png | ImageFormat::Gif | ImageFormat::WebP
+            )
+        })
+        .ok_or_else(|| {
+            PhotoError::Rejected("Unsupported image. Allowed formats: jpeg, png, gif, webp".to_string())
+        })?;
+
+    let mut reader = ImageReader::with_format(Cursor::new(data), format);
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_DIMENSION);
+    limits.max_image_height = Some(MAX_DIMENSION);
+    limits.max_alloc = Some(MAX_DECODE_ALLOC);
+    reader.limits(limits);
+
+    let rejected = |e: image::ImageError| PhotoError::Rejected(format!("Invalid image: {}", e));
+    let mut decoder = reader.into_decoder().map_err(rejected)?;
+    let (width, height) = decoder.dimensions();
+    if width < MIN_DIMENSION || height < MIN_DIMENSION {
+        return Err(PhotoError::Rejected(format!(
+            "Photo must be at least {}x{} pixels",
+            MIN_DIMENSION, MIN_DIMENSION
+        )));
+    }
+    let orientation = decoder.orientation().map_err(rejected)?;
+    let mut photo = DynamicImage::from_decoder(decoder).map_err(rejected)?;
+    photo.apply_orientation(orientation);
+
+    let mut variants = Vec::with_capacity(PHOTO_SIZES.len() * 2);
+    for size in PHOTO_SIZES {
+        let square = photo.resize_to_fill(size, size, FilterType::Lanczos3);
+        variants.push(PhotoVariant {
+            size,
+            extension: "jpg",
+            content_type: "image/jpeg",
+            data: encode_jpeg(&square)?,
+        });
+        variants.push(PhotoVariant {
+            size,
+            extension: "webp",
+            content_type: "image/webp",
+            data: encode_webp(&square)?,
+        });
+    }
+    Ok(variants)
+}
+
+/// JPEG has no alpha channel, so transparent areas are flattened onto white
+fn encode_jpeg(image: &DynamicImage) -> Result<Vec<u8>, PhotoError> {
+    let mut rgba = image.to_rgba8();
+    for pixel in rgba.pixels_mut() {
+        let alpha = pixel[3] as u16;
+        for channel in &mut pixel.0[..3] {
+            *channel = ((*channel as u16 * alpha + 255 * (255 - alpha)) / 255) as u8;
+        }
+    }
+    let rgb = DynamicImage::ImageRgba8(rgba).to_rgb8();
+
+    let mut out = Vec::new();
+    JpegEncoder::new_with_quality(&mut out, JPEG_QUALITY)
+        .write_image(&rgb, rgb.width(), rgb.height(), ExtendedColorType::Rgb8)
+        .map_err(|e| PhotoError::Failed(format!("Failed to encode JPEG: {}", e)))?;
+    Ok(out)
+}
+
+fn encode_webp(image: &DynamicImage) -> Result<Vec<u8>, PhotoError> {
+    let rgba = image.to_rgba8();
+    let mut out = Vec::new();
+    WebPEncoder::new_lossless(&mut out)
+        .write_image(&rgba, rgba.width(), rgba.height(), ExtendedColorType::Rgba8)
+        .map_err(|e| PhotoError::Failed(format!("Failed to encode WebP: {}", e)))?;
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{codecs::png::PngEncoder, Rgb, RgbImage};
+
+    const RED: Rgb<u8> = Rgb([220, 20, 20]);
+    const BLUE: Rgb<u8> = Rgb([20, 20, 220]);
+
+    /// Left half red, right half blue
+    fn halves(width: u32, height: u32) -> RgbImage {
+        RgbImage::from_fn(width, height, |x, _| if x < width / 2 { RED } else { BLUE })
+    }
+
+    fn png(image: &RgbImage) -> Vec<u8> {
+        let mut out = Vec::new();
+        PngEncoder::new(&mut out)
+            .write_image(image, image.width(), image.height(), ExtendedColorType::Rgb8)
+            .unwrap();
+        out
+    }
+
+    fn jpeg(image: &RgbImage) -> Vec<u8> {
+        let mut out = Vec::new();
+        JpegEncoder::new_with_quality(&mut out, 95)
+            .write_image(image, image.width(), image.height(), ExtendedColorType::Rgb8)
+            .unwrap();
+        out
+    }
+
+    /// An APP1 segment holding EXIF with orientation 6 (rotated 90° clockwise)
+    /// and a GPS IFD
+    fn exif_segment() -> Vec<u8> {
+        let mut tiff = b"II\x2a\x00\x08\x00\x00\x00".to_vec();
+        // IFD0: Orientation and the GPS IFD pointer
+        tiff.extend([2, 0]);
+        tiff.extend([0x12, 0x01, 3, 0, 1, 0, 0, 0, 6, 0, 0, 0]);
+        tiff.extend([0x25, 0x88, 4, 0, 1, 0, 0, 0, 38, 0, 0, 0]);
+        tiff.extend([0, 0, 0, 0]);
+        // GPS IFD at offset 38: GPSLatitudeRef "N"
+        tiff.extend([1, 0]);
+        tiff.extend([0x01, 0x00, 2, 0, 2, 0, 0, 0, b'N', 0, 0, 0]);
+        tiff.extend([0, 0, 0, 0]);
+
+        let mut segment = vec![0xff, 0xe1];
+        segment.extend(((2 + 6 + tiff.len()) as u16).to_be_bytes());
+        segment.extend(b"Exif\0\0");
+        segment.extend(tiff);
+        segment
+    }
+
+    /// Markers of the segments before the image data
+    fn jpeg_markers(data: &[u8]) -> Vec<u8> {
+        assert_eq!(&data[..2], [0xff, 0xd8]);
+        let mut markers = Vec::new();
+        let mut at = 2;
+        while data[at] == 0xff && data[at + 1] != 0xda {
+            markers.push(data[at + 1]);
+            at += 2 + u16::from_be_bytes([data[at + 2], data[at + 3]]) as usize;
+        }
+        markers
+    }
+
+    fn rejection(data: &[u8]) -> String {
+        match process_photo(data) {
+            Err(PhotoError::Rejected(message)) => message,
+            Err(PhotoError::Failed(message)) => panic!("failed instead of rejected: {}", message),
+            Ok(_) => panic!("accepted"),
+        }
+    }
+
+    #[test]
+    fn type_is_sniffed_from_the_content() {
+        assert!(rejection(b"definitely not an image").starts_with("Unsupported image"));
+        let svg = br#"<svg xmlns="http://www.w3.org/2000/svg" width="64" height="64"><rect width="64" height="64"/></svg>"#;
+        assert!(rejection(svg).starts_with("Unsupported image"));
+        // Truncated data of a supported type is rejected when decoding
+        let truncated = &png(&halves(64, 64))[..40];
+        assert!(rejection(truncated).starts_with("Invalid image"));
+    }
+
+    #[test]
+    fn dimensions_and_size_are_bounded() {
+        assert_eq!(rejection(&png(&halves(31, 64))), "Photo must be at least 32x32 pixels");
+        assert_eq!(rejection(&png(&halves(64, 16))), "Photo must be at least 32x32 pixels");
+        let too_wide = png(&halves(MAX_DIMENSION + 1, 32));
+        assert!(rejection(&too_wide).starts_with("Invalid image"));
+
+        let mut too_large = png(&halves(64, 64));
+        too_large.resize(MAX_PHOTO_BYTES + 1, 0);
+        assert_eq!(rejection(&too_large), "Photo is larger than 10 MB");
+    }
+
+    #[test]
+    fn every_variant_is_square_in_both_formats() {
+        for data in [png(&halves(120, 60)), jpeg(&halves(60, 120))] {
+            let variants = process_photo(&data).unwrap();
+            let names: Vec<String> = variants.iter().map(PhotoVariant::file_name).collect();
+            assert_eq!(names, ["64.jpg", "64.webp", "256.jpg", "256.webp", "512.jpg", "512.webp"]);
+
+            for variant in &variants {
+                let format = match variant.extension {
+                    "jpg" => ImageFormat::Jpeg,
+                    _ => ImageFormat::WebP,
+                };
+                assert_eq!(image::guess_format(&variant.data).unwrap(), format);
+                let decoded = image::load_from_memory_with_format(&variant.data, format).unwrap();
+                assert_eq!((decoded.width(), decoded.height()), (variant.size, variant.size));
+            }
+        }
+    }
+
+    #[test]
+    fn exif_is_applied_then_stripped() {
+        let plain = jpeg(&halves(128, 64));
+        let mut data = plain[..2].to_vec();
+        data.extend(exif_segment());
+        data.extend(&plain[2..]);
+        assert!(jpeg_markers(&data).contains(&0xe1));
+
+        let variants = process_photo(&data).unwrap();
+        for variant in variants.iter().filter(|variant| variant.extension == "jpg") {
+            assert!(!jpeg_markers(&variant.data).contains(&0xe1), "{} kept APP1", variant.file_name());
+            assert!(!variant.data.windows(4).any(|window| window == b"Exif"));
+        }
+        for variant in variants.iter().filter(|variant| variant.extension == "webp") {
+            assert!(!variant.data.windows(4).any(|window| window == b"EXIF"));
+        }
+
+        // Rotated upright, the red left half ends up on top
+        let upright = image::load_from_memory(&variants[0].data).unwrap().to_rgb8();
+        let is_red = |pixel: &Rgb<u8>| pixel[0] > 150 && pixel[2] < 100;
+        assert!(is_red(upright.get_pixel(4, 4)));
+        assert!(is_red(upright.get_pixel(59, 4)));
+        assert!(!is_red(upright.get_pixel(4, 59)));
+    }
+}
@@ -0,0 +1,47 @@
+use axum::async_trait;
+use lettre::message::Mailbox;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+use super::{build_message, Email, Mailer};
+
+/// Delivers email into a local Maildir, one `.eml`-compatible file per message,
+/// readable with any mail client
+pub struct MaildirMailer {
+    root: PathBuf,
+    from: Mailbox,
+}
+
+impl MaildirMailer {
+    pub fn new(root: impl Into<PathBuf>, from: Mailbox) -> Self {
+        MaildirMailer {
+            root: root.into(),
+            from,
+        }
+    }
+}
+
+#[async_trait]
+impl Mailer for MaildirMailer {
+    async fn send(&self, email: &Email) -> Result<(), String> {
+        let message = build_message(&self.from, email)?.formatted();
+
+        // Maildir delivery: write into tmp/, then move into new/ so readers never see partial files
+        let (tmp, new) = (self.root.join("tmp"), self.root.join("new"));
+        for dir in [&tmp, &new, &self.root.join("cur")] {
+            tokio::fs::create_dir_all(dir)
+                .await
+                .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        }
+        let file_name = format!("{}.{}.auth_api", chrono::Utc::now().timestamp(), Uuid::new_v4());
+        tokio::fs::write(tmp.join(&file_name), message)
+            .await
+            .map_err(|e| format!("Failed to write {}: {}", file_name, e))?;
+        tokio::fs::rename(tmp.join(&file_name), new.join(&file_name))
+            .await
+            .map_err(|e| format!("Failed to deliver {}: {}", file_name, e))?;
+
+        tracing::info!(subject = %email.subject, path = %new.display(), "Delivered email to maildir");
+        Ok(())
+    }
+}
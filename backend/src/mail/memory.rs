@@ -0,0 +1,27 @@
+use axum::async_trait;
+use std::sync::{Arc, Mutex};
+
+use super::{Email, Mailer};
+
+/// Keeps sent email in memory, for tests
+#[derive(Clone, Default)]
+pub struct InMemoryMailer {
+    sent: Arc<Mutex<Vec<Email>>>,
+}
+
+impl InMemoryMailer {
+    /// Email sent so far, oldest first
+    #[allow(dead_code)]
+    pub fn sent(&self) -> Vec<Email> {
+        self.sent.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl Mailer for InMemoryMailer {
+    async fn send(&self, email: &Email) -> Result<(), String> {
+        tracing::debug!(subject = %email.subject, "Kept email in memory");
+        self.sent.lock().unwrap().push(email.clone());
+        Ok(())
+    }
+}
@@ -0,0 +1,66 @@
+pub mod maildir;
+pub mod memory;
+pub mod smtp;
+pub mod templates;
+
+use axum::async_trait;
+use lettre::{
+    message::{header::ContentType, Mailbox, MultiPart},
+    Message,
+};
+use std::sync::Arc;
+
+use crate::config::config::{app_config, MailTransport};
+
+/// An email ready to send; the sender is the mailer's configured address
+#[derive(Debug, Clone)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub text: String,
+    /// HTML alternative to the text body
+    pub html: Option<String>,
+}
+
+/// Sends email
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, email: &Email) -> Result<(), String>;
+}
+
+/// Builds the RFC 5322 message for `email`, as multipart/alternative when it has HTML
+pub fn build_message(from: &Mailbox, email: &Email) -> Result<Message, String> {
+    let to: Mailbox = email
+        .to
+        .parse()
+        .map_err(|e| format!("Invalid recipient {}: {}", email.to, e))?;
+    let builder = Message::builder()
+        .from(from.clone())
+        .to(to)
+        .subject(&email.subject);
+
+    match &email.html {
+        Some(html) => builder.multipart(MultiPart::alternative_plain_html(
+            email.text.clone(),
+            html.clone(),
+        )),
+        None => builder
+            .header(ContentType::TEXT_PLAIN)
+            .body(email.text.clone()),
+    }
+    .map_err(|e| format!("Failed to build email: {}", e))
+}
+
+/// Builds the mailer selected by `mail.transport`
+pub fn from_config() -> Arc<dyn Mailer> {
+    let config = &app_config().mail;
+    let from: Mailbox = config.from.parse().expect("mail.from is validated at startup");
+    match config.transport {
+        MailTransport::Maildir => Arc::new(maildir::MaildirMailer::new(config.maildir_path.clone(), from)),
+        MailTransport::Smtp => {
+            let url = config.smtp_url.as_ref().expect("mail.smtp_url is validated at startup");
+            Arc::new(smtp::SmtpMailer::new(url.expose(), from).expect("mail.smtp_url must be a valid SMTP URL"))
+        }
+        MailTransport::Memory => Arc::new(memory::InMemoryMailer::default()),
+    }
+}
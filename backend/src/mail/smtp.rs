@@ -0,0 +1,182 @@
+use axum::async_trait;
+use lettre::{message::Mailbox, AsyncSmtpTransport, AsyncTransport, Tokio1Executor};
+
+use super::{build_message, Email, Mailer};
+
+/// Sends email through an SMTP relay.
+///
+/// The URL selects the security: `smtps://` for implicit TLS, `smtp://...?tls=required`
+/// for STARTTLS and plain `smtp://` for a local fake server such as
+/// `smtp://localhost:1025` (MailHog, Mailpit).
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpMailer {
+    pub fn new(url: &str, from: Mailbox) -> Result<Self, String> {
+        let transport = AsyncSmtpTransport::<Tokio1Executor>::from_url(url)
+            .map_err(|e| format!("Invalid SMTP URL: {}", e))?
+            .build();
+        Ok(SmtpMailer { transport, from })
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, email: &Email) -> Result<(), String> {
+        let message = build_message(&self.from, email)?;
+        self.transport
+            .send(message)
+            .await
+            .map_err(|e| format!("SMTP delivery failed: {}", e))?;
+        tracing::info!(subject = %email.subject, "Sent email over SMTP");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::{
+        io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+        net::TcpListener,
+    };
+
+    /// What a client handed to the fake server
+    #[derive(Debug, Default)]
+    struct Received {
+        mail_from: String,
+        rcpt_to: Vec<String>,
+        data: String,
+    }
+
+    /// Accepts one SMTP session on a local port, answering every command with
+    /// success, and returns the port and the session's outcome
+    async fn fake_server() -> (u16, tokio::task::JoinHandle<Received>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = BufReader::new(reader).lines();
+            let mut received = Received::default();
+
+            writer.write_all(b"220 localhost ESMTP fake\r\n").await.unwrap();
+            while let Some(line) = lines.next_line().await.unwrap() {
+                let command = line.to_ascii_uppercase();
+                let reply: &[u8] = if command.starts_with("EHLO") || command.starts_with("HELO") {
+                    b"250-localhost\r\n250 8BITMIME\r\n"
+                } else if command.starts_with("MAIL FROM:") {
+                    received.mail_from = line["MAIL FROM:".len()..].to_string();
+                    b"250 OK\r\n"
+                } else if command.starts_with("RCPT TO:") {
+                    received.rcpt_to.push(line["RCPT TO:".len()..].to_string());
+                    b"250 OK\r\n"
+                } else if command == "DATA" {
+                    writer.write_all(b"354 End data with <CR><LF>.<CR><LF>\r\n").await.unwrap();
+                    while let Some(line) = lines.next_line().await.unwrap() {
+                        if line == "." {
+                            break;
+                        }
+                        received.data.push_str(&line);
+                        received.data.push('\n');
+                    }
+                    b"250 OK: queued\r\n"
+                } else if command == "QUIT" {
+                    writer.write_all(b"221 Bye\r\n").await.unwrap();
+                    break;
+                } else {
+                    b"250 OK\r\n"
+                };
+                writer.write_all(reply).await.unwrap();
+            }
+            received
+        });
+        (port, server)
+    }
+
+    fn mailer(port: u16) -> SmtpMailer {
+        let from = "Auth API <no-reply@example.com>".parse().unwrap();
+        SmtpMailer::new(&format!("smtp://127.0.0.1:{}", port), from).unwrap()
+    }
+
+    #[tokio::test]
+    async fn sends_html_email_as_multipart_alternative() {
+        let (port, server) = fake_server().await;
+        let email = Email {
+            to: "ada@example.com".to_string(),
+            subject: "Reset your password".to_string(),
+            text: "Open the link to choose a new password".to_string(),
+            html: Some("<p>Choose a new password</p>".to_string()),
+        };
+
+        mailer(port).send(&email).await.unwrap();
+        // The mailer is dropped once sent, which ends the session
+        let received = server.await.unwrap();
+
+        assert_eq!(received.mail_from, "<no-reply@example.com>");
+        assert_eq!(received.rcpt_to, ["<ada@example.com>"]);
+        let data = received.data;
+        assert!(data.contains("From: \"Auth API\" <no-reply@example.com>"), "{}", data);
+        assert!(data.contains("To: ada@example.com"), "{}", data);
+        assert!(data.contains("Subject: Reset your password"), "{}", data);
+        assert!(data.contains("Content-Type: multipart/alternative;"), "{}", data);
+
+        let text = data.find("Content-Type: text/plain").expect("a text part");
+        let html = data.find("Content-Type: text/html").expect("an HTML part");
+        // Clients show the last alternative they support, so HTML goes last
+        assert!(text < html);
+        assert!(data[text..html].contains("Open the link to choose a new password"));
+        assert!(data[html..].contains("<p>Choose a new password</p>"));
+    }
+
+    #[tokio::test]
+    async fn sends_plain_text_without_html() {
+        let (port, server) = fake_server().await;
+        let email = Email {
+            to: "ada@example.com".to_string(),
+            subject: "Plain".to_string(),
+            text: "Only text".to_string(),
+            html: None,
+        };
+
+        mailer(port).send(&email).await.unwrap();
+        let data = server.await.unwrap().data;
+
+        assert!(data.contains("Content-Type: text/plain"), "{}", data);
+        assert!(!data.contains("multipart"), "{}", data);
+        assert!(data.contains("Only text"), "{}", data);
+    }
+
+    #[tokio::test]
+    async fn rejected_recipients_fail_delivery() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = BufReader::new(reader).lines();
+            writer.write_all(b"220 localhost ESMTP fake\r\n").await.unwrap();
+            while let Some(line) = lines.next_line().await.unwrap() {
+                let reply: &[u8] = if line.to_ascii_uppercase().starts_with("RCPT TO:") {
+                    b"550 No such user\r\n"
+                } else if line.to_ascii_uppercase().starts_with("EHLO") {
+                    b"250 localhost\r\n"
+                } else {
+                    b"250 OK\r\n"
+                };
+                writer.write_all(reply).await.unwrap();
+            }
+        });
+
+        let email = Email {
+            to: "nobody@example.com".to_string(),
+            subject: "Hello".to_string(),
+            text: "Hello".to_string(),
+            html: None,
+        };
+        let error = mailer(port).send(&email).await.unwrap_err();
+        assert!(error.starts_with("SMTP delivery failed"), "{}", error);
+    }
+}
@@ -0,0 +1,221 @@
+use minijinja::{Environment, UndefinedBehavior};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::sync::OnceLock;
+
+use super::Email;
+use crate::models::mail::MailTemplate;
+
+/// Locales every template is translated to; the first is the fallback
+pub const LOCALES: [&str; 2] = ["en", "fr"];
+pub const DEFAULT_LOCALE: &str = LOCALES[0];
+
+/// Templates are compiled into the binary so deployments need no template directory
+macro_rules! locale_templates {
+    ($locale:literal) => {
+        locale_templates!($locale, "verification", "password_reset", "invitation", "login_alert")
+    };
+    ($locale:literal, $($name:literal),+) => {
+        [$(
+            (
+                concat!($locale, "/", $name, ".subject"),
+                include_str!(concat!("../../templates/email/", $locale, "/", $name, ".subject")),
+            ),
+            (
+                concat!($locale, "/", $name, ".txt"),
+                include_str!(concat!("../../templates/email/", $locale, "/", $name, ".txt")),
+            ),
+            (
+                concat!($locale, "/", $name, ".html"),
+                include_str!(concat!("../../templates/email/", $locale, "/", $name, ".html")),
+            ),
+        )+]
+    };
+}
+
+fn environment() -> &'static Environment<'static> {
+    static ENVIRONMENT: OnceLock<Environment<'static>> = OnceLock::new();
+    ENVIRONMENT.get_or_init(|| {
+        let mut env = Environment::new();
+        // A missing variable is a bug in the caller, not an empty string
+        env.set_undefined_behavior(UndefinedBehavior::Strict);
+        env.add_template("base.html", include_str!("../../templates/email/base.html"))
+            .expect("Invalid email layout");
+        for (name, source) in locale_templates!("en").into_iter().chain(locale_templates!("fr")) {
+            env.add_template(name, source)
+                .unwrap_or_else(|e| panic!("Invalid email template {}: {}", name, e));
+        }
+        env
+    })
+}
+
+/// Picks the best supported locale for a tag or `Accept-Language` list,
+/// e.g. `fr-CA,fr;q=0.9,en;q=0.8` gives `fr`
+pub fn resolve_locale(requested: Option<&str>) -> &'static str {
+    requested
+        .into_iter()
+        .flat_map(|list| list.split(','))
+        .filter_map(|tag| tag.split(';').next())
+        .map(|tag| tag.trim().to_lowercase())
+        .find_map(|tag| {
+            let language = tag.split(['-', '_']).next().unwrap_or_default();
+            LOCALES.iter().copied().find(|locale| *locale == tag || *locale == language)
+        })
+        .unwrap_or(DEFAULT_LOCALE)
+}
+
+/// Subject and bodies of a rendered template
+#[derive(Debug, Clone)]
+pub struct RenderedEmail {
+    pub subject: String,
+    pub text: String,
+    pub html: String,
+}
+
+impl RenderedEmail {
+    pub fn addressed_to(self, to: &str) -> Email {
+        Email {
+            to: to.to_string(),
+            subject: self.subject,
+            text: self.text,
+            html: Some(self.html),
+        }
+    }
+}
+
+/// Renders `template` in `locale` (see `resolve_locale`) with the given variables
+pub fn render(
+    template: MailTemplate,
+    locale: &str,
+    context: &impl Serialize,
+) -> Result<RenderedEmail, String> {
+    let locale = resolve_locale(Some(locale));
+    let part = |extension: &str| {
+        let name = format!("{}/{}.{}", locale, template.as_str(), extension);
+        environment()
+            .get_template(&name)
+            .and_then(|t| t.render(context))
+            .map_err(|e| format!("Failed to render {}: {}", name, e))
+    };
+
+    Ok(RenderedEmail {
+        // Subjects are single line; drop the template's trailing newline
+        subject: part("subject")?.trim().to_string(),
+        text: part("txt")?,
+        html: part("html")?,
+    })
+}
+
+/// Representative variables for previewing a template
+pub fn sample_context(template: MailTemplate) -> Value {
+    match template {
+        MailTemplate::Verification => json!({
+            "name": "Ada",
+            "verify_url": "https://example.com/verify?token=00000000-0000-0000-0000-000000000000",
+            "expires_hours": 24,
+        }),
+        MailTemplate::PasswordReset => json!({
+            "name": "Ada",
+            "reset_url": "https://example.com/reset?token=00000000-0000-0000-0000-000000000000",
+            "expires_minutes": 30,
+        }),
+        MailTemplate::Invitation => json!({
+            "inviter": "Grace Hopper",
+            "organization": "Analytical Engines",
+            "role": "Member",
+            "accept_url": "https://example.com/invitations/00000000-0000-0000-0000-000000000000",
+            "expires_days": 7,
+        }),
+        MailTemplate::LoginAlert => json!({
+            "name": "Ada",
+            "suspicious": true,
+            "device": "desktop",
+            "browser": "Firefox",
+            "os": "Linux",
+            "ip": "203.0.113.7",
+            "failed_attempts": 6,
+            "action_url": "https://example.com/not-me?token=00000000-0000-0000-0000-000000000000",
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The link each template's sample context carries
+    fn sample_url(template: MailTemplate) -> String {
+        let context = sample_context(template);
+        let key = ["verify_url", "reset_url", "accept_url", "action_url"]
+            .into_iter()
+            .find(|key| context.get(key).is_some())
+            .expect("every template links somewhere");
+        context[key].as_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn every_template_renders_in_every_locale() {
+        for template in MailTemplate::ALL {
+            let url = sample_url(template);
+            let mut subjects = Vec::new();
+            for locale in LOCALES {
+                let email = render(template, locale, &sample_context(template))
+                    .unwrap_or_else(|e| panic!("{}", e));
+                let name = format!("{}/{}", locale, template.as_str());
+                assert!(!email.subject.is_empty(), "{} has an empty subject", name);
+                assert!(!email.subject.contains('\n'), "{} subject spans lines", name);
+                assert!(email.text.contains(&url), "{} text lacks its link", name);
+                // HTML escaping rewrites the slashes, so only look for the token
+                let token = url.rsplit(['/', '=']).next().unwrap();
+                assert!(email.html.contains(token), "{} HTML lacks its link", name);
+                assert!(
+                    email.html.contains(&format!("<html lang=\"{}\">", locale)),
+                    "{} HTML is not in its language",
+                    name
+                );
+                subjects.push(email.subject);
+            }
+            subjects.dedup();
+            assert_eq!(subjects.len(), LOCALES.len(), "{} is not translated", template.as_str());
+        }
+    }
+
+    #[test]
+    fn missing_variables_fail_to_render() {
+        for template in MailTemplate::ALL {
+            assert!(render(template, DEFAULT_LOCALE, &json!({})).is_err());
+        }
+    }
+
+    #[test]
+    fn unsupported_locales_render_the_default() {
+        let template = MailTemplate::PasswordReset;
+        let english = render(template, "en", &sample_context(template)).unwrap();
+        let fallback = render(template, "de-DE", &sample_context(template)).unwrap();
+        assert_eq!(fallback.subject, english.subject);
+    }
+
+    #[test]
+    fn resolves_supported_locales() {
+        assert_eq!(resolve_locale(Some("fr")), "fr");
+        assert_eq!(resolve_locale(Some("en")), "en");
+        assert_eq!(resolve_locale(Some("FR")), "fr");
+        assert_eq!(resolve_locale(Some("fr-CA")), "fr");
+        assert_eq!(resolve_locale(Some("fr_BE")), "fr");
+    }
+
+    #[test]
+    fn resolves_accept_language_lists_in_order() {
+        assert_eq!(resolve_locale(Some("fr-CA,fr;q=0.9,en;q=0.8")), "fr");
+        assert_eq!(resolve_locale(Some("de-DE, en;q=0.5, fr;q=0.3")), "en");
+        assert_eq!(resolve_locale(Some("de-DE,fr;q=0.7")), "fr");
+    }
+
+    #[test]
+    fn falls_back_to_the_default_locale() {
+        assert_eq!(resolve_locale(None), DEFAULT_LOCALE);
+        assert_eq!(resolve_locale(Some("")), DEFAULT_LOCALE);
+        assert_eq!(resolve_locale(Some("de-DE,es;q=0.8")), DEFAULT_LOCALE);
+        assert_eq!(resolve_locale(Some("*")), DEFAULT_LOCALE);
+    }
+}
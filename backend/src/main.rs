@@ -1,68 +1,312 @@
+mod authz;
 mod config;
 mod db;
+mod grpc;
+mod imaging;
+mod mail;
 mod middleware;
 mod models;
+mod notify;
+mod policy;
 mod routes;
+mod state;
+mod storage;
+mod telemetry;
+#[cfg(test)]
+mod test_support;
 
 use crate::{
-    config::config::{get_database_url, get_frontend_url, get_port, init},
+    authz::schema::AuthzSchema,
+    config::config::{app_config, init, AppConfig},
     db::queries::init_db,
-    middleware::auth::auth_middleware,
-    models::user::Role,
-    routes::{auth, protected},
+    middleware::{
+        auth::auth_middleware, impersonation::reject_impersonation, organization::org_middleware,
+        permissions::PermissionCache, scopes::require_scopes,
+    },
+    models::{
+        organization::{OrgAdmin, OrgMember},
+        role::{
+            AdminAccess, ExplainPolicies, Impersonate, ManageGroups, ManageRelations, ManageRoles, ManageUsers,
+            ProfileRead, ReadAudit, ReadUsers, UserAccess,
+        },
+    },
+    policy::schema::PolicySet,
+    routes::{auth, health::ReadinessCache, protected},
+    state::{AppState, Draining},
 };
 use axum::{
+    extract::DefaultBodyLimit,
     http::HeaderValue,
-    middleware::from_fn_with_state,
-    routing::{get, post, put},
+    middleware::{from_fn, from_fn_with_state},
+    routing::{any, delete, get, patch, post, put},
     Router,
 };
-use tower_http::{cors::CorsLayer, services::ServeDir};
+use tower::ServiceBuilder;
+use tower_http::{
+    cors::CorsLayer,
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    trace::{DefaultOnFailure, DefaultOnResponse, TraceLayer},
+};
+use tracing::Level;
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
-use std::path::PathBuf;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
 
 #[derive(OpenApi)]
 #[openapi(
     paths(
         routes::auth::login,
         routes::auth::register,
+        routes::auth::issue_scoped_token,
+        routes::auth::disown,
+        routes::auth::reset_password,
+        routes::auth::verify_email,
+        routes::forward_auth::verify,
         routes::protected::admin_route,
         routes::protected::user_route,
         routes::profile::get_profile,
         routes::profile::update_profile,
         routes::profile::upload_photo,
+        routes::profile::delete_photo,
+        routes::photo_uploads::create_upload,
+        routes::photo_uploads::get_upload_state,
+        routes::photo_uploads::patch_upload,
+        routes::photo_uploads::complete_upload,
+        routes::photo_uploads::delete_upload,
+        routes::uploads::get_upload,
+        routes::avatars::get_avatar,
+        routes::sessions::get_login_history,
+        routes::sessions::get_sessions,
+        routes::sessions::delete_session,
+        routes::sessions::delete_other_sessions,
         routes::health::health_check,
+        routes::health::live,
+        routes::health::ready,
+        routes::metrics::get_metrics,
+        routes::roles::get_roles,
+        routes::roles::put_role,
+        routes::organizations::create_org,
+        routes::organizations::list_my_orgs,
+        routes::organizations::switch_org,
+        routes::organizations::get_members,
+        routes::organizations::put_member_role,
+        routes::organizations::delete_member,
+        routes::organizations::invite,
+        routes::organizations::get_invitations,
+        routes::organizations::accept,
+        routes::groups::get_groups,
+        routes::groups::post_group,
+        routes::groups::get_group_detail,
+        routes::groups::delete_group_by_id,
+        routes::groups::put_group_user,
+        routes::groups::delete_group_user,
+        routes::groups::put_subgroup,
+        routes::groups::delete_subgroup,
+        routes::groups::put_group_role,
+        routes::groups::delete_group_role,
+        routes::groups::put_group_permission,
+        routes::groups::delete_group_permission,
+        routes::authz::post_tuple,
+        routes::authz::delete_tuple_route,
+        routes::authz::get_tuples,
+        routes::authz::post_check,
+        routes::authz::post_expand,
+        routes::authz::post_list_objects,
+        routes::policies::explain,
+        routes::users::get_users,
+        routes::users::get_user_count,
+        routes::users::get_user_detail,
+        routes::users::disable,
+        routes::users::enable,
+        routes::users::force_password_reset,
+        routes::users::put_user_role,
+        routes::users::delete_user_by_id,
+        routes::users::get_user_actions,
+        routes::users::impersonate,
+        routes::audit::get_audit_events,
+        routes::audit::export_audit_events,
+        routes::audit::verify_audit_chain,
+        routes::mail::get_mail_templates,
+        routes::mail::preview_mail_template,
     ),
     components(
         schemas(
+            models::health::Readiness,
+            models::health::ComponentCheck,
+            models::health::ComponentStatus,
+            models::health::ReadinessStatus,
             models::user::User,
             models::user::Role,
             models::user::LoginRequest,
             models::user::RegisterRequest,
-            models::user::TokenResponse
+            models::user::TokenResponse,
+            models::user::ScopedTokenRequest,
+            models::photo_upload::CreatePhotoUploadRequest,
+            models::photo_upload::PhotoUpload,
+            models::role::RoleDefinition,
+            models::role::RoleResponse,
+            models::role::RoleUpsertRequest,
+            models::organization::Organization,
+            models::organization::OrganizationMembership,
+            models::organization::Member,
+            models::organization::Invitation,
+            models::organization::OrgRole,
+            models::organization::CreateOrganizationRequest,
+            models::organization::InvitationRequest,
+            models::organization::MemberRoleRequest,
+            models::group::Group,
+            models::group::GroupMember,
+            models::group::GroupDetail,
+            models::group::CreateGroupRequest,
+            models::relation::TupleRequest,
+            models::relation::CheckRequest,
+            models::relation::CheckResponse,
+            models::relation::ExpandRequest,
+            models::relation::ExpandNode,
+            models::relation::ListObjectsRequest,
+            models::relation::ListObjectsResponse,
+            models::admin::UserSummary,
+            models::admin::UserDetail,
+            models::admin::UserPage,
+            models::admin::UserSort,
+            models::admin::SortOrder,
+            models::admin::UserCount,
+            models::admin::RoleCount,
+            models::admin::AccountActionRequest,
+            models::admin::ChangeRoleRequest,
+            models::admin::ImpersonationRequest,
+            models::admin::ImpersonationResponse,
+            models::audit::AuditEvent,
+            models::audit::AuditEventType,
+            models::audit::AuditPage,
+            models::audit::ExportFormat,
+            models::audit::ChainVerification,
+            models::mail::MailTemplate,
+            models::mail::MailTemplateInfo,
+            models::mail::PreviewFormat,
+            models::mail::EmailPreview,
+            models::avatar::AvatarStyle,
+            models::avatar::AvatarFormat,
+            models::session::LoginRecord,
+            models::session::Session,
+            models::session::SessionResponse,
+            models::session::RevokedSessions,
+            models::session::DisownLoginRequest,
+            models::user::PasswordResetRequest,
+            models::user::EmailVerificationRequest,
+            routes::policies::ExplainRequest,
+            policy::engine::PolicyDecision,
+            policy::engine::PolicyEvaluation,
+            policy::schema::Effect,
+            policy::schema::PolicyMode
         )
     ),
     tags(
         (name = "auth", description = "Authentication endpoints"),
         (name = "protected", description = "Protected endpoints"),
         (name = "profile", description = "User profile endpoints"),
-        (name = "health", description = "Health check endpoint")
+        (name = "health", description = "Health check endpoint"),
+        (name = "Admin", description = "Administration endpoints"),
+        (name = "Organizations", description = "Organization membership and invitations"),
+        (name = "Authorization", description = "Relationship-based access control")
     )
 )]
 struct ApiDoc;
 
+/// Command line: `[--config <file>] [--print-config]`
+struct Args {
+    config_path: Option<String>,
+    print_config: bool,
+}
+
+fn parse_args() -> Args {
+    let mut args = Args {
+        config_path: std::env::var("CONFIG_FILE").ok(),
+        print_config: false,
+    };
+    let mut argv = std::env::args().skip(1);
+    while let Some(arg) = argv.next() {
+        match arg.as_str() {
+            "--config" => args.config_path = Some(argv.next().expect("--config needs a file path")),
+            "--print-config" => args.print_config = true,
+            other => {
+                eprintln!("Unknown argument: {}", other);
+                eprintln!("Usage: auth_api [--config <file>] [--print-config]");
+                std::process::exit(2);
+            }
+        }
+    }
+    args
+}
+
+/// Resolves on Ctrl+C or SIGTERM, after readiness has reported draining for
+/// `grace` so load balancers stop sending traffic before the listener closes
+async fn shutdown_signal(draining: Draining, grace: Duration) {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Failed to listen for Ctrl+C");
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to listen for SIGTERM")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+    draining.start();
+    tracing::info!(grace_seconds = grace.as_secs(), "Shutdown requested, draining");
+    tokio::time::sleep(grace).await;
+}
+
 #[tokio::main]
 async fn main() {
-    // Initialize environment
-    init();
-
-    // Get configuration
-    let frontend_url = get_frontend_url();
-    let database_url = get_database_url();
+    // Load configuration: file, then .env and environment overrides
+    dotenv::dotenv().ok();
+    let args = parse_args();
+    let config = AppConfig::load(args.config_path.as_deref()).unwrap_or_else(|errors| {
+        eprintln!("❌ Invalid configuration:");
+        for error in errors {
+            eprintln!("   - {}", error);
+        }
+        std::process::exit(1);
+    });
+    if args.print_config {
+        print!("{}", config.to_toml());
+        return;
+    }
+    init(config);
+    let config = app_config();
+    telemetry::init(&config.log, &config.otel);
+    let metrics = telemetry::metrics::install();
 
     // Initialize database connection
-    let pool = init_db(&database_url).await;
+    let pool = init_db(&config.database, &config.admin).await;
+    let authz_schema = AuthzSchema::load().expect("Failed to load authz schema");
+    let policies = PolicySet::load().expect("Failed to load policies");
+    let mailer = mail::from_config();
+    let draining = Draining::default();
+    let state = AppState {
+        pool,
+        permissions: PermissionCache::default(),
+        authz_schema: Arc::new(authz_schema),
+        policies: Arc::new(policies),
+        notifier: notify::from_config(mailer.clone()),
+        mailer,
+        blobs: storage::from_config(),
+        metrics,
+        draining: draining.clone(),
+        readiness: ReadinessCache::default(),
+    };
 
     // Configure CORS
     let cors = CorsLayer::new()
@@ -71,49 +315,376 @@ async fn main() {
             axum::http::Method::GET,
             axum::http::Method::POST,
             axum::http::Method::PUT,
+            axum::http::Method::DELETE,
             axum::http::Method::OPTIONS,
             axum::http::Method::HEAD,
         ])
         // Allow specific origins during development
-        .allow_origin(frontend_url.parse::<HeaderValue>().unwrap())
+        .allow_origin(config.server.frontend_url.parse::<HeaderValue>().unwrap())
         .allow_headers([
             axum::http::header::AUTHORIZATION,
             axum::http::header::CONTENT_TYPE,
             axum::http::header::ACCEPT,
         ]);
 
-    // Create uploads directory if it doesn't exist
-    tokio::fs::create_dir_all("backend/uploads").await.unwrap_or_default();
+    // Remove photos left behind by failed uploads and deleted users
+    let sweep_interval = config.storage.photo_sweep_interval_minutes;
+    if sweep_interval > 0 {
+        storage::photos::spawn_photo_sweeper(state.pool.clone(), state.blobs.clone(), sweep_interval);
+    }
+
+    // Serve the internal gRPC API alongside the HTTP router; it stops
+    // accepting calls once draining starts
+    let grpc_state = state.clone();
+    let grpc_draining = draining.clone();
+    let grpc_server = tokio::spawn(async move {
+        let addr = SocketAddr::from(([0, 0, 0, 0], config.server.grpc_port));
+        if let Err(e) = grpc::service::serve(grpc_state, addr, grpc_draining.wait()).await {
+            tracing::error!(error = %e, "gRPC server failed");
+        }
+    });
+
+    let metrics_routes = if config.metrics.enabled {
+        Router::new().route("/metrics", get(routes::metrics::get_metrics))
+    } else {
+        Router::new()
+    };
 
     // Build router
     let app = Router::new()
         .route("/health", get(routes::health::health_check))
+        .route("/health/live", get(routes::health::live))
+        .route("/health/ready", get(routes::health::ready))
         .route("/auth/login", post(auth::login))
         .route("/auth/register", post(auth::register))
-        .route("/api/profile", get(routes::profile::get_profile))
-        .route("/api/profile/update", put(routes::profile::update_profile))
-        .route("/api/profile/photo", post(routes::profile::upload_photo))
+        .route("/auth/not-me", post(auth::disown))
+        .route("/auth/reset-password", post(auth::reset_password))
+        .route("/auth/verify-email", post(auth::verify_email))
+        .route(
+            "/auth/token",
+            post(auth::issue_scoped_token)
+                .layer(from_fn(reject_impersonation))
+                .layer(from_fn_with_state(state.clone(), auth_middleware::<ProfileRead>)),
+        )
+        // Reverse proxies may forward the original method, so accept any
+        .route("/auth/verify", any(routes::forward_auth::verify))
+        .route(
+            "/api/profile",
+            get(routes::profile::get_profile)
+                .layer(from_fn_with_state(state.clone(), auth_middleware::<ProfileRead>)),
+        )
+        .route(
+            "/api/profile/update",
+            put(routes::profile::update_profile)
+                .layer(require_scopes(["profile:write"]))
+                .layer(from_fn_with_state(state.clone(), auth_middleware::<ProfileRead>)),
+        )
+        .route(
+            "/api/profile/photo",
+            post(routes::profile::upload_photo)
+                // Leave room for the multipart framing around the photo itself
+                .layer(DefaultBodyLimit::max(imaging::MAX_PHOTO_BYTES + 64 * 1024))
+                .layer(require_scopes(["profile:write"]))
+                .layer(from_fn_with_state(state.clone(), auth_middleware::<ProfileRead>)),
+        )
+        .route(
+            "/api/profile/photo",
+            delete(routes::profile::delete_photo)
+                .layer(require_scopes(["profile:write"]))
+                .layer(from_fn_with_state(state.clone(), auth_middleware::<ProfileRead>)),
+        )
+        .route(
+            "/api/profile/photo/uploads",
+            post(routes::photo_uploads::create_upload)
+                .layer(require_scopes(["profile:write"]))
+                .layer(from_fn_with_state(state.clone(), auth_middleware::<ProfileRead>)),
+        )
+        .route(
+            "/api/profile/photo/uploads/:id",
+            get(routes::photo_uploads::get_upload_state)
+                .layer(from_fn_with_state(state.clone(), auth_middleware::<ProfileRead>)),
+        )
+        .route(
+            "/api/profile/photo/uploads/:id",
+            patch(routes::photo_uploads::patch_upload)
+                .layer(DefaultBodyLimit::max(routes::photo_uploads::MAX_CHUNK_BYTES))
+                .layer(require_scopes(["profile:write"]))
+                .layer(from_fn_with_state(state.clone(), auth_middleware::<ProfileRead>)),
+        )
+        .route(
+            "/api/profile/photo/uploads/:id",
+            delete(routes::photo_uploads::delete_upload)
+                .layer(require_scopes(["profile:write"]))
+                .layer(from_fn_with_state(state.clone(), auth_middleware::<ProfileRead>)),
+        )
+        .route(
+            "/api/profile/photo/uploads/:id/complete",
+            post(routes::photo_uploads::complete_upload)
+                .layer(require_scopes(["profile:write"]))
+                .layer(from_fn_with_state(state.clone(), auth_middleware::<ProfileRead>)),
+        )
+        .route(
+            "/api/profile/logins",
+            get(routes::sessions::get_login_history)
+                .layer(from_fn_with_state(state.clone(), auth_middleware::<ProfileRead>)),
+        )
+        .route(
+            "/api/profile/sessions",
+            get(routes::sessions::get_sessions)
+                .layer(from_fn_with_state(state.clone(), auth_middleware::<ProfileRead>)),
+        )
+        .route(
+            "/api/profile/sessions",
+            delete(routes::sessions::delete_other_sessions)
+                .layer(require_scopes(["profile:write"]))
+                .layer(from_fn(reject_impersonation))
+                .layer(from_fn_with_state(state.clone(), auth_middleware::<ProfileRead>)),
+        )
+        .route(
+            "/api/profile/sessions/:id",
+            delete(routes::sessions::delete_session)
+                .layer(require_scopes(["profile:write"]))
+                .layer(from_fn(reject_impersonation))
+                .layer(from_fn_with_state(state.clone(), auth_middleware::<ProfileRead>)),
+        )
         .route(
             "/api/admin",
             get(protected::admin_route)
-                .layer(from_fn_with_state(pool.clone(), auth_middleware::<Role>)),
+                .layer(from_fn_with_state(state.clone(), auth_middleware::<AdminAccess>)),
         )
         .route(
             "/api/user",
             get(protected::user_route)
-                .layer(from_fn_with_state(pool.clone(), auth_middleware::<Role>)),
+                .layer(from_fn_with_state(state.clone(), auth_middleware::<UserAccess>)),
+        )
+        .route(
+            "/api/admin/users",
+            get(routes::users::get_users)
+                .layer(from_fn_with_state(state.clone(), auth_middleware::<ReadUsers>)),
+        )
+        .route(
+            "/api/admin/users/count",
+            get(routes::users::get_user_count)
+                .layer(from_fn_with_state(state.clone(), auth_middleware::<ReadUsers>)),
+        )
+        .route(
+            "/api/admin/users/:id",
+            get(routes::users::get_user_detail)
+                .layer(from_fn_with_state(state.clone(), auth_middleware::<ReadUsers>)),
+        )
+        // Same path, stricter requirement; axum merges the method routers
+        .route(
+            "/api/admin/users/:id",
+            delete(routes::users::delete_user_by_id)
+                .layer(from_fn_with_state(state.clone(), auth_middleware::<ManageUsers>)),
+        )
+        .route(
+            "/api/admin/users/:id/actions",
+            get(routes::users::get_user_actions)
+                .layer(from_fn_with_state(state.clone(), auth_middleware::<ReadUsers>)),
         )
-        .nest_service("/uploads", ServeDir::new(PathBuf::from("backend/uploads")))
-        .with_state(pool)
+        .route(
+            "/api/admin/users/:id/impersonate",
+            post(routes::users::impersonate)
+                .layer(from_fn(reject_impersonation))
+                .layer(from_fn_with_state(state.clone(), auth_middleware::<Impersonate>)),
+        )
+        .route(
+            "/api/admin/users/:id/disable",
+            post(routes::users::disable)
+                .layer(from_fn_with_state(state.clone(), auth_middleware::<ManageUsers>)),
+        )
+        .route(
+            "/api/admin/users/:id/enable",
+            post(routes::users::enable)
+                .layer(from_fn_with_state(state.clone(), auth_middleware::<ManageUsers>)),
+        )
+        .route(
+            "/api/admin/users/:id/force-password-reset",
+            post(routes::users::force_password_reset)
+                .layer(from_fn_with_state(state.clone(), auth_middleware::<ManageUsers>)),
+        )
+        .route(
+            "/api/admin/users/:id/role",
+            put(routes::users::put_user_role)
+                .layer(from_fn_with_state(state.clone(), auth_middleware::<ManageUsers>)),
+        )
+        .route(
+            "/api/admin/audit",
+            get(routes::audit::get_audit_events)
+                .layer(from_fn_with_state(state.clone(), auth_middleware::<ReadAudit>)),
+        )
+        .route(
+            "/api/admin/audit/export",
+            get(routes::audit::export_audit_events)
+                .layer(from_fn_with_state(state.clone(), auth_middleware::<ReadAudit>)),
+        )
+        .route(
+            "/api/admin/audit/verify",
+            get(routes::audit::verify_audit_chain)
+                .layer(from_fn_with_state(state.clone(), auth_middleware::<ReadAudit>)),
+        )
+        .route(
+            "/api/admin/mail/templates",
+            get(routes::mail::get_mail_templates)
+                .layer(from_fn_with_state(state.clone(), auth_middleware::<AdminAccess>)),
+        )
+        .route(
+            "/api/admin/mail/templates/:template/preview",
+            get(routes::mail::preview_mail_template)
+                .layer(from_fn_with_state(state.clone(), auth_middleware::<AdminAccess>)),
+        )
+        .route(
+            "/api/admin/roles",
+            get(routes::roles::get_roles)
+                .layer(from_fn_with_state(state.clone(), auth_middleware::<ManageRoles>)),
+        )
+        .route(
+            "/api/admin/roles/:name",
+            put(routes::roles::put_role)
+                .layer(from_fn_with_state(state.clone(), auth_middleware::<ManageRoles>)),
+        )
+        .route(
+            "/api/admin/policies/explain",
+            post(routes::policies::explain)
+                .layer(from_fn_with_state(state.clone(), auth_middleware::<ExplainPolicies>)),
+        )
+        .route(
+            "/api/admin/groups",
+            get(routes::groups::get_groups)
+                .post(routes::groups::post_group)
+                .layer(from_fn_with_state(state.clone(), auth_middleware::<ManageGroups>)),
+        )
+        .route(
+            "/api/admin/groups/:id",
+            get(routes::groups::get_group_detail)
+                .delete(routes::groups::delete_group_by_id)
+                .layer(from_fn_with_state(state.clone(), auth_middleware::<ManageGroups>)),
+        )
+        .route(
+            "/api/admin/groups/:id/users/:user_id",
+            put(routes::groups::put_group_user)
+                .delete(routes::groups::delete_group_user)
+                .layer(from_fn_with_state(state.clone(), auth_middleware::<ManageGroups>)),
+        )
+        .route(
+            "/api/admin/groups/:id/groups/:child_id",
+            put(routes::groups::put_subgroup)
+                .delete(routes::groups::delete_subgroup)
+                .layer(from_fn_with_state(state.clone(), auth_middleware::<ManageGroups>)),
+        )
+        .route(
+            "/api/admin/groups/:id/roles/:role",
+            put(routes::groups::put_group_role)
+                .delete(routes::groups::delete_group_role)
+                .layer(from_fn_with_state(state.clone(), auth_middleware::<ManageGroups>)),
+        )
+        .route(
+            "/api/admin/groups/:id/permissions/:permission",
+            put(routes::groups::put_group_permission)
+                .delete(routes::groups::delete_group_permission)
+                .layer(from_fn_with_state(state.clone(), auth_middleware::<ManageGroups>)),
+        )
+        .route(
+            "/api/orgs",
+            get(routes::organizations::list_my_orgs)
+                .post(routes::organizations::create_org)
+                .layer(from_fn_with_state(state.clone(), auth_middleware::<UserAccess>)),
+        )
+        .route(
+            "/api/orgs/:id/switch",
+            post(routes::organizations::switch_org)
+                .layer(from_fn(reject_impersonation))
+                .layer(from_fn_with_state(state.clone(), auth_middleware::<UserAccess>)),
+        )
+        .route(
+            "/api/invitations/:token/accept",
+            post(routes::organizations::accept)
+                .layer(from_fn(reject_impersonation))
+                .layer(from_fn_with_state(state.clone(), auth_middleware::<UserAccess>)),
+        )
+        .route(
+            "/api/org/members",
+            get(routes::organizations::get_members)
+                .layer(from_fn_with_state(state.clone(), org_middleware::<OrgMember>))
+                .layer(from_fn_with_state(state.clone(), auth_middleware::<UserAccess>)),
+        )
+        .route(
+            "/api/org/members/:user_id",
+            put(routes::organizations::put_member_role)
+                .delete(routes::organizations::delete_member)
+                .layer(from_fn_with_state(state.clone(), org_middleware::<OrgAdmin>))
+                .layer(from_fn_with_state(state.clone(), auth_middleware::<UserAccess>)),
+        )
+        .route(
+            "/api/org/invitations",
+            get(routes::organizations::get_invitations)
+                .post(routes::organizations::invite)
+                .layer(from_fn_with_state(state.clone(), org_middleware::<OrgAdmin>))
+                .layer(from_fn_with_state(state.clone(), auth_middleware::<UserAccess>)),
+        )
+        .route(
+            "/authz/tuples",
+            get(routes::authz::get_tuples)
+                .post(routes::authz::post_tuple)
+                .delete(routes::authz::delete_tuple_route)
+                .layer(from_fn_with_state(state.clone(), auth_middleware::<ManageRelations>)),
+        )
+        .route(
+            "/authz/check",
+            post(routes::authz::post_check)
+                .layer(from_fn_with_state(state.clone(), auth_middleware::<UserAccess>)),
+        )
+        .route(
+            "/authz/expand",
+            post(routes::authz::post_expand)
+                .layer(from_fn_with_state(state.clone(), auth_middleware::<UserAccess>)),
+        )
+        .route(
+            "/authz/list-objects",
+            post(routes::authz::post_list_objects)
+                .layer(from_fn_with_state(state.clone(), auth_middleware::<UserAccess>)),
+        )
+        .route("/uploads/*key", get(routes::uploads::get_upload))
+        .route(
+            "/api/users/:id/avatar",
+            get(routes::avatars::get_avatar)
+                .layer(from_fn_with_state(state.clone(), auth_middleware::<UserAccess>)),
+        )
+        .merge(metrics_routes)
+        // Per-route request metrics, labelled by the matched route template
+        .route_layer(from_fn(telemetry::metrics::track_http))
+        .with_state(state)
         .merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", ApiDoc::openapi()))
-        .layer(cors);
+        .layer(cors)
+        // Outermost first: assign a request id, open the request span, echo the id back
+        .layer(
+            ServiceBuilder::new()
+                .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+                .layer(
+                    TraceLayer::new_for_http()
+                        .make_span_with(telemetry::request_span)
+                        .on_response(DefaultOnResponse::new().level(Level::INFO))
+                        .on_failure(DefaultOnFailure::new().level(Level::ERROR)),
+                )
+                .layer(PropagateRequestIdLayer::x_request_id()),
+        );
 
-    println!("🚀 Server running on https://backend-auth-system.onrender.com");
-    println!("📚 Swagger UI available at https://backend-auth-system.onrender.com/swagger-ui/");
-    println!("💚 Health check available at https://backend-auth-system.onrender.com/health");
+    tracing::info!(port = config.server.port, "Server running");
 
-    let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", get_port()))
+    let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", config.server.port))
+        .await
+        .unwrap();
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(shutdown_signal(
+            draining,
+            Duration::from_secs(config.server.shutdown_grace_seconds),
+        ))
         .await
         .unwrap();
-    axum::serve(listener, app).await.unwrap();
+    if let Err(e) = grpc_server.await {
+        tracing::error!(error = %e, "gRPC server task failed");
+    }
+    tracing::info!("Server stopped");
+    telemetry::otel::shutdown();
 }
@@ -10,68 +10,401 @@ use axum_extra::{
 };
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 use sqlx::{Pool, Postgres};
-use crate::db::queries::get_user_by_email;
+use std::sync::Arc;
+use uuid::Uuid;
+use crate::{
+    config::config::app_config,
+    db::{
+        queries::{get_user_by_email, get_user_by_id},
+        sessions::touch_session,
+        users::log_admin_action,
+    },
+    middleware::{client::ClientInfo, permissions::PermissionCache},
+    models::{
+        role::{EffectivePermissions, Requirement},
+        user::User,
+    },
+    policy::{
+        engine::{attributes, evaluate, PolicyRequest},
+        schema::{PolicyMode, PolicySet},
+    },
+    telemetry::{self, metrics},
+};
 
-const JWT_SECRET: &[u8] = b"your-secret-key";
+/// Permissions a user may exercise while they must change their password
+pub const PASSWORD_CHANGE_SCOPE: &str = "profile:read profile:write";
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String,  // email
     pub role: String, // user role
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org: Option<i32>, // active organization
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>, // space-delimited permissions the token is limited to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<Uuid>, // session the token belongs to, revocable by the user
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub act: Option<Actor>, // administrator impersonating `sub` (RFC 8693 actor claim)
     pub exp: usize,   // expiration time
 }
 
-pub fn create_token(email: &str, role: &str) -> String {
-    let expiration = chrono::Utc::now()
-        .checked_add_signed(chrono::Duration::hours(24))
-        .expect("valid timestamp")
-        .timestamp() as usize;
+/// The administrator behind an impersonation token
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Actor {
+    pub sub: String, // administrator email
+    pub id: i32,     // administrator user id
+}
 
-    let claims = Claims {
-        sub: email.to_string(),
-        role: role.to_string(),
-        exp: expiration,
-    };
+fn expires_in(duration: chrono::Duration) -> usize {
+    chrono::Utc::now()
+        .checked_add_signed(duration)
+        .expect("valid timestamp")
+        .timestamp() as usize
+}
 
+fn sign(claims: &Claims) -> String {
     encode(
         &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(JWT_SECRET),
+        claims,
+        &EncodingKey::from_secret(app_config().auth.jwt_secret.expose().as_bytes()),
     )
     .unwrap()
 }
 
+/// Lifetime of regular tokens, and of the sessions they belong to
+pub fn token_ttl() -> chrono::Duration {
+    chrono::Duration::hours(app_config().auth.token_ttl_hours)
+}
+
+pub fn create_token(
+    email: &str,
+    role: &str,
+    org: Option<i32>,
+    scope: Option<&str>,
+    sid: Option<Uuid>,
+) -> String {
+    sign(&Claims {
+        sub: email.to_string(),
+        role: role.to_string(),
+        org,
+        scope: scope.map(|s| s.to_string()),
+        sid,
+        act: None,
+        exp: expires_in(token_ttl()),
+    })
+}
+
+/// Mints a short-lived token letting `admin` act as `user`; returns the token and its expiry
+pub fn create_impersonation_token(
+    user: &User,
+    admin: &User,
+    scope: &str,
+    ttl: chrono::Duration,
+) -> (String, usize) {
+    let claims = Claims {
+        sub: user.email.clone(),
+        role: user.role.clone(),
+        org: None,
+        scope: Some(scope.to_string()),
+        sid: None,
+        act: Some(Actor {
+            sub: admin.email.clone(),
+            id: admin.id,
+        }),
+        exp: expires_in(ttl),
+    };
+    (sign(&claims), claims.exp)
+}
+
 pub fn decode_token(token: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
     let token_data = decode::<Claims>(
         token,
-        &DecodingKey::from_secret(JWT_SECRET),
+        &DecodingKey::from_secret(app_config().auth.jwt_secret.expose().as_bytes()),
         &Validation::default(),
     )?;
     Ok(token_data.claims)
 }
 
+/// Counts a refused token and builds the error returned for it
+fn rejected(result: &'static str, status: axum::http::StatusCode, message: &str) -> (axum::http::StatusCode, String) {
+    metrics::token_validation(result);
+    (status, message.to_string())
+}
+
+/// Signs and verifies a throwaway token, proving the signing key is usable
+pub fn check_signing_key() -> Result<(), String> {
+    let key = app_config().auth.jwt_secret.expose();
+    if key.is_empty() {
+        return Err("Signing key is not set".to_string());
+    }
+    let claims = Claims {
+        sub: "health-check".to_string(),
+        role: String::new(),
+        org: None,
+        scope: Some(String::new()),
+        sid: None,
+        act: None,
+        exp: expires_in(chrono::Duration::minutes(1)),
+    };
+    let token = encode(&Header::default(), &claims, &EncodingKey::from_secret(key.as_bytes()))
+        .map_err(|e| format!("Failed to sign: {}", e))?;
+    decode_token(&token)
+        .map(|_| ())
+        .map_err(|e| format!("Failed to verify: {}", e))
+}
+
+/// Resolves a raw JWT to its claims, the current user and the permissions the
+/// token may exercise. A token carrying a `scope` claim only exercises the
+/// permissions listed in it, and any token of a user who must change their
+/// password only exercises `PASSWORD_CHANGE_SCOPE`.
+pub async fn authenticate(
+    pool: &Pool<Postgres>,
+    permission_cache: &PermissionCache,
+    token: &str,
+) -> Result<(Claims, User, EffectivePermissions), (axum::http::StatusCode, String)> {
+    use axum::http::StatusCode;
+
+    let claims = decode_token(token)
+        .map_err(|_| rejected("invalid_token", StatusCode::UNAUTHORIZED, "Invalid token"))?;
+
+    let user = get_user_by_email(pool, &claims.sub)
+        .await
+        .map_err(|_| rejected("unknown_user", StatusCode::UNAUTHORIZED, "User not found"))?;
+
+    if user.disabled_at.is_some() {
+        return Err(rejected("account_disabled", StatusCode::UNAUTHORIZED, "Account disabled"));
+    }
+
+    // Revoked and expired sessions invalidate every token minted for them
+    if let Some(sid) = claims.sid {
+        let active = touch_session(pool, sid, user.id).await.map_err(|_| {
+            rejected("error", StatusCode::INTERNAL_SERVER_ERROR, "Failed to resolve session")
+        })?;
+        if !active {
+            return Err(rejected("session_revoked", StatusCode::UNAUTHORIZED, "Session revoked"));
+        }
+    }
+
+    // Impersonation ends as soon as the administrator is disabled or removed
+    if let Some(actor) = &claims.act {
+        match get_user_by_id(pool, actor.id).await {
+            Ok(admin) if admin.email == actor.sub && admin.disabled_at.is_none() => {}
+            _ => {
+                return Err(rejected(
+                    "impersonation_invalid",
+                    StatusCode::UNAUTHORIZED,
+                    "Impersonation no longer valid",
+                ))
+            }
+        }
+    }
+
+    let permissions = permission_cache
+        .get_or_load(pool, &user)
+        .await
+        .map_err(|_| {
+            rejected("error", StatusCode::INTERNAL_SERVER_ERROR, "Failed to resolve permissions")
+        })?;
+    let permissions = match claims.scope.as_deref() {
+        Some(scope) => permissions.restrict_to_scope(scope),
+        None => permissions,
+    };
+    // A pending password change also narrows tokens issued before it was required
+    let permissions = if user.must_change_password {
+        permissions.restrict_to_scope(PASSWORD_CHANGE_SCOPE)
+    } else {
+        permissions
+    };
+
+    metrics::token_validation("valid");
+    Ok((claims, user, permissions))
+}
+
+/// Evaluates the route policies for `method path`, returning the reason an
+/// enforced policy set denies the request. Dry-run denials are only logged.
+pub fn check_policies(
+    policies: &PolicySet,
+    claims: &Claims,
+    user: &User,
+    permissions: &EffectivePermissions,
+    client: &ClientInfo,
+    method: &str,
+    path: &str,
+) -> Result<(), String> {
+    let decision = evaluate(
+        policies,
+        method,
+        path,
+        attributes(&PolicyRequest {
+            user,
+            permissions,
+            method,
+            path,
+            ip: client.ip,
+            organization: claims.org,
+        }),
+    );
+    if !decision.allowed {
+        match decision.mode {
+            PolicyMode::Enforce => return Err(decision.reason),
+            PolicyMode::DryRun => {
+                tracing::warn!(%method, %path, reason = %decision.reason, "Policy dry run would deny request");
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Records a request made with an impersonation token as an
+/// `impersonated_request` admin action on the impersonated user, attributed
+/// to the administrator; ordinary tokens are not recorded. Every entry point
+/// accepting tokens (HTTP, forward-auth and gRPC) calls this after
+/// authenticating.
+pub async fn record_impersonated_request(
+    pool: &Pool<Postgres>,
+    client: &ClientInfo,
+    claims: &Claims,
+    user_id: i32,
+    details: Value,
+) {
+    let Some(actor) = &claims.act else {
+        return;
+    };
+    let context = client.audit(Some(actor.id));
+    if let Err(e) = log_admin_action(pool, &context, user_id, "impersonated_request", details).await {
+        tracing::warn!(error = %e, "Failed to record impersonated request");
+    }
+}
+
+/// Authenticates the bearer token, checks `R::PERMISSION` against the
+/// user's effective (inherited) permissions and evaluates the route policies.
+///
+/// The scope-narrowed permission set is what downstream handlers and
+/// `require_scopes` see. Requests made with an impersonation token are
+/// recorded in the target user's audit trail.
 pub async fn auth_middleware<R>(
     State(pool): State<Pool<Postgres>>,
+    State(permission_cache): State<PermissionCache>,
+    State(policies): State<Arc<PolicySet>>,
+    client: ClientInfo,
     TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
     mut request: Request<axum::body::Body>,
     next: Next,
 ) -> Result<Response, (axum::http::StatusCode, String)>
 where
-    R: std::str::FromStr + PartialEq + ToString,
+    R: Requirement,
 {
-    let claims = decode_token(auth.token())
-        .map_err(|_| (axum::http::StatusCode::UNAUTHORIZED, "Invalid token".to_string()))?;
+    let (claims, user, permissions) = authenticate(&pool, &permission_cache, auth.token()).await?;
+    telemetry::record_user(user.id);
 
-    let user = get_user_by_email(&pool, &claims.sub)
-        .await
-        .map_err(|_| {
-            (
-                axum::http::StatusCode::UNAUTHORIZED,
-                "User not found".to_string(),
-            )
-        })?;
+    if !permissions.contains(R::PERMISSION) {
+        return Err((
+            axum::http::StatusCode::FORBIDDEN,
+            format!("Missing permission: {}", R::PERMISSION),
+        ));
+    }
 
+    let method = request.method().to_string();
+    let path = request.uri().path().to_string();
+    check_policies(&policies, &claims, &user, &permissions, &client, &method, &path)
+        .map_err(|reason| (axum::http::StatusCode::FORBIDDEN, reason))?;
+
+    let impersonator = claims.clone();
+    request.extensions_mut().insert(claims);
+    request.extensions_mut().insert(permissions);
+    let user_id = user.id;
     request.extensions_mut().insert(user);
-    Ok(next.run(request).await)
+    let response = next.run(request).await;
+
+    let details = json!({
+        "method": method,
+        "path": path,
+        "status": response.status().as_u16(),
+    });
+    record_impersonated_request(&pool, &client, &impersonator, user_id, details).await;
+
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::config::init_for_tests,
+        models::{audit::AuditEvent, role::ProfileRead},
+        test_support::{insert_user, AuthState},
+    };
+    use axum::{body::Body, http::header, middleware::from_fn_with_state, routing::get, Router};
+    use tower::ServiceExt;
+
+    #[sqlx::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn pending_password_change_narrows_unscoped_tokens(pool: Pool<Postgres>) {
+        init_for_tests();
+        let user = insert_user(&pool, "admin@example.com", "Admin").await;
+        let token = create_token(&user.email, &user.role, None, None, None);
+        let cache = PermissionCache::default();
+
+        let (_, _, permissions) = authenticate(&pool, &cache, &token).await.unwrap();
+        assert!(permissions.contains("admin:access"));
+
+        sqlx::query("UPDATE users SET must_change_password = TRUE WHERE id = $1")
+            .bind(user.id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let (_, _, permissions) = authenticate(&pool, &cache, &token).await.unwrap();
+        assert_eq!(permissions.to_scope(), PASSWORD_CHANGE_SCOPE);
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn impersonated_requests_are_audited(pool: Pool<Postgres>) {
+        init_for_tests();
+        let admin = insert_user(&pool, "admin@example.com", "Admin").await;
+        let user = insert_user(&pool, "user@example.com", "User").await;
+        let app = Router::new().route(
+            "/api/profile",
+            get(|| async { "profile" }).layer(from_fn_with_state(
+                AuthState::new(&pool),
+                auth_middleware::<ProfileRead>,
+            )),
+        );
+        let call = |token: String| {
+            app.clone().oneshot(
+                Request::builder()
+                    .uri("/api/profile")
+                    .header(header::AUTHORIZATION, format!("Bearer {}", token))
+                    .header("x-request-id", "req-1")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+        };
+        let impersonated = || async {
+            sqlx::query_as::<_, AuditEvent>(
+                "SELECT * FROM audit_events WHERE details->>'action' = 'impersonated_request'",
+            )
+            .fetch_all(&pool)
+            .await
+            .unwrap()
+        };
+
+        let own = create_token(&user.email, &user.role, None, None, None);
+        assert_eq!(call(own).await.unwrap().status(), axum::http::StatusCode::OK);
+        assert!(impersonated().await.is_empty());
+
+        let (token, _) = create_impersonation_token(&user, &admin, "profile:read", chrono::Duration::minutes(5));
+        assert_eq!(call(token).await.unwrap().status(), axum::http::StatusCode::OK);
+        let events = impersonated().await;
+        assert_eq!(events.len(), 1);
+        let event = &events[0];
+        assert_eq!((event.actor_id, event.target_user_id), (Some(admin.id), Some(user.id)));
+        assert_eq!(event.request_id.as_deref(), Some("req-1"));
+        assert_eq!(event.details["method"], "GET");
+        assert_eq!(event.details["path"], "/api/profile");
+        assert_eq!(event.details["status"], 200);
+    }
 }
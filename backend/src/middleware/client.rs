@@ -0,0 +1,152 @@
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts},
+    http::{request::Parts, HeaderMap},
+};
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+
+use crate::{
+    config::config::app_config, models::audit::AuditContext, policy::engine::ip_in_ranges,
+};
+
+/// Network details of the caller
+#[derive(Debug, Clone, Default)]
+pub struct ClientInfo {
+    /// The peer address, or when the peer is a trusted proxy the right-most
+    /// `X-Forwarded-For` entry no trusted proxy added (see `client_ip`)
+    pub ip: Option<IpAddr>,
+    pub user_agent: Option<String>,
+    /// `X-Request-Id` from the proxy or client, or assigned on arrival; ties
+    /// audit events to the request's logs
+    pub request_id: Option<String>,
+}
+
+/// Resolves the caller's address. Clients can write any `X-Forwarded-For`
+/// they like, so the header is only read when `peer` is one of
+/// `trusted_proxies`, and only from the right: each trusted proxy appends the
+/// address it received the request from, so the first untrusted entry found
+/// walking leftwards is the client. Unparseable entries end the walk.
+pub fn client_ip(headers: &HeaderMap, peer: Option<IpAddr>, trusted_proxies: &[String]) -> Option<IpAddr> {
+    let is_trusted = |ip: IpAddr| ip_in_ranges(ip, trusted_proxies.iter().map(String::as_str));
+    let mut client = peer?;
+    if !is_trusted(client) {
+        return Some(client);
+    }
+
+    let hops = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .collect::<Vec<_>>();
+    for hop in hops.into_iter().rev() {
+        let Ok(ip) = hop.trim().parse::<IpAddr>() else {
+            break;
+        };
+        client = ip;
+        if !is_trusted(ip) {
+            break;
+        }
+    }
+    Some(client)
+}
+
+impl ClientInfo {
+    pub fn from_headers(headers: &HeaderMap, peer: Option<SocketAddr>, trusted_proxies: &[String]) -> Self {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.to_string())
+        };
+
+        ClientInfo {
+            ip: client_ip(headers, peer.map(|addr| addr.ip()), trusted_proxies),
+            user_agent: header("user-agent"),
+            request_id: header("x-request-id"),
+        }
+    }
+
+    /// Audit context for an action performed by `actor_id` from this client
+    pub fn audit(&self, actor_id: Option<i32>) -> AuditContext {
+        AuditContext {
+            actor_id,
+            ip: self.ip.map(|ip| ip.to_string()),
+            user_agent: self.user_agent.clone(),
+            request_id: self.request_id.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for ClientInfo
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let peer = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| *addr);
+        Ok(ClientInfo::from_headers(
+            &parts.headers,
+            peer,
+            &app_config().server.trusted_proxies,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    const PROXIES: &[&str] = &["10.0.0.0/8"];
+
+    fn resolve(forwarded: Option<&str>, peer: &str) -> Option<IpAddr> {
+        let mut headers = HeaderMap::new();
+        if let Some(forwarded) = forwarded {
+            headers.insert("x-forwarded-for", HeaderValue::from_str(forwarded).unwrap());
+        }
+        let proxies: Vec<String> = PROXIES.iter().map(|p| p.to_string()).collect();
+        client_ip(&headers, Some(peer.parse().unwrap()), &proxies)
+    }
+
+    fn ip(ip: &str) -> Option<IpAddr> {
+        Some(ip.parse().unwrap())
+    }
+
+    #[test]
+    fn ignores_forwarded_for_from_untrusted_peers() {
+        assert_eq!(resolve(Some("192.0.2.1"), "198.51.100.7"), ip("198.51.100.7"));
+    }
+
+    #[test]
+    fn takes_the_entry_the_trusted_proxy_appended() {
+        // The client sent a spoofed first entry; the proxy appended the real address
+        assert_eq!(resolve(Some("192.0.2.1, 198.51.100.7"), "10.0.0.2"), ip("198.51.100.7"));
+    }
+
+    #[test]
+    fn skips_trusted_proxies_walking_from_the_right() {
+        assert_eq!(resolve(Some("198.51.100.7, 10.0.0.3"), "10.0.0.2"), ip("198.51.100.7"));
+    }
+
+    #[test]
+    fn stops_at_unparseable_entries() {
+        assert_eq!(resolve(Some("198.51.100.7, garbage, 10.0.0.3"), "10.0.0.2"), ip("10.0.0.3"));
+    }
+
+    #[test]
+    fn falls_back_to_the_peer_without_the_header() {
+        assert_eq!(resolve(None, "10.0.0.2"), ip("10.0.0.2"));
+    }
+
+    #[test]
+    fn unknown_peer_has_no_address() {
+        assert_eq!(client_ip(&HeaderMap::new(), None, &[]), None);
+    }
+}
@@ -0,0 +1,97 @@
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+
+use crate::middleware::auth::Claims;
+
+/// Route middleware rejecting impersonation tokens on sensitive routes, such as
+/// those minting new tokens or changing credentials and second factors.
+///
+/// Must be applied inside `auth_middleware`, which inserts the `Claims`.
+pub async fn reject_impersonation(
+    request: Request<Body>,
+    next: Next,
+) -> Result<Response, (StatusCode, String)> {
+    let impersonating = request
+        .extensions()
+        .get::<Claims>()
+        .is_some_and(|claims| claims.act.is_some());
+    if impersonating {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "Not allowed while impersonating".to_string(),
+        ));
+    }
+    Ok(next.run(request).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::config::init_for_tests,
+        middleware::auth::{auth_middleware, create_impersonation_token, create_token},
+        models::role::ProfileRead,
+        test_support::{insert_user, AuthState},
+    };
+    use axum::{
+        http::{header, Method},
+        middleware::{from_fn, from_fn_with_state},
+        routing::{delete, get},
+        Router,
+    };
+    use sqlx::{Pool, Postgres};
+    use tower::ServiceExt;
+
+    #[sqlx::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn impersonation_tokens_are_refused_on_sensitive_routes(pool: Pool<Postgres>) {
+        init_for_tests();
+        let admin = insert_user(&pool, "admin@example.com", "Admin").await;
+        let user = insert_user(&pool, "user@example.com", "User").await;
+        let state = AuthState::new(&pool);
+        // Layered as in `main`: listing sessions is allowed, revoking them is not
+        let app = Router::new()
+            .route(
+                "/api/profile/sessions",
+                get(|| async { "listed" })
+                    .layer(from_fn_with_state(state.clone(), auth_middleware::<ProfileRead>)),
+            )
+            .route(
+                "/api/profile/sessions",
+                delete(|| async { "revoked" })
+                    .layer(from_fn(reject_impersonation))
+                    .layer(from_fn_with_state(state.clone(), auth_middleware::<ProfileRead>)),
+            );
+        let call = |method: Method, token: &str| {
+            app.clone().oneshot(
+                Request::builder()
+                    .method(method)
+                    .uri("/api/profile/sessions")
+                    .header(header::AUTHORIZATION, format!("Bearer {}", token))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+        };
+
+        let (impersonation, _) = create_impersonation_token(
+            &user,
+            &admin,
+            "profile:read profile:write",
+            chrono::Duration::minutes(5),
+        );
+        let response = call(Method::DELETE, &impersonation).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(body, "Not allowed while impersonating");
+        let response = call(Method::GET, &impersonation).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let own = create_token(&user.email, &user.role, None, None, None);
+        let response = call(Method::DELETE, &own).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
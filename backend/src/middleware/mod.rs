@@ -1 +1,6 @@
-pub mod auth; 
\ No newline at end of file
+pub mod auth;
+pub mod client;
+pub mod impersonation;
+pub mod organization;
+pub mod permissions;
+pub mod scopes;
@@ -0,0 +1,66 @@
+use axum::{
+    extract::{Extension, State},
+    http::{Request, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use sqlx::{Pool, Postgres};
+
+use crate::{
+    db::organizations::get_membership_role,
+    middleware::auth::Claims,
+    models::{
+        organization::{ActiveOrganization, OrgRequirement, OrgRole},
+        user::User,
+    },
+};
+
+/// Resolves the token's active organization and checks the caller's membership
+/// role against `R::MIN_ROLE`.
+///
+/// Must run after `auth_middleware`, which provides the `Claims` and `User`
+/// extensions. Membership is re-checked on every request, so removing a member
+/// takes effect immediately even though their token still names the organization.
+pub async fn org_middleware<R>(
+    State(pool): State<Pool<Postgres>>,
+    Extension(claims): Extension<Claims>,
+    Extension(user): Extension<User>,
+    mut request: Request<axum::body::Body>,
+    next: Next,
+) -> Result<Response, (StatusCode, String)>
+where
+    R: OrgRequirement,
+{
+    let organization_id = claims.org.ok_or((
+        StatusCode::FORBIDDEN,
+        "No active organization selected".to_string(),
+    ))?;
+
+    let role = get_membership_role(&pool, organization_id, user.id)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to resolve organization membership".to_string(),
+            )
+        })?
+        .ok_or((
+            StatusCode::FORBIDDEN,
+            "Not a member of the active organization".to_string(),
+        ))?
+        .parse::<OrgRole>()
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    if !role.includes(R::MIN_ROLE) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            format!("Requires organization role {}", R::MIN_ROLE),
+        ));
+    }
+
+    request.extensions_mut().insert(ActiveOrganization {
+        id: organization_id,
+        role,
+    });
+    Ok(next.run(request).await)
+}
@@ -0,0 +1,170 @@
+use sqlx::{Pool, Postgres};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::{
+    db::roles::get_user_permissions,
+    models::{role::EffectivePermissions, user::User},
+};
+
+/// How long an entry is reused before permissions are loaded again. Edits made
+/// on another replica only clear that replica's cache, so this bounds how long
+/// a revoked permission can outlive the change.
+const DEFAULT_TTL: Duration = Duration::from_secs(30);
+
+/// Users cached at once; the oldest entry is evicted beyond this
+const DEFAULT_CAPACITY: usize = 10_000;
+
+struct CachedPermissions {
+    role: String,
+    permissions: EffectivePermissions,
+    loaded_at: Instant,
+}
+
+/// Per-user cache of effective permissions.
+///
+/// An entry is only reused while the user's role is unchanged and for at most
+/// the cache's TTL, so role assignments take effect on the next request and
+/// edits made on other replicas within the TTL. Editing role definitions or
+/// group memberships and grants must still call `invalidate_all`, which
+/// applies them immediately on this replica.
+#[derive(Clone)]
+pub struct PermissionCache {
+    entries: Arc<RwLock<HashMap<i32, CachedPermissions>>>,
+    ttl: Duration,
+    capacity: usize,
+}
+
+impl Default for PermissionCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_TTL, DEFAULT_CAPACITY)
+    }
+}
+
+impl PermissionCache {
+    pub fn new(ttl: Duration, capacity: usize) -> Self {
+        Self {
+            entries: Arc::default(),
+            ttl,
+            capacity,
+        }
+    }
+
+    pub async fn get_or_load(
+        &self,
+        pool: &Pool<Postgres>,
+        user: &User,
+    ) -> Result<EffectivePermissions, sqlx::Error> {
+        if let Some(permissions) = self.cached(user.id, &user.role) {
+            return Ok(permissions);
+        }
+
+        let permissions = EffectivePermissions::new(get_user_permissions(pool, user.id).await?);
+        self.store(user.id, &user.role, permissions.clone());
+        Ok(permissions)
+    }
+
+    fn cached(&self, user_id: i32, role: &str) -> Option<EffectivePermissions> {
+        let entries = self.entries.read().unwrap();
+        entries
+            .get(&user_id)
+            .filter(|cached| cached.role == role && cached.loaded_at.elapsed() < self.ttl)
+            .map(|cached| cached.permissions.clone())
+    }
+
+    fn store(&self, user_id: i32, role: &str, permissions: EffectivePermissions) {
+        let mut entries = self.entries.write().unwrap();
+        if entries.len() >= self.capacity && !entries.contains_key(&user_id) {
+            entries.retain(|_, cached| cached.loaded_at.elapsed() < self.ttl);
+            if entries.len() >= self.capacity {
+                let oldest = entries
+                    .iter()
+                    .min_by_key(|(_, cached)| cached.loaded_at)
+                    .map(|(id, _)| *id);
+                if let Some(oldest) = oldest {
+                    entries.remove(&oldest);
+                }
+            }
+        }
+        entries.insert(
+            user_id,
+            CachedPermissions {
+                role: role.to_string(),
+                permissions,
+                loaded_at: Instant::now(),
+            },
+        );
+    }
+
+    pub fn invalidate_user(&self, user_id: i32) {
+        self.entries.write().unwrap().remove(&user_id);
+    }
+
+    pub fn invalidate_all(&self) {
+        tracing::debug!("Clearing permission cache");
+        self.entries.write().unwrap().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn permissions(names: &[&str]) -> EffectivePermissions {
+        EffectivePermissions::new(names.iter().map(|name| name.to_string()).collect())
+    }
+
+    #[test]
+    fn reuses_entries_for_the_same_role() {
+        let cache = PermissionCache::default();
+        cache.store(1, "User", permissions(&["profile:read"]));
+
+        let cached = cache.cached(1, "User").expect("entry is cached");
+        assert!(cached.contains("profile:read"));
+    }
+
+    #[test]
+    fn role_change_misses_the_cache() {
+        let cache = PermissionCache::default();
+        cache.store(1, "User", permissions(&["profile:read"]));
+
+        assert!(cache.cached(1, "Admin").is_none());
+    }
+
+    #[test]
+    fn expired_entries_are_not_reused() {
+        let cache = PermissionCache::new(Duration::ZERO, DEFAULT_CAPACITY);
+        cache.store(1, "User", permissions(&["profile:read"]));
+
+        assert!(cache.cached(1, "User").is_none());
+    }
+
+    #[test]
+    fn evicts_the_oldest_entry_at_capacity() {
+        let cache = PermissionCache::new(DEFAULT_TTL, 2);
+        for user_id in 1..=3 {
+            cache.store(user_id, "User", permissions(&[]));
+            std::thread::sleep(Duration::from_millis(2));
+        }
+
+        assert!(cache.cached(1, "User").is_none());
+        assert!(cache.cached(2, "User").is_some());
+        assert!(cache.cached(3, "User").is_some());
+        assert_eq!(cache.entries.read().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn invalidation_drops_entries() {
+        let cache = PermissionCache::default();
+        cache.store(1, "User", permissions(&[]));
+        cache.store(2, "User", permissions(&[]));
+
+        cache.invalidate_user(1);
+        assert!(cache.cached(1, "User").is_none());
+        assert!(cache.cached(2, "User").is_some());
+
+        cache.invalidate_all();
+        assert!(cache.cached(2, "User").is_none());
+    }
+}
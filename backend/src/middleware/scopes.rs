@@ -0,0 +1,91 @@
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    response::{IntoResponse, Response},
+};
+use std::convert::Infallible;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+
+use crate::models::role::EffectivePermissions;
+
+/// Route layer rejecting requests whose token lacks any of the given scopes.
+///
+/// Must be applied inside `auth_middleware`, which resolves the token's
+/// (scope-narrowed) permissions:
+///
+/// ```ignore
+/// put(handler)
+///     .layer(require_scopes(["profile:write"]))
+///     .layer(from_fn_with_state(state.clone(), auth_middleware::<ProfileRead>))
+/// ```
+pub fn require_scopes<const N: usize>(scopes: [&'static str; N]) -> RequireScopes {
+    RequireScopes {
+        scopes: Arc::from(scopes.as_slice()),
+    }
+}
+
+#[derive(Clone)]
+pub struct RequireScopes {
+    scopes: Arc<[&'static str]>,
+}
+
+impl<S> Layer<S> for RequireScopes {
+    type Service = RequireScopesService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequireScopesService {
+            inner,
+            scopes: self.scopes.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RequireScopesService<S> {
+    inner: S,
+    scopes: Arc<[&'static str]>,
+}
+
+impl<S> Service<Request<Body>> for RequireScopesService<S>
+where
+    S: Service<Request<Body>, Response = Response, Error = Infallible> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, Infallible>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let missing: Vec<&str> = match request.extensions().get::<EffectivePermissions>() {
+            Some(granted) => self
+                .scopes
+                .iter()
+                .copied()
+                .filter(|scope| !granted.contains(scope))
+                .collect(),
+            None => self.scopes.to_vec(),
+        };
+
+        if !missing.is_empty() {
+            let response = (
+                StatusCode::FORBIDDEN,
+                format!("Missing scope: {}", missing.join(" ")),
+            )
+                .into_response();
+            return Box::pin(async move { Ok(response) });
+        }
+
+        // Take the service that was driven to readiness, leaving a fresh clone behind
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        Box::pin(async move { inner.call(request).await })
+    }
+}
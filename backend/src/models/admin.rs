@@ -0,0 +1,171 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::{IntoParams, ToSchema};
+use validator::Validate;
+
+/// A user as seen by administrators; never includes the password hash
+#[derive(Debug, Serialize, FromRow, ToSchema, Clone)]
+pub struct UserSummary {
+    /// Unique identifier for the user
+    pub id: i32,
+    /// First name
+    pub firstname: String,
+    /// Last name
+    pub lastname: String,
+    /// Email address
+    pub email: String,
+    /// Role name
+    pub role: String,
+    /// Account creation date
+    pub created_at: Option<DateTime<Utc>>,
+    /// Last login date/time
+    pub last_login: Option<DateTime<Utc>>,
+    /// Total number of logins
+    pub login_count: Option<i32>,
+    /// Profile picture URL
+    pub profile_picture: Option<String>,
+    /// Whether the email address has been verified
+    pub email_verified: bool,
+    /// When the account was disabled
+    pub disabled_at: Option<DateTime<Utc>>,
+    /// Why the account was disabled
+    pub disabled_reason: Option<String>,
+    /// Whether a password change is required at next login
+    pub must_change_password: bool,
+}
+
+/// A user with the roles and permissions they currently resolve to
+#[derive(Debug, Serialize, ToSchema)]
+pub struct UserDetail {
+    #[serde(flatten)]
+    pub user: UserSummary,
+    /// Roles held directly, through groups or through inheritance
+    pub roles: Vec<String>,
+    /// Effective permissions
+    pub permissions: Vec<String>,
+}
+
+/// Column the user list is ordered by; ties are broken by id
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum UserSort {
+    Id,
+    Email,
+    #[default]
+    CreatedAt,
+    LastLogin,
+    LoginCount,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+/// Filters shared by the user list and count endpoints
+#[derive(Debug, Default, Deserialize, IntoParams)]
+pub struct UserFilter {
+    /// Exact role name
+    pub role: Option<String>,
+    /// Case-insensitive substring of the email address
+    pub email: Option<String>,
+    /// Created at or after this time (RFC 3339)
+    #[param(value_type = Option<String>)]
+    pub created_after: Option<DateTime<Utc>>,
+    /// Created before this time (RFC 3339)
+    #[param(value_type = Option<String>)]
+    pub created_before: Option<DateTime<Utc>>,
+    /// Last logged in at or after this time (RFC 3339)
+    #[param(value_type = Option<String>)]
+    pub last_login_after: Option<DateTime<Utc>>,
+    /// Last logged in before this time (RFC 3339)
+    #[param(value_type = Option<String>)]
+    pub last_login_before: Option<DateTime<Utc>>,
+    /// Only users who have (true) or have never (false) logged in
+    pub has_logged_in: Option<bool>,
+    /// Only disabled (true) or active (false) accounts
+    pub disabled: Option<bool>,
+}
+
+/// Paging and ordering of the user list; combined with `UserFilter`
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct UserListQuery {
+    /// Sort column (default `created_at`)
+    #[serde(default)]
+    #[param(inline)]
+    pub sort: UserSort,
+    /// Sort direction (default `desc`)
+    #[serde(default)]
+    #[param(inline)]
+    pub order: SortOrder,
+    /// Page size, 1-200 (default 50)
+    pub limit: Option<i64>,
+    /// `next_cursor` from the previous page
+    pub cursor: Option<String>,
+}
+
+/// A page of users
+#[derive(Debug, Serialize, ToSchema)]
+pub struct UserPage {
+    pub users: Vec<UserSummary>,
+    /// Pass as `cursor` to fetch the next page; absent on the last page
+    pub next_cursor: Option<String>,
+}
+
+/// Number of users per role
+#[derive(Debug, Serialize, FromRow, ToSchema)]
+pub struct RoleCount {
+    pub role: String,
+    pub count: i64,
+}
+
+/// User totals for dashboards
+#[derive(Debug, Serialize, ToSchema)]
+pub struct UserCount {
+    /// Users matching the filters
+    pub total: i64,
+    /// Matching users per role
+    pub by_role: Vec<RoleCount>,
+}
+
+/// Request payload for disabling, enabling or forcing a password reset
+#[derive(Debug, Default, Deserialize, Validate, ToSchema)]
+pub struct AccountActionRequest {
+    /// Recorded in the audit trail; for `disable` also shown to administrators
+    #[validate(length(max = 500))]
+    pub reason: Option<String>,
+}
+
+/// Request payload for changing a user's role
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct ChangeRoleRequest {
+    /// New role name
+    #[validate(length(min = 1, max = 50))]
+    pub role: String,
+    /// Recorded in the audit trail
+    #[validate(length(max = 500))]
+    pub reason: Option<String>,
+}
+
+/// Request payload for impersonating a user
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct ImpersonationRequest {
+    /// Why the user is being impersonated, e.g. a support ticket; recorded in the audit trail
+    #[validate(length(min = 1, max = 500))]
+    pub reason: String,
+}
+
+/// A short-lived token acting as another user
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ImpersonationResponse {
+    /// Token carrying an `act` claim naming the administrator
+    pub token: String,
+    /// Expiration of the token
+    pub expires_at: DateTime<Utc>,
+    /// The impersonated user
+    pub user: UserSummary,
+}
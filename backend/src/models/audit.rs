@@ -0,0 +1,135 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::{IntoParams, ToSchema};
+
+/// Kinds of security events recorded in the audit log
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditEventType {
+    LoginSucceeded,
+    LoginFailed,
+    Registered,
+    ProfileUpdated,
+    EmailChanged,
+    EmailVerified,
+    PasswordChanged,
+    RoleChanged,
+    TokenIssued,
+    TokenRevoked,
+    LoginDisowned,
+    AdminAction,
+}
+
+impl AuditEventType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditEventType::LoginSucceeded => "login_succeeded",
+            AuditEventType::LoginFailed => "login_failed",
+            AuditEventType::Registered => "registered",
+            AuditEventType::ProfileUpdated => "profile_updated",
+            AuditEventType::EmailChanged => "email_changed",
+            AuditEventType::EmailVerified => "email_verified",
+            AuditEventType::PasswordChanged => "password_changed",
+            AuditEventType::RoleChanged => "role_changed",
+            AuditEventType::TokenIssued => "token_issued",
+            AuditEventType::TokenRevoked => "token_revoked",
+            AuditEventType::LoginDisowned => "login_disowned",
+            AuditEventType::AdminAction => "admin_action",
+        }
+    }
+}
+
+/// Who acted and from where; attached to every audit event
+#[derive(Debug, Clone, Default)]
+pub struct AuditContext {
+    pub actor_id: Option<i32>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub request_id: Option<String>,
+}
+
+/// A recorded security event
+#[derive(Debug, Serialize, FromRow, ToSchema)]
+pub struct AuditEvent {
+    pub id: i64,
+    /// One of the `AuditEventType` names
+    pub event_type: String,
+    /// User who performed the action, if known
+    pub actor_id: Option<i32>,
+    /// User the action applied to
+    pub target_user_id: Option<i32>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub request_id: Option<String>,
+    /// Event specific details
+    #[schema(value_type = Object)]
+    pub details: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+    /// Hash of the previous event
+    pub prev_hash: String,
+    /// SHA-256 over this event's fields and `prev_hash`
+    pub hash: String,
+}
+
+/// Filters for querying and exporting the audit log
+#[derive(Debug, Default, Deserialize, IntoParams)]
+pub struct AuditFilter {
+    #[param(inline)]
+    pub event_type: Option<AuditEventType>,
+    pub actor_id: Option<i32>,
+    pub target_user_id: Option<i32>,
+    /// Events at or after this time (RFC 3339)
+    #[param(value_type = Option<String>)]
+    pub from: Option<DateTime<Utc>>,
+    /// Events before this time (RFC 3339)
+    #[param(value_type = Option<String>)]
+    pub to: Option<DateTime<Utc>>,
+}
+
+/// Paging of the audit log, newest first
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct AuditPageQuery {
+    /// `next_before_id` from the previous page
+    pub before_id: Option<i64>,
+    /// Page size, 1-500 (default 100)
+    pub limit: Option<i64>,
+}
+
+/// A page of audit events
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AuditPage {
+    pub events: Vec<AuditEvent>,
+    /// Pass as `before_id` to fetch older events; absent on the last page
+    pub next_before_id: Option<i64>,
+}
+
+#[derive(Debug, Default, Clone, Copy, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    #[default]
+    Jsonl,
+    Csv,
+}
+
+/// Format of an audit log export
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ExportQuery {
+    /// `jsonl` (default) or `csv`
+    #[serde(default)]
+    #[param(inline)]
+    pub format: ExportFormat,
+}
+
+/// Result of re-computing the hash chain
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ChainVerification {
+    /// Whether every event matches its hash and links to its predecessor
+    pub valid: bool,
+    /// Number of events checked
+    pub events_checked: i64,
+    /// First event whose hash or link does not match
+    pub first_invalid_id: Option<i64>,
+    /// Why verification failed
+    pub reason: Option<String>,
+}
@@ -0,0 +1,35 @@
+use serde::Deserialize;
+use utoipa::{IntoParams, ToSchema};
+
+/// What a generated avatar shows
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum AvatarStyle {
+    /// Initials of the user's name; users whose names start with other
+    /// scripts get an identicon instead
+    #[default]
+    Initials,
+    /// Symmetric pattern derived from the user id
+    Identicon,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum AvatarFormat {
+    #[default]
+    Svg,
+    Png,
+}
+
+/// Query parameters of a generated avatar
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct AvatarQuery {
+    /// Edge length in pixels, 16-512 (default 128)
+    pub size: Option<u32>,
+    #[serde(default)]
+    #[param(inline)]
+    pub format: AvatarFormat,
+    #[serde(default)]
+    #[param(inline)]
+    pub style: AvatarStyle,
+}
@@ -0,0 +1,57 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use validator::Validate;
+
+/// A group of users and other groups
+#[derive(Debug, Serialize, Deserialize, FromRow, ToSchema, Clone)]
+pub struct Group {
+    /// Unique identifier for the group
+    pub id: i32,
+    /// Unique group name
+    pub name: String,
+    /// Human readable description
+    pub description: Option<String>,
+    /// Creation date
+    pub created_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// A user who belongs to a group, directly or through a subgroup
+#[derive(Debug, Serialize, FromRow, ToSchema)]
+pub struct GroupMember {
+    /// User identifier
+    pub id: i32,
+    /// First name
+    pub firstname: String,
+    /// Last name
+    pub lastname: String,
+    /// Email address
+    pub email: String,
+    /// Whether the user was added to this group directly
+    pub direct: bool,
+}
+
+/// A group with its grants and memberships
+#[derive(Debug, Serialize, ToSchema)]
+pub struct GroupDetail {
+    #[serde(flatten)]
+    pub group: Group,
+    /// Groups nested directly inside this one
+    pub subgroups: Vec<Group>,
+    /// Roles granted to every member
+    pub roles: Vec<String>,
+    /// Permissions granted to every member
+    pub permissions: Vec<String>,
+    /// Direct and transitive members
+    pub members: Vec<GroupMember>,
+}
+
+/// Request payload for creating a group
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct CreateGroupRequest {
+    /// Unique group name (2-100 characters)
+    #[validate(length(min = 2, max = 100))]
+    pub name: String,
+    /// Human readable description
+    pub description: Option<String>,
+}
@@ -0,0 +1,38 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ComponentStatus {
+    Up,
+    Down,
+}
+
+/// Outcome of one readiness check
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ComponentCheck {
+    pub status: ComponentStatus,
+    /// How long the check took
+    pub latency_ms: u64,
+    /// Why the component is down
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReadinessStatus {
+    Ready,
+    NotReady,
+    /// Shutting down; in-flight requests finish but no new traffic should arrive
+    Draining,
+}
+
+/// Whether the instance can serve traffic, with each dependency's check
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Readiness {
+    pub status: ReadinessStatus,
+    /// Keyed by component: `database`, `migrations`, `storage` and `signing_key`
+    pub checks: BTreeMap<String, ComponentCheck>,
+}
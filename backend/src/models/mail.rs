@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+/// Transactional emails the backend can send
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MailTemplate {
+    Verification,
+    PasswordReset,
+    Invitation,
+    LoginAlert,
+}
+
+impl MailTemplate {
+    pub const ALL: [MailTemplate; 4] = [
+        MailTemplate::Verification,
+        MailTemplate::PasswordReset,
+        MailTemplate::Invitation,
+        MailTemplate::LoginAlert,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MailTemplate::Verification => "verification",
+            MailTemplate::PasswordReset => "password_reset",
+            MailTemplate::Invitation => "invitation",
+            MailTemplate::LoginAlert => "login_alert",
+        }
+    }
+}
+
+/// A template and the locales it is available in
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MailTemplateInfo {
+    pub template: MailTemplate,
+    pub locales: Vec<String>,
+}
+
+/// How to return a rendered preview
+#[derive(Debug, Clone, Copy, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum PreviewFormat {
+    /// Subject and both bodies as JSON
+    #[default]
+    Json,
+    /// The HTML body, viewable in a browser
+    Html,
+    /// The plain text body
+    Text,
+}
+
+/// Query parameters of the template preview
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct PreviewQuery {
+    /// Locale to render, e.g. `fr` (default `en`)
+    pub locale: Option<String>,
+    #[serde(default)]
+    #[param(inline)]
+    pub format: PreviewFormat,
+}
+
+/// A template rendered with sample data
+#[derive(Debug, Serialize, ToSchema)]
+pub struct EmailPreview {
+    pub template: MailTemplate,
+    /// Locale actually rendered, after falling back
+    pub locale: String,
+    pub subject: String,
+    pub text: String,
+    pub html: String,
+}
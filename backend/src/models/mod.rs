@@ -1 +1,12 @@
+pub mod admin;
+pub mod audit;
+pub mod avatar;
+pub mod group;
+pub mod health;
+pub mod mail;
+pub mod organization;
+pub mod photo_upload;
+pub mod relation;
+pub mod role;
+pub mod session;
 pub mod user;
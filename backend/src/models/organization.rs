@@ -0,0 +1,177 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use std::str::FromStr;
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::Validate;
+
+/// An organization (tenant)
+#[derive(Debug, Serialize, Deserialize, FromRow, ToSchema, Clone)]
+pub struct Organization {
+    /// Unique identifier for the organization
+    pub id: i32,
+    /// Display name
+    pub name: String,
+    /// URL-friendly unique identifier
+    pub slug: String,
+    /// Creation date
+    pub created_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// An organization the current user belongs to
+#[derive(Debug, Serialize, FromRow, ToSchema)]
+pub struct OrganizationMembership {
+    /// Organization identifier
+    pub organization_id: i32,
+    /// Organization display name
+    pub name: String,
+    /// Organization slug
+    pub slug: String,
+    /// The user's role in the organization
+    pub role: String,
+    /// When the user joined
+    pub joined_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// A member of the active organization
+#[derive(Debug, Serialize, FromRow, ToSchema)]
+pub struct Member {
+    /// User identifier
+    pub user_id: i32,
+    /// First name
+    pub firstname: String,
+    /// Last name
+    pub lastname: String,
+    /// Email address
+    pub email: String,
+    /// Role within the organization
+    pub role: String,
+    /// When the user joined
+    pub joined_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// An invitation to join an organization
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct Invitation {
+    /// Invitation identifier
+    pub id: i32,
+    /// Organization the invitation is for
+    pub organization_id: i32,
+    /// Invited email address
+    pub email: String,
+    /// Role granted on acceptance
+    pub role: String,
+    /// Secret token used to accept the invitation
+    pub token: Uuid,
+    /// User who sent the invitation
+    pub invited_by: Option<i32>,
+    /// Creation date
+    pub created_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Expiry date
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+    /// Acceptance date, if accepted
+    pub accepted_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Roles within an organization; each role includes the ones below it
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone, Copy, PartialEq, Eq)]
+pub enum OrgRole {
+    /// Full control, including ownership transfer
+    Owner,
+    /// Manages members and invitations
+    Admin,
+    /// Regular member
+    Member,
+}
+
+impl OrgRole {
+    fn rank(&self) -> u8 {
+        match self {
+            OrgRole::Owner => 2,
+            OrgRole::Admin => 1,
+            OrgRole::Member => 0,
+        }
+    }
+
+    /// Whether this role grants everything `other` grants
+    pub fn includes(&self, other: OrgRole) -> bool {
+        self.rank() >= other.rank()
+    }
+}
+
+impl FromStr for OrgRole {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Owner" => Ok(OrgRole::Owner),
+            "Admin" => Ok(OrgRole::Admin),
+            "Member" => Ok(OrgRole::Member),
+            _ => Err(format!("Invalid organization role: {}", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for OrgRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            OrgRole::Owner => "Owner",
+            OrgRole::Admin => "Admin",
+            OrgRole::Member => "Member",
+        })
+    }
+}
+
+/// The organization selected by the token's `org` claim, with the caller's role in it
+#[derive(Debug, Clone, Copy)]
+pub struct ActiveOrganization {
+    pub id: i32,
+    pub role: OrgRole,
+}
+
+/// Minimum organization role a route requires, checked by `org_middleware`
+pub trait OrgRequirement {
+    const MIN_ROLE: OrgRole;
+}
+
+/// Any member of the active organization
+pub struct OrgMember;
+
+impl OrgRequirement for OrgMember {
+    const MIN_ROLE: OrgRole = OrgRole::Member;
+}
+
+/// Admins and owners of the active organization
+pub struct OrgAdmin;
+
+impl OrgRequirement for OrgAdmin {
+    const MIN_ROLE: OrgRole = OrgRole::Admin;
+}
+
+/// Request payload for creating an organization
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct CreateOrganizationRequest {
+    /// Display name (2-100 characters)
+    #[validate(length(min = 2, max = 100))]
+    pub name: String,
+    /// Unique slug (2-100 characters, lowercase letters, digits and dashes)
+    #[validate(length(min = 2, max = 100))]
+    pub slug: String,
+}
+
+/// Request payload for inviting someone to the active organization
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct InvitationRequest {
+    /// Email address to invite
+    #[validate(email)]
+    pub email: String,
+    /// Role granted on acceptance
+    pub role: OrgRole,
+}
+
+/// Request payload for changing a member's role
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct MemberRoleRequest {
+    /// New role within the organization
+    pub role: OrgRole,
+}
@@ -0,0 +1,31 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Request payload for starting a resumable photo upload
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreatePhotoUploadRequest {
+    /// Total size of the photo in bytes
+    pub size: i64,
+    /// Hex SHA-256 of the whole photo, verified on completion
+    pub sha256: String,
+}
+
+/// A resumable photo upload in progress
+#[derive(Debug, Serialize, FromRow, ToSchema)]
+pub struct PhotoUpload {
+    pub id: Uuid,
+    #[serde(skip)]
+    #[allow(dead_code)]
+    pub user_id: i32,
+    /// Total size of the photo in bytes
+    pub size: i64,
+    pub sha256: String,
+    /// Bytes received so far; the offset the next chunk must start at
+    pub received: i64,
+    pub created_at: DateTime<Utc>,
+    /// Incomplete uploads are discarded after this
+    pub expires_at: DateTime<Utc>,
+}
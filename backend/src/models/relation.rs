@@ -0,0 +1,183 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use std::fmt;
+use std::str::FromStr;
+use utoipa::{IntoParams, ToSchema};
+
+/// An object reference written as `type:id`, e.g. `doc:readme`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ObjectRef {
+    pub object_type: String,
+    pub object_id: String,
+}
+
+impl FromStr for ObjectRef {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some((object_type, object_id))
+                if !object_type.is_empty() && !object_id.is_empty() && !s.contains('#') =>
+            {
+                Ok(ObjectRef {
+                    object_type: object_type.to_string(),
+                    object_id: object_id.to_string(),
+                })
+            }
+            _ => Err(format!("Invalid object (expected type:id): {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for ObjectRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.object_type, self.object_id)
+    }
+}
+
+/// A subject: either an object (`user:42`) or a userset (`team:core#member`)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SubjectRef {
+    pub object: ObjectRef,
+    pub relation: Option<String>,
+}
+
+impl FromStr for SubjectRef {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (object, relation) = match s.split_once('#') {
+            Some((_, "")) => return Err(format!("Invalid subject (empty relation): {}", s)),
+            Some((object, relation)) => (object, Some(relation.to_string())),
+            None => (s, None),
+        };
+
+        Ok(SubjectRef {
+            object: object.parse()?,
+            relation,
+        })
+    }
+}
+
+impl fmt::Display for SubjectRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.relation {
+            Some(relation) => write!(f, "{}#{}", self.object, relation),
+            None => write!(f, "{}", self.object),
+        }
+    }
+}
+
+/// A stored relationship tuple
+#[derive(Debug, Clone, FromRow)]
+pub struct RelationTuple {
+    pub object_type: String,
+    pub object_id: String,
+    pub relation: String,
+    pub subject_type: String,
+    pub subject_id: String,
+    pub subject_relation: String,
+}
+
+impl RelationTuple {
+    pub fn object(&self) -> ObjectRef {
+        ObjectRef {
+            object_type: self.object_type.clone(),
+            object_id: self.object_id.clone(),
+        }
+    }
+
+    pub fn subject(&self) -> SubjectRef {
+        SubjectRef {
+            object: ObjectRef {
+                object_type: self.subject_type.clone(),
+                object_id: self.subject_id.clone(),
+            },
+            relation: Some(self.subject_relation.clone()).filter(|r| !r.is_empty()),
+        }
+    }
+}
+
+/// A relationship tuple as exchanged over the API
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct TupleRequest {
+    /// Object, e.g. `doc:readme`
+    pub object: String,
+    /// Relation on the object, e.g. `editor`
+    pub relation: String,
+    /// Subject, e.g. `user:42` or `team:core#member`
+    pub subject: String,
+}
+
+impl From<RelationTuple> for TupleRequest {
+    fn from(tuple: RelationTuple) -> Self {
+        TupleRequest {
+            object: tuple.object().to_string(),
+            subject: tuple.subject().to_string(),
+            relation: tuple.relation,
+        }
+    }
+}
+
+/// Query parameters for listing the tuples stored on an object
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct TupleQuery {
+    /// Object, e.g. `doc:readme`
+    pub object: String,
+}
+
+/// Request payload for a permission check
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CheckRequest {
+    /// Object, e.g. `doc:readme`
+    pub object: String,
+    /// Relation to check, e.g. `viewer`
+    pub relation: String,
+    /// Subject to check; defaults to the authenticated user (`user:<id>`)
+    pub subject: Option<String>,
+}
+
+/// Result of a permission check
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CheckResponse {
+    /// Whether the subject has the relation on the object
+    pub allowed: bool,
+}
+
+/// Request payload for expanding a relation into its userset tree
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ExpandRequest {
+    /// Object, e.g. `doc:readme`
+    pub object: String,
+    /// Relation to expand, e.g. `editor`
+    pub relation: String,
+}
+
+/// A node of an expanded userset tree
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ExpandNode {
+    /// The userset this node describes, e.g. `doc:readme#editor`
+    pub userset: String,
+    /// Subjects granted the relation directly
+    pub subjects: Vec<String>,
+    /// Usersets that contribute to this one (nested usersets, implied relations, parents)
+    pub children: Vec<ExpandNode>,
+}
+
+/// Request payload for listing the objects a subject has a relation on
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ListObjectsRequest {
+    /// Object type to search, e.g. `doc`
+    pub object_type: String,
+    /// Relation the subject must have, e.g. `viewer`
+    pub relation: String,
+    /// Subject to check; defaults to the authenticated user (`user:<id>`)
+    pub subject: Option<String>,
+}
+
+/// Objects the subject has the relation on
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ListObjectsResponse {
+    /// Matching objects, e.g. `doc:readme`
+    pub objects: Vec<String>,
+}
@@ -0,0 +1,188 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use std::collections::HashSet;
+use std::sync::Arc;
+use utoipa::ToSchema;
+use validator::Validate;
+
+/// A role definition; a role inherits every permission of its parent
+#[derive(Debug, Serialize, Deserialize, FromRow, ToSchema, Clone)]
+pub struct RoleDefinition {
+    /// Role name as stored on `users.role`
+    pub name: String,
+    /// Parent role whose permissions are inherited
+    pub parent: Option<String>,
+    /// Human readable description
+    pub description: Option<String>,
+}
+
+/// A role together with its own and inherited permissions
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RoleResponse {
+    #[serde(flatten)]
+    pub role: RoleDefinition,
+    /// Permissions granted directly to this role
+    pub permissions: Vec<String>,
+    /// Permissions granted to this role or any ancestor
+    pub effective_permissions: Vec<String>,
+}
+
+/// Request payload for creating or updating a role
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct RoleUpsertRequest {
+    /// Parent role to inherit from
+    #[validate(length(min = 1, max = 50))]
+    pub parent: Option<String>,
+    /// Human readable description
+    pub description: Option<String>,
+    /// Permissions granted directly to this role (replaces the existing set)
+    pub permissions: Vec<String>,
+}
+
+/// The resolved permission set of an authenticated user
+#[derive(Debug, Clone, Default)]
+pub struct EffectivePermissions(Arc<HashSet<String>>);
+
+impl EffectivePermissions {
+    pub fn new(permissions: HashSet<String>) -> Self {
+        Self(Arc::new(permissions))
+    }
+
+    pub fn contains(&self, permission: &str) -> bool {
+        self.0.contains(permission)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &String> {
+        self.0.iter()
+    }
+
+    /// Keeps only the permissions listed in a space-delimited OAuth `scope`
+    pub fn restrict_to_scope(&self, scope: &str) -> Self {
+        let scope: HashSet<&str> = scope.split_whitespace().collect();
+        Self::new(
+            self.0
+                .iter()
+                .filter(|permission| scope.contains(permission.as_str()))
+                .cloned()
+                .collect(),
+        )
+    }
+
+    /// The permissions as a sorted, space-delimited OAuth `scope`
+    pub fn to_scope(&self) -> String {
+        let mut permissions: Vec<&str> = self.0.iter().map(String::as_str).collect();
+        permissions.sort_unstable();
+        permissions.join(" ")
+    }
+}
+
+/// A permission a route requires, checked by `auth_middleware`
+pub trait Requirement {
+    const PERMISSION: &'static str;
+}
+
+/// Any authenticated user
+pub struct UserAccess;
+
+impl Requirement for UserAccess {
+    const PERMISSION: &'static str = "user:access";
+}
+
+/// Reading your own profile; held by every role, and the minimum scope of a narrowed token
+pub struct ProfileRead;
+
+impl Requirement for ProfileRead {
+    const PERMISSION: &'static str = "profile:read";
+}
+
+/// Administrators only
+pub struct AdminAccess;
+
+impl Requirement for AdminAccess {
+    const PERMISSION: &'static str = "admin:access";
+}
+
+/// Browsing user accounts
+pub struct ReadUsers;
+
+impl Requirement for ReadUsers {
+    const PERMISSION: &'static str = "users:read";
+}
+
+/// Disabling, deleting and changing the role of user accounts
+pub struct ManageUsers;
+
+impl Requirement for ManageUsers {
+    const PERMISSION: &'static str = "users:write";
+}
+
+/// Acting as another user through a short-lived impersonation token
+pub struct Impersonate;
+
+impl Requirement for Impersonate {
+    const PERMISSION: &'static str = "users:impersonate";
+}
+
+/// Querying and exporting the security audit log
+pub struct ReadAudit;
+
+impl Requirement for ReadAudit {
+    const PERMISSION: &'static str = "audit:read";
+}
+
+/// Managing role definitions
+pub struct ManageRoles;
+
+impl Requirement for ManageRoles {
+    const PERMISSION: &'static str = "roles:write";
+}
+
+/// Managing groups and group grants
+pub struct ManageGroups;
+
+impl Requirement for ManageGroups {
+    const PERMISSION: &'static str = "groups:write";
+}
+
+/// Writing relationship tuples
+pub struct ManageRelations;
+
+impl Requirement for ManageRelations {
+    const PERMISSION: &'static str = "relations:write";
+}
+
+/// Explaining policy decisions
+pub struct ExplainPolicies;
+
+impl Requirement for ExplainPolicies {
+    const PERMISSION: &'static str = "policies:read";
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn permissions(names: &[&str]) -> EffectivePermissions {
+        EffectivePermissions::new(names.iter().map(|name| name.to_string()).collect())
+    }
+
+    #[test]
+    fn scope_keeps_only_held_permissions() {
+        let held = permissions(&["profile:read", "profile:write", "users:read"]);
+
+        let narrowed = held.restrict_to_scope("profile:read  users:read admin:access");
+        assert_eq!(narrowed.to_scope(), "profile:read users:read");
+        assert!(!narrowed.contains("admin:access"));
+    }
+
+    #[test]
+    fn empty_scope_grants_nothing() {
+        let held = permissions(&["profile:read"]);
+        assert_eq!(held.restrict_to_scope("").to_scope(), "");
+    }
+
+    #[test]
+    fn to_scope_is_sorted() {
+        assert_eq!(permissions(&["users:read", "admin:access"]).to_scope(), "admin:access users:read");
+    }
+}
@@ -0,0 +1,271 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+/// Device, browser and operating system guessed from a `User-Agent` header
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DeviceInfo {
+    /// `desktop`, `mobile`, `tablet`, `bot` or `unknown`
+    pub device: String,
+    pub browser: Option<String>,
+    pub os: Option<String>,
+}
+
+impl DeviceInfo {
+    /// Best-effort parse of the common browser and client user agents
+    pub fn from_user_agent(user_agent: Option<&str>) -> Self {
+        let Some(ua) = user_agent.filter(|ua| !ua.is_empty()) else {
+            return DeviceInfo {
+                device: "unknown".to_string(),
+                ..Default::default()
+            };
+        };
+        let lower = ua.to_lowercase();
+
+        // Order matters: Edge and Opera also claim Chrome, Chrome also claims
+        // Safari, and the iOS browsers all claim Safari
+        let browser = [
+            ("edg/", "Edge"),
+            ("edga/", "Edge"),
+            ("edgios/", "Edge"),
+            ("opr/", "Opera"),
+            ("firefox/", "Firefox"),
+            ("fxios/", "Firefox"),
+            ("chrome/", "Chrome"),
+            ("crios/", "Chrome"),
+            ("version/", "Safari"),
+            ("curl/", "curl"),
+            ("postmanruntime/", "Postman"),
+            ("okhttp/", "okhttp"),
+        ]
+        .iter()
+        .find(|(marker, _)| lower.contains(marker))
+        .map(|(_, name)| name.to_string());
+
+        let os = [
+            ("windows nt", "Windows"),
+            ("iphone", "iOS"),
+            ("ipad", "iPadOS"),
+            ("android", "Android"),
+            ("mac os x", "macOS"),
+            ("cros ", "ChromeOS"),
+            ("linux", "Linux"),
+        ]
+        .iter()
+        .find(|(marker, _)| lower.contains(marker))
+        .map(|(_, name)| name.to_string());
+
+        let device = if ["bot", "crawler", "spider"].iter().any(|m| lower.contains(m)) {
+            "bot"
+        } else if lower.contains("ipad")
+            || lower.contains("tablet")
+            // Android tablets are the Android browsers not claiming to be mobile
+            || lower.contains("android") && !lower.contains("mobi")
+        {
+            "tablet"
+        } else if lower.contains("mobi") || lower.contains("iphone") || lower.contains("android") {
+            "mobile"
+        } else if os.is_some() {
+            "desktop"
+        } else {
+            "unknown"
+        };
+
+        DeviceInfo {
+            device: device.to_string(),
+            browser,
+            os,
+        }
+    }
+}
+
+/// A login attempt against the user's account
+#[derive(Debug, Serialize, FromRow, ToSchema)]
+pub struct LoginRecord {
+    pub id: i64,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    /// `desktop`, `mobile`, `tablet`, `bot` or `unknown`
+    pub device: Option<String>,
+    pub browser: Option<String>,
+    pub os: Option<String>,
+    /// How the user authenticated, e.g. `password`
+    pub auth_method: String,
+    pub success: bool,
+    /// Why the attempt failed, e.g. `invalid_password`
+    pub failure_reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A signed-in session
+#[derive(Debug, Serialize, FromRow, ToSchema)]
+pub struct Session {
+    pub id: Uuid,
+    #[serde(skip)]
+    #[allow(dead_code)]
+    pub user_id: i32,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub device: Option<String>,
+    pub browser: Option<String>,
+    pub os: Option<String>,
+    pub auth_method: String,
+    pub created_at: DateTime<Utc>,
+    /// Last request made with the session, to within a minute
+    pub last_seen_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    #[serde(skip)]
+    #[allow(dead_code)]
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+/// A session as listed to its owner
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SessionResponse {
+    #[serde(flatten)]
+    pub session: Session,
+    /// Whether this is the session making the request
+    pub current: bool,
+}
+
+/// Query parameters for the login history
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct LoginHistoryQuery {
+    /// Number of attempts to return, 1-100 (default 20)
+    pub limit: Option<i64>,
+}
+
+/// Number of sessions revoked
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RevokedSessions {
+    pub revoked: u64,
+}
+
+/// How a login compares to the user's earlier ones, taken before it is recorded
+#[derive(Debug, Clone, Default, FromRow)]
+pub struct LoginAssessment {
+    /// Successful logins recorded so far
+    pub previous_logins: i64,
+    /// Whether one of them came from the same IP, device, browser and OS
+    pub known_device: bool,
+    /// Failed attempts since the last successful login, within the alert window
+    pub recent_failures: i64,
+}
+
+/// Token from a login alert's "this wasn't me" link
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct DisownLoginRequest {
+    pub token: Uuid,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(user_agent: &str) -> (String, Option<String>, Option<String>) {
+        let info = DeviceInfo::from_user_agent(Some(user_agent));
+        (info.device, info.browser, info.os)
+    }
+
+    fn expected(device: &str, browser: Option<&str>, os: Option<&str>) -> (String, Option<String>, Option<String>) {
+        (device.to_string(), browser.map(str::to_string), os.map(str::to_string))
+    }
+
+    #[test]
+    fn desktop_browsers() {
+        let cases = [
+            (
+                "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36",
+                expected("desktop", Some("Chrome"), Some("Windows")),
+            ),
+            (
+                "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36 Edg/120.0.2210.91",
+                expected("desktop", Some("Edge"), Some("Windows")),
+            ),
+            (
+                "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36 OPR/106.0.0.0",
+                expected("desktop", Some("Opera"), Some("macOS")),
+            ),
+            (
+                "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.2 Safari/605.1.15",
+                expected("desktop", Some("Safari"), Some("macOS")),
+            ),
+            (
+                "Mozilla/5.0 (X11; Ubuntu; Linux x86_64; rv:121.0) Gecko/20100101 Firefox/121.0",
+                expected("desktop", Some("Firefox"), Some("Linux")),
+            ),
+            (
+                "Mozilla/5.0 (X11; CrOS x86_64 14541.0.0) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36",
+                expected("desktop", Some("Chrome"), Some("ChromeOS")),
+            ),
+        ];
+        for (user_agent, expected) in cases {
+            assert_eq!(parse(user_agent), expected, "{}", user_agent);
+        }
+    }
+
+    #[test]
+    fn mobile_browsers() {
+        let cases = [
+            (
+                "Mozilla/5.0 (iPhone; CPU iPhone OS 17_2 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.2 Mobile/15E148 Safari/604.1",
+                expected("mobile", Some("Safari"), Some("iOS")),
+            ),
+            (
+                "Mozilla/5.0 (iPhone; CPU iPhone OS 17_2 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) CriOS/120.0.6099.119 Mobile/15E148 Safari/604.1",
+                expected("mobile", Some("Chrome"), Some("iOS")),
+            ),
+            (
+                "Mozilla/5.0 (iPhone; CPU iPhone OS 17_2 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) FxiOS/121.0 Mobile/15E148 Safari/605.1.15",
+                expected("mobile", Some("Firefox"), Some("iOS")),
+            ),
+            (
+                "Mozilla/5.0 (iPhone; CPU iPhone OS 17_2 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.0 EdgiOS/120.2210.150 Mobile/15E148 Safari/605.1.15",
+                expected("mobile", Some("Edge"), Some("iOS")),
+            ),
+            (
+                "Mozilla/5.0 (Linux; Android 10; K) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Mobile Safari/537.36",
+                expected("mobile", Some("Chrome"), Some("Android")),
+            ),
+            (
+                "Mozilla/5.0 (Linux; Android 10; K) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Mobile Safari/537.36 EdgA/120.0.2210.115",
+                expected("mobile", Some("Edge"), Some("Android")),
+            ),
+            (
+                "Mozilla/5.0 (Android 14; Mobile; rv:121.0) Gecko/121.0 Firefox/121.0",
+                expected("mobile", Some("Firefox"), Some("Android")),
+            ),
+            (
+                "Mozilla/5.0 (iPad; CPU OS 17_2 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.2 Mobile/15E148 Safari/604.1",
+                expected("tablet", Some("Safari"), Some("iPadOS")),
+            ),
+            (
+                "Mozilla/5.0 (Linux; Android 13; SM-X700) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36",
+                expected("tablet", Some("Chrome"), Some("Android")),
+            ),
+        ];
+        for (user_agent, expected) in cases {
+            assert_eq!(parse(user_agent), expected, "{}", user_agent);
+        }
+    }
+
+    #[test]
+    fn clients_bots_and_unknown_agents() {
+        assert_eq!(parse("curl/8.4.0"), expected("unknown", Some("curl"), None));
+        assert_eq!(parse("PostmanRuntime/7.36.0"), expected("unknown", Some("Postman"), None));
+        assert_eq!(parse("okhttp/4.12.0"), expected("unknown", Some("okhttp"), None));
+        assert_eq!(
+            parse("Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)"),
+            expected("bot", None, None)
+        );
+        // Not ChromeOS, despite "Microsoft" containing "cros"
+        assert_eq!(
+            parse("Microsoft Office/16.0 (Microsoft Outlook 16.0.17126; Pro)"),
+            expected("unknown", None, None)
+        );
+        assert_eq!(DeviceInfo::from_user_agent(Some("")).device, "unknown");
+        assert_eq!(DeviceInfo::from_user_agent(None), DeviceInfo { device: "unknown".to_string(), ..Default::default() });
+    }
+}
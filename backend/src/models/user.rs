@@ -3,6 +3,7 @@ use utoipa::ToSchema;
 use validator::Validate;
 use sqlx::FromRow;
 use std::str::FromStr;
+use uuid::Uuid;
 
 /// Represents a user in the system
 #[derive(Debug, Serialize, Deserialize, FromRow, ToSchema, Clone)]
@@ -18,7 +19,7 @@ pub struct User {
     /// Hashed password (not returned in responses)
     #[schema(write_only)]
     pub password: String,
-    /// User's role ("Admin" or "User")
+    /// User's role ("Admin", "Moderator", "User" or a custom role)
     pub role: String,
     /// Account creation date
     pub created_at: Option<chrono::DateTime<chrono::Utc>>,
@@ -26,17 +27,26 @@ pub struct User {
     pub last_login: Option<chrono::DateTime<chrono::Utc>>,
     /// Total number of logins
     pub login_count: Option<i32>,
-    /// Profile picture URL
+    /// Profile picture URL. Uploaded photos are a variant set: append
+    /// `/{64|256|512}.{jpg|webp}` to pick one; the bare URL redirects to the 256px WebP
     pub profile_picture: Option<String>,
+    /// Whether the email address has been verified
+    pub email_verified: bool,
+    /// When an administrator disabled the account; disabled accounts cannot authenticate
+    pub disabled_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Why the account was disabled
+    pub disabled_reason: Option<String>,
+    /// Whether the user must change their password before doing anything else
+    pub must_change_password: bool,
+    /// Whether password login is refused until a reset link from email is used
+    pub password_reset_required: bool,
 }
 
 impl User {
+    /// Returns the built-in role, or an error for custom roles
     #[allow(dead_code)]
-    pub fn get_role(&self) -> Role {
-        match self.role.as_str() {
-            "Admin" => Role::Admin,
-            _ => Role::User,
-        }
+    pub fn get_role(&self) -> Result<Role, String> {
+        self.role.parse()
     }
 }
 
@@ -45,6 +55,8 @@ impl User {
 pub enum Role {
     /// Administrator with full access
     Admin,
+    /// Moderator, inherits every User permission
+    Moderator,
     /// Regular user with limited access
     User,
 }
@@ -55,18 +67,20 @@ impl FromStr for Role {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "Admin" => Ok(Role::Admin),
+            "Moderator" => Ok(Role::Moderator),
             "User" => Ok(Role::User),
             _ => Err(format!("Invalid role: {}", s)),
         }
     }
 }
 
-impl ToString for Role {
-    fn to_string(&self) -> String {
-        match self {
-            Role::Admin => "Admin".to_string(),
-            Role::User => "User".to_string(),
-        }
+impl std::fmt::Display for Role {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Role::Admin => "Admin",
+            Role::Moderator => "Moderator",
+            Role::User => "User",
+        })
     }
 }
 
@@ -89,6 +103,7 @@ pub struct RegisterRequest {
 
 /// Request payload for user login
 #[derive(Debug, Deserialize, Validate, ToSchema)]
+#[allow(dead_code)] // documents the login body in the OpenAPI spec
 pub struct LoginRequest {
     /// Email address
     #[validate(email)]
@@ -104,6 +119,30 @@ pub struct TokenResponse {
     pub token: String,
 }
 
+/// Request payload for issuing a narrowed token
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ScopedTokenRequest {
+    /// Permissions the new token is limited to, e.g. `["profile:read"]`
+    pub scopes: Vec<String>,
+}
+
+/// Request payload for completing a password reset
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct PasswordResetRequest {
+    /// Token from the reset link
+    pub token: Uuid,
+    /// New password (minimum 6 characters)
+    #[validate(length(min = 6))]
+    pub password: String,
+}
+
+/// Request payload for confirming an email address
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct EmailVerificationRequest {
+    /// Token from the verification link
+    pub token: Uuid,
+}
+
 /// Request payload for profile update
 #[derive(Debug, Deserialize, Validate, ToSchema, Default)]
 pub struct ProfileUpdateRequest {
@@ -0,0 +1,42 @@
+use axum::async_trait;
+use std::path::PathBuf;
+use tokio::{fs::OpenOptions, io::AsyncWriteExt, sync::Mutex};
+
+use super::{Notification, Notifier};
+
+/// Appends notifications to a JSON lines file, one per line
+pub struct FileNotifier {
+    path: PathBuf,
+    /// Serializes appends so concurrent lines do not interleave
+    lock: Mutex<()>,
+}
+
+impl FileNotifier {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        FileNotifier {
+            path: path.into(),
+            lock: Mutex::new(()),
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for FileNotifier {
+    async fn send(&self, notification: &Notification) -> Result<(), String> {
+        let mut line = serde_json::to_string(notification).map_err(|e| e.to_string())?;
+        line.push('\n');
+
+        let _guard = self.lock.lock().await;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await
+            .map_err(|e| format!("Failed to open {}: {}", self.path.display(), e))?;
+        file.write_all(line.as_bytes())
+            .await
+            .map_err(|e| format!("Failed to write {}: {}", self.path.display(), e))?;
+        tracing::info!(kind = ?notification.kind, user_id = notification.user_id, "Wrote notification");
+        Ok(())
+    }
+}
@@ -0,0 +1,160 @@
+use chrono::{Duration, Utc};
+use serde_json::json;
+use sqlx::{Pool, Postgres};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use super::{Notification, NotificationKind, Notifier};
+use crate::{
+    config::config::app_config,
+    db::sessions::create_login_alert,
+    mail::templates::{render, DEFAULT_LOCALE},
+    middleware::client::ClientInfo,
+    models::{
+        mail::MailTemplate,
+        session::{DeviceInfo, LoginAssessment},
+        user::User,
+    },
+};
+
+/// Failed attempts before a successful login that make it suspicious
+const FAILED_LOGIN_THRESHOLD: i64 = 5;
+/// How far back failed attempts count, in minutes
+pub const FAILED_LOGIN_WINDOW_MINUTES: i32 = 15;
+/// How long the "this wasn't me" link stays valid
+const ALERT_LINK_TTL_DAYS: i64 = 7;
+
+/// Why a login deserves an alert; empty for a usual login
+fn alert_reasons(assessment: &LoginAssessment) -> Vec<&'static str> {
+    let mut reasons = Vec::new();
+    // The very first login has nothing to compare against
+    if assessment.previous_logins > 0 && !assessment.known_device {
+        reasons.push("new_device");
+    }
+    if assessment.recent_failures >= FAILED_LOGIN_THRESHOLD {
+        reasons.push("failed_attempts");
+    }
+    reasons
+}
+
+/// Notifies the user of an unusual login, with a link to disown it.
+/// Failures are logged, never surfaced to the login itself.
+pub async fn alert_unusual_login(
+    pool: &Pool<Postgres>,
+    notifier: Arc<dyn Notifier>,
+    user: &User,
+    client: &ClientInfo,
+    assessment: &LoginAssessment,
+    session_id: Uuid,
+) {
+    let reasons = alert_reasons(assessment);
+    if reasons.is_empty() {
+        return;
+    }
+
+    let ip = client.ip.map(|ip| ip.to_string());
+    let token = Uuid::new_v4();
+    if let Err(e) = create_login_alert(
+        pool,
+        user.id,
+        token,
+        session_id,
+        &reasons,
+        ip.as_deref(),
+        client.user_agent.as_deref(),
+        Utc::now() + Duration::days(ALERT_LINK_TTL_DAYS),
+    )
+    .await
+    {
+        tracing::error!(error = %e, "Failed to record login alert");
+        return;
+    }
+
+    let device = DeviceInfo::from_user_agent(client.user_agent.as_deref());
+    let suspicious = assessment.recent_failures >= FAILED_LOGIN_THRESHOLD;
+    let action_url = format!("{}/not-me?token={}", app_config().server.frontend_url, token);
+    let rendered = match render(
+        MailTemplate::LoginAlert,
+        DEFAULT_LOCALE,
+        &json!({
+            "name": user.firstname,
+            "suspicious": suspicious,
+            "device": device.device,
+            "browser": device.browser,
+            "os": device.os,
+            "ip": ip,
+            "failed_attempts": if suspicious { assessment.recent_failures } else { 0 },
+            "action_url": action_url,
+        }),
+    ) {
+        Ok(rendered) => rendered,
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to render login alert");
+            return;
+        }
+    };
+
+    let notification = Notification {
+        id: Uuid::new_v4(),
+        kind: if suspicious {
+            NotificationKind::SuspiciousLogin
+        } else {
+            NotificationKind::NewDeviceLogin
+        },
+        user_id: user.id,
+        to: user.email.clone(),
+        subject: rendered.subject,
+        body: rendered.text,
+        html: Some(rendered.html),
+        action_url: Some(action_url),
+        created_at: Utc::now(),
+    };
+    if let Err(e) = notifier.send(&notification).await {
+        tracing::error!(error = %e, "Failed to send login alert");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assessment(previous_logins: i64, known_device: bool, recent_failures: i64) -> LoginAssessment {
+        LoginAssessment {
+            previous_logins,
+            known_device,
+            recent_failures,
+        }
+    }
+
+    #[test]
+    fn first_login_raises_nothing() {
+        assert!(alert_reasons(&assessment(0, false, 0)).is_empty());
+    }
+
+    #[test]
+    fn known_device_raises_nothing() {
+        assert!(alert_reasons(&assessment(3, true, 0)).is_empty());
+    }
+
+    #[test]
+    fn unknown_device_after_earlier_logins_is_new() {
+        assert_eq!(alert_reasons(&assessment(3, false, 0)), ["new_device"]);
+    }
+
+    #[test]
+    fn failures_count_from_the_threshold() {
+        let below = assessment(3, true, FAILED_LOGIN_THRESHOLD - 1);
+        assert!(alert_reasons(&below).is_empty());
+        let at = assessment(3, true, FAILED_LOGIN_THRESHOLD);
+        assert_eq!(alert_reasons(&at), ["failed_attempts"]);
+        // Failures before the very first login still count
+        let first = assessment(0, false, FAILED_LOGIN_THRESHOLD);
+        assert_eq!(alert_reasons(&first), ["failed_attempts"]);
+    }
+
+    #[test]
+    fn reasons_combine() {
+        let both = assessment(3, false, FAILED_LOGIN_THRESHOLD);
+        assert_eq!(alert_reasons(&both), ["new_device", "failed_attempts"]);
+    }
+}
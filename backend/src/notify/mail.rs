@@ -0,0 +1,30 @@
+use axum::async_trait;
+use std::sync::Arc;
+
+use super::{Notification, Notifier};
+use crate::mail::{Email, Mailer};
+
+/// Delivers notifications by email
+pub struct MailNotifier {
+    mailer: Arc<dyn Mailer>,
+}
+
+impl MailNotifier {
+    pub fn new(mailer: Arc<dyn Mailer>) -> Self {
+        MailNotifier { mailer }
+    }
+}
+
+#[async_trait]
+impl Notifier for MailNotifier {
+    async fn send(&self, notification: &Notification) -> Result<(), String> {
+        self.mailer
+            .send(&Email {
+                to: notification.to.clone(),
+                subject: notification.subject.clone(),
+                text: notification.body.clone(),
+                html: notification.html.clone(),
+            })
+            .await
+    }
+}
@@ -0,0 +1,93 @@
+use axum::async_trait;
+use std::sync::{Arc, Mutex};
+
+use super::{Notification, Notifier};
+
+/// Keeps notifications in memory instead of delivering them, for tests and local runs
+#[derive(Clone, Default)]
+pub struct InMemoryNotifier {
+    sent: Arc<Mutex<Vec<Notification>>>,
+}
+
+impl InMemoryNotifier {
+    /// Notifications sent so far, oldest first
+    #[allow(dead_code)]
+    pub fn sent(&self) -> Vec<Notification> {
+        self.sent.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl Notifier for InMemoryNotifier {
+    async fn send(&self, notification: &Notification) -> Result<(), String> {
+        tracing::debug!(kind = ?notification.kind, user_id = notification.user_id, "Queued notification");
+        self.sent.lock().unwrap().push(notification.clone());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Utc};
+    use sqlx::{Pool, Postgres};
+
+    use crate::{
+        config::config::init_for_tests,
+        db::sessions::{assess_login, create_session, record_login},
+        middleware::client::ClientInfo,
+        models::user::User,
+        notify::{
+            login::{alert_unusual_login, FAILED_LOGIN_WINDOW_MINUTES},
+            NotificationKind,
+        },
+        test_support::insert_user,
+    };
+
+    const LAPTOP: &str = "Mozilla/5.0 (X11; Linux x86_64; rv:128.0) Gecko/20100101 Firefox/128.0";
+    const PHONE: &str = "Mozilla/5.0 (iPhone; CPU iPhone OS 17_5 like Mac OS X) \
+                         AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.5 Mobile/15E148 Safari/604.1";
+
+    /// Assesses, records and alerts on a successful login the way the login route does
+    async fn log_in(pool: &Pool<Postgres>, notifier: &InMemoryNotifier, user: &User, ip: &str, user_agent: &str) {
+        let client = ClientInfo {
+            ip: Some(ip.parse().unwrap()),
+            user_agent: Some(user_agent.to_string()),
+            request_id: None,
+        };
+        let assessment = assess_login(pool, user.id, Some(ip), Some(user_agent), FAILED_LOGIN_WINDOW_MINUTES)
+            .await
+            .unwrap();
+        record_login(pool, user.id, Some(ip), Some(user_agent), "password", None)
+            .await
+            .unwrap();
+        let expires_at = Utc::now() + Duration::hours(1);
+        let session_id = create_session(pool, user.id, Some(ip), Some(user_agent), "password", expires_at)
+            .await
+            .unwrap();
+        alert_unusual_login(pool, Arc::new(notifier.clone()), user, &client, &assessment, session_id).await;
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn only_new_devices_are_notified(pool: Pool<Postgres>) {
+        init_for_tests();
+        let user = insert_user(&pool, "user@example.com", "User").await;
+        let notifier = InMemoryNotifier::default();
+
+        // The first login has nothing to compare against
+        log_in(&pool, &notifier, &user, "203.0.113.7", LAPTOP).await;
+        assert!(notifier.sent().is_empty());
+
+        log_in(&pool, &notifier, &user, "198.51.100.20", PHONE).await;
+        let sent = notifier.sent();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].kind, NotificationKind::NewDeviceLogin);
+        assert_eq!(sent[0].user_id, user.id);
+        assert!(sent[0].action_url.as_deref().unwrap().contains("/not-me?token="));
+
+        log_in(&pool, &notifier, &user, "203.0.113.7", LAPTOP).await;
+        log_in(&pool, &notifier, &user, "198.51.100.20", PHONE).await;
+        assert_eq!(notifier.sent().len(), 1);
+    }
+}
@@ -0,0 +1,59 @@
+pub mod file;
+pub mod login;
+pub mod mail;
+pub mod memory;
+
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::{
+    config::config::{app_config, NotifierKind},
+    mail::Mailer,
+};
+
+/// Kinds of notifications sent to users
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    /// Login from a device or network the user has not signed in from before
+    NewDeviceLogin,
+    /// Successful login following a burst of failed attempts
+    SuspiciousLogin,
+}
+
+/// A message addressed to a user
+#[derive(Debug, Clone, Serialize)]
+pub struct Notification {
+    pub id: Uuid,
+    pub kind: NotificationKind,
+    pub user_id: i32,
+    /// Address to deliver to
+    pub to: String,
+    pub subject: String,
+    /// Plain text body
+    pub body: String,
+    /// HTML alternative to the body, for channels that can show it
+    pub html: Option<String>,
+    /// Link the user can follow to act on the notification
+    pub action_url: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Delivers notifications to users
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    async fn send(&self, notification: &Notification) -> Result<(), String>;
+}
+
+/// Builds the notifier selected by `notifications.notifier`
+pub fn from_config(mailer: Arc<dyn Mailer>) -> Arc<dyn Notifier> {
+    let config = &app_config().notifications;
+    match config.notifier {
+        NotifierKind::File => Arc::new(file::FileNotifier::new(config.path.clone())),
+        NotifierKind::Mail => Arc::new(mail::MailNotifier::new(mailer)),
+        NotifierKind::Memory => Arc::new(memory::InMemoryNotifier::default()),
+    }
+}
@@ -0,0 +1,324 @@
+use chrono::{Datelike, Timelike, Utc};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::net::IpAddr;
+use utoipa::ToSchema;
+
+use crate::{
+    models::{role::EffectivePermissions, user::User},
+    policy::schema::{Condition, Effect, Operator, PolicyMode, PolicySet},
+};
+
+/// The request facts policies are evaluated against
+pub struct PolicyRequest<'a> {
+    pub user: &'a User,
+    pub permissions: &'a EffectivePermissions,
+    pub method: &'a str,
+    pub path: &'a str,
+    pub ip: Option<IpAddr>,
+    pub organization: Option<i32>,
+}
+
+/// Outcome of a single applicable policy
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PolicyEvaluation {
+    /// Policy id
+    pub policy: String,
+    /// Policy description
+    pub description: Option<String>,
+    /// Effect of the policy when its condition matches
+    pub effect: Effect,
+    /// Whether the policy's condition matched the request
+    pub matched: bool,
+}
+
+/// Result of evaluating the policy set for a request
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PolicyDecision {
+    /// Whether the request is allowed
+    pub allowed: bool,
+    /// Whether the decision is enforced or only logged
+    pub mode: PolicyMode,
+    /// Why the request was allowed or denied
+    pub reason: String,
+    /// Every applicable policy and whether it matched
+    pub evaluations: Vec<PolicyEvaluation>,
+    /// Attributes the policies were evaluated against
+    #[schema(value_type = Object)]
+    pub attributes: Value,
+}
+
+/// Builds the `user`, `resource` and `context` attribute document for a request
+pub fn attributes(request: &PolicyRequest) -> Value {
+    let now = Utc::now();
+    let user = request.user;
+    let mut permissions: Vec<&String> = request.permissions.iter().collect();
+    permissions.sort();
+
+    json!({
+        "user": {
+            "id": user.id,
+            "email": user.email,
+            "email_domain": user.email.rsplit_once('@').map(|(_, domain)| domain.to_lowercase()),
+            "role": user.role,
+            "email_verified": user.email_verified,
+            "permissions": permissions,
+        },
+        "resource": {
+            "method": request.method,
+            "path": request.path,
+            "segments": request.path.split('/').filter(|s| !s.is_empty()).collect::<Vec<_>>(),
+        },
+        "context": {
+            "time": now.to_rfc3339(),
+            "hour": now.hour(),
+            "weekday": now.weekday().to_string(),
+            "ip": request.ip.map(|ip| ip.to_string()),
+            "organization": request.organization,
+        },
+    })
+}
+
+/// Evaluates every policy applicable to `method path` against `attributes`
+pub fn evaluate(policies: &PolicySet, method: &str, path: &str, attributes: Value) -> PolicyDecision {
+    let evaluations: Vec<PolicyEvaluation> = policies
+        .policies
+        .iter()
+        .filter(|policy| policy.applies_to(method, path))
+        .map(|policy| PolicyEvaluation {
+            policy: policy.id.clone(),
+            description: policy.description.clone(),
+            effect: policy.effect,
+            matched: policy
+                .condition
+                .as_ref()
+                .is_none_or(|condition| eval_condition(condition, &attributes)),
+        })
+        .collect();
+
+    let denied_by = evaluations
+        .iter()
+        .find(|e| e.effect == Effect::Deny && e.matched);
+    let allows: Vec<&PolicyEvaluation> = evaluations
+        .iter()
+        .filter(|e| e.effect == Effect::Allow)
+        .collect();
+
+    let (allowed, reason) = if let Some(deny) = denied_by {
+        (false, format!("Denied by policy {}", deny.policy))
+    } else if allows.is_empty() {
+        (true, "No allow policy applies".to_string())
+    } else if let Some(allow) = allows.iter().find(|e| e.matched) {
+        (true, format!("Allowed by policy {}", allow.policy))
+    } else {
+        (false, "No applicable allow policy matched".to_string())
+    };
+
+    PolicyDecision {
+        allowed,
+        mode: policies.mode,
+        reason,
+        evaluations,
+        attributes,
+    }
+}
+
+fn eval_condition(condition: &Condition, attributes: &Value) -> bool {
+    match condition {
+        Condition::All(conditions) => conditions.iter().all(|c| eval_condition(c, attributes)),
+        Condition::Any(conditions) => conditions.iter().any(|c| eval_condition(c, attributes)),
+        Condition::Not(condition) => !eval_condition(condition, attributes),
+        Condition::Match { attribute, op, value } => {
+            let actual = attributes
+                .pointer(&format!("/{}", attribute.replace('.', "/")))
+                .filter(|v| !v.is_null());
+            compare(*op, actual, value)
+        }
+    }
+}
+
+/// Missing attributes never match, except that `exists` reports them as absent
+fn compare(op: Operator, actual: Option<&Value>, expected: &Value) -> bool {
+    let Some(actual) = actual else {
+        return matches!(op, Operator::Exists) && !expected.as_bool().unwrap_or(true);
+    };
+
+    match op {
+        Operator::Exists => expected.as_bool().unwrap_or(true),
+        Operator::Eq => actual == expected,
+        Operator::Ne => actual != expected,
+        Operator::In => expected.as_array().is_some_and(|values| values.contains(actual)),
+        Operator::NotIn => expected.as_array().is_none_or(|values| !values.contains(actual)),
+        Operator::StartsWith => match (actual.as_str(), expected.as_str()) {
+            (Some(actual), Some(expected)) => actual.starts_with(expected),
+            _ => false,
+        },
+        Operator::EndsWith => match (actual.as_str(), expected.as_str()) {
+            (Some(actual), Some(expected)) => actual.ends_with(expected),
+            _ => false,
+        },
+        Operator::Contains => match actual {
+            Value::Array(values) => values.contains(expected),
+            Value::String(actual) => expected.as_str().is_some_and(|e| actual.contains(e)),
+            _ => false,
+        },
+        Operator::Gt | Operator::Gte | Operator::Lt | Operator::Lte => {
+            match (actual.as_f64(), expected.as_f64()) {
+                (Some(actual), Some(expected)) => match op {
+                    Operator::Gt => actual > expected,
+                    Operator::Gte => actual >= expected,
+                    Operator::Lt => actual < expected,
+                    _ => actual <= expected,
+                },
+                _ => false,
+            }
+        }
+        Operator::InCidr => {
+            let Some(ip) = actual.as_str().and_then(|ip| ip.parse::<IpAddr>().ok()) else {
+                return false;
+            };
+            let ranges: Vec<&Value> = match expected.as_array() {
+                Some(ranges) => ranges.iter().collect(),
+                None => vec![expected],
+            };
+            ip_in_ranges(ip, ranges.iter().filter_map(|range| range.as_str()))
+        }
+    }
+}
+
+/// Parses `10.0.0.0/8` style ranges; a bare address is a single-host range
+pub fn parse_cidr(range: &str) -> Option<(IpAddr, u8)> {
+    let (address, prefix) = match range.split_once('/') {
+        Some((address, prefix)) => (address, Some(prefix.parse::<u8>().ok()?)),
+        None => (range, None),
+    };
+    let address: IpAddr = address.parse().ok()?;
+    let max = if address.is_ipv4() { 32 } else { 128 };
+    let prefix = prefix.unwrap_or(max);
+    (prefix <= max).then_some((address, prefix))
+}
+
+/// Whether `ip` falls in any of `ranges` (see `parse_cidr`); invalid ranges never match
+pub fn ip_in_ranges<'a>(ip: IpAddr, ranges: impl IntoIterator<Item = &'a str>) -> bool {
+    ranges
+        .into_iter()
+        .filter_map(parse_cidr)
+        .any(|(network, prefix)| ip_in_network(ip, network, prefix))
+}
+
+fn ip_in_network(ip: IpAddr, network: IpAddr, prefix: u8) -> bool {
+    match (ip.to_canonical(), network) {
+        (IpAddr::V4(ip), IpAddr::V4(network)) => {
+            let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+            u32::from(ip) & mask == u32::from(network) & mask
+        }
+        (IpAddr::V6(ip), IpAddr::V6(network)) => {
+            let mask = u128::MAX.checked_shl(128 - prefix as u32).unwrap_or(0);
+            u128::from(ip) & mask == u128::from(network) & mask
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(condition: Value, attributes: Value) -> bool {
+        let condition: Condition = serde_json::from_value(condition).unwrap();
+        eval_condition(&condition, &attributes)
+    }
+
+    #[test]
+    fn exists_reports_present_and_missing_attributes() {
+        let attributes = json!({ "context": { "organization": 7, "ip": null } });
+        let exists = |attribute: &str, value: bool| {
+            matches(
+                json!({ "match": { "attribute": attribute, "op": "exists", "value": value } }),
+                attributes.clone(),
+            )
+        };
+
+        assert!(exists("context.organization", true));
+        assert!(!exists("context.organization", false));
+        // Null attributes count as missing
+        assert!(!exists("context.ip", true));
+        assert!(exists("context.ip", false));
+        assert!(exists("context.missing", false));
+    }
+
+    #[test]
+    fn missing_attributes_match_no_other_operator() {
+        let attributes = json!({});
+        assert!(!matches(json!({ "match": { "attribute": "user.role", "op": "ne", "value": "Admin" } }), attributes.clone()));
+        assert!(!matches(json!({ "match": { "attribute": "user.role", "op": "not_in", "value": ["Admin"] } }), attributes));
+    }
+
+    #[test]
+    fn in_cidr_matches_ranges_and_single_hosts() {
+        let attributes = json!({ "context": { "ip": "10.1.2.3" } });
+        let in_cidr = |value: Value| {
+            matches(json!({ "match": { "attribute": "context.ip", "op": "in_cidr", "value": value } }), attributes.clone())
+        };
+
+        assert!(in_cidr(json!(["192.168.0.0/16", "10.0.0.0/8"])));
+        assert!(in_cidr(json!("10.1.2.3")));
+        assert!(!in_cidr(json!("10.1.2.4")));
+        assert!(!in_cidr(json!(["not-a-range"])));
+    }
+
+    #[test]
+    fn ipv4_mapped_addresses_match_ipv4_ranges() {
+        let ip: IpAddr = "::ffff:10.1.2.3".parse().unwrap();
+        assert!(ip_in_ranges(ip, ["10.0.0.0/8"]));
+        assert!(!ip_in_ranges(ip, ["192.168.0.0/16"]));
+    }
+
+    #[test]
+    fn parse_cidr_rejects_oversized_prefixes() {
+        assert_eq!(parse_cidr("10.0.0.0/8"), Some(("10.0.0.0".parse().unwrap(), 8)));
+        assert_eq!(parse_cidr("::1"), Some(("::1".parse().unwrap(), 128)));
+        assert_eq!(parse_cidr("10.0.0.0/33"), None);
+        assert_eq!(parse_cidr("office"), None);
+    }
+
+    #[test]
+    fn matching_deny_wins_over_allow() {
+        let policies: PolicySet = serde_json::from_value(json!({
+            "policies": [
+                { "id": "allow-all", "effect": "allow", "resources": ["/api/*"] },
+                {
+                    "id": "deny-guests",
+                    "effect": "deny",
+                    "resources": ["/api/admin"],
+                    "condition": { "match": { "attribute": "user.role", "op": "eq", "value": "Guest" } }
+                }
+            ]
+        }))
+        .unwrap();
+
+        let decision = evaluate(&policies, "GET", "/api/admin", json!({ "user": { "role": "Guest" } }));
+        assert!(!decision.allowed);
+        assert_eq!(decision.reason, "Denied by policy deny-guests");
+
+        let decision = evaluate(&policies, "GET", "/api/admin", json!({ "user": { "role": "Admin" } }));
+        assert!(decision.allowed);
+        assert_eq!(decision.reason, "Allowed by policy allow-all");
+    }
+
+    #[test]
+    fn example_policies_gate_photo_uploads_on_verified_email() {
+        let policies = PolicySet::parse(include_str!("../../policies.example.json")).unwrap();
+        let attributes = |verified: bool| {
+            json!({
+                "user": { "role": "User", "email_verified": verified },
+                "context": { "hour": 12, "ip": "203.0.113.9" },
+            })
+        };
+
+        assert!(!evaluate(&policies, "POST", "/api/profile/photo", attributes(false)).allowed);
+        assert!(!evaluate(&policies, "POST", "/api/profile/photo/uploads", attributes(false)).allowed);
+        assert!(evaluate(&policies, "POST", "/api/profile/photo", attributes(true)).allowed);
+        assert!(evaluate(&policies, "PUT", "/api/profile/update", attributes(false)).allowed);
+    }
+}
@@ -0,0 +1,177 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use utoipa::ToSchema;
+
+use crate::{
+    config::config::app_config,
+    policy::engine::parse_cidr,
+};
+
+/// Built-in policy set used when `POLICY_PATH` is not set
+const DEFAULT_POLICIES: &str = include_str!("../../policies.json");
+
+/// Attribute-based route policies.
+///
+/// A policy applies to requests whose path matches one of its `resources`
+/// (exact, or a prefix when ending in `*`) and, if given, one of its `methods`.
+/// Among the applicable policies a matching `deny` always wins; otherwise, if
+/// any `allow` policy applies, at least one of them must match.
+#[derive(Debug, Deserialize)]
+pub struct PolicySet {
+    #[serde(default)]
+    pub mode: PolicyMode,
+    #[serde(default)]
+    pub policies: Vec<Policy>,
+}
+
+/// `dry_run` evaluates and logs every decision without blocking requests
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyMode {
+    #[default]
+    Enforce,
+    DryRun,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Policy {
+    pub id: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub effect: Effect,
+    pub resources: Vec<String>,
+    #[serde(default)]
+    pub methods: Vec<String>,
+    /// Matches every applicable request when absent
+    #[serde(default)]
+    pub condition: Option<Condition>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Effect {
+    Allow,
+    Deny,
+}
+
+/// A boolean expression over request attributes (`user.*`, `resource.*`, `context.*`)
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Condition {
+    All(Vec<Condition>),
+    Any(Vec<Condition>),
+    Not(Box<Condition>),
+    Match {
+        attribute: String,
+        op: Operator,
+        #[serde(default)]
+        value: serde_json::Value,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Operator {
+    Eq,
+    Ne,
+    In,
+    NotIn,
+    StartsWith,
+    EndsWith,
+    Contains,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+    /// IP attribute inside one of the CIDR ranges in `value`
+    InCidr,
+    Exists,
+}
+
+impl Policy {
+    pub fn applies_to(&self, method: &str, path: &str) -> bool {
+        let method_matches = self.methods.is_empty()
+            || self.methods.iter().any(|m| m.eq_ignore_ascii_case(method));
+        let resource_matches = self.resources.iter().any(|resource| match resource.strip_suffix('*') {
+            Some(prefix) => path.starts_with(prefix),
+            None => path == resource,
+        });
+        method_matches && resource_matches
+    }
+}
+
+impl PolicySet {
+    /// Loads policies from `policies.path` (or the built-in set); `policies.mode` overrides the file's mode
+    pub fn load() -> Result<Self, String> {
+        let config = &app_config().policies;
+        let source = match &config.path {
+            Some(path) => std::fs::read_to_string(path)
+                .map_err(|e| format!("Failed to read policies {}: {}", path, e))?,
+            None => DEFAULT_POLICIES.to_string(),
+        };
+
+        let mut policies = Self::parse(&source)?;
+        if let Some(mode) = config.mode {
+            policies.mode = mode;
+        }
+        Ok(policies)
+    }
+
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let policies: PolicySet =
+            serde_json::from_str(source).map_err(|e| format!("Invalid policies: {}", e))?;
+        policies.validate()?;
+        Ok(policies)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let mut errors = Vec::new();
+        let mut ids = HashSet::new();
+
+        for policy in &self.policies {
+            if !ids.insert(policy.id.as_str()) {
+                errors.push(format!("duplicate policy id {}", policy.id));
+            }
+            if policy.resources.is_empty() {
+                errors.push(format!("policy {} has no resources", policy.id));
+            }
+            if let Some(condition) = &policy.condition {
+                validate_condition(&policy.id, condition, &mut errors);
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("Invalid policies: {}", errors.join("; ")))
+        }
+    }
+}
+
+fn validate_condition(policy: &str, condition: &Condition, errors: &mut Vec<String>) {
+    match condition {
+        Condition::All(conditions) | Condition::Any(conditions) => {
+            for condition in conditions {
+                validate_condition(policy, condition, errors);
+            }
+        }
+        Condition::Not(condition) => validate_condition(policy, condition, errors),
+        Condition::Match { attribute, op, value } => {
+            let root = attribute.split('.').next().unwrap_or_default();
+            if !["user", "resource", "context"].contains(&root) {
+                errors.push(format!("policy {} uses unknown attribute {}", policy, attribute));
+            }
+            if let Operator::InCidr = op {
+                let ranges: Vec<&serde_json::Value> = match value.as_array() {
+                    Some(ranges) => ranges.iter().collect(),
+                    None => vec![value],
+                };
+                for range in ranges {
+                    if range.as_str().and_then(parse_cidr).is_none() {
+                        errors.push(format!("policy {} has invalid CIDR {}", policy, range));
+                    }
+                }
+            }
+        }
+    }
+}
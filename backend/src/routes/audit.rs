@@ -0,0 +1,284 @@
+use axum::{
+    extract::{Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use sqlx::{Pool, Postgres};
+
+use crate::{
+    db::audit::{export_events, list_events, verify_chain},
+    models::audit::{
+        AuditEvent, AuditFilter, AuditPage, AuditPageQuery, ChainVerification, ExportFormat,
+        ExportQuery,
+    },
+};
+
+/// Default and maximum page sizes of the audit log
+const DEFAULT_PAGE_SIZE: i64 = 100;
+const MAX_PAGE_SIZE: i64 = 500;
+
+const CSV_HEADER: &str =
+    "id,event_type,actor_id,target_user_id,ip,user_agent,request_id,details,created_at,prev_hash,hash";
+
+/// Quotes a CSV field when it contains a delimiter, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn csv_row(event: &AuditEvent) -> String {
+    let optional = |value: Option<String>| value.unwrap_or_default();
+    [
+        event.id.to_string(),
+        event.event_type.clone(),
+        optional(event.actor_id.map(|id| id.to_string())),
+        optional(event.target_user_id.map(|id| id.to_string())),
+        optional(event.ip.clone()),
+        optional(event.user_agent.clone()),
+        optional(event.request_id.clone()),
+        event.details.to_string(),
+        event.created_at.to_rfc3339(),
+        event.prev_hash.clone(),
+        event.hash.clone(),
+    ]
+    .iter()
+    .map(|field| csv_field(field))
+    .collect::<Vec<_>>()
+    .join(",")
+}
+
+/// Query the audit log
+///
+/// Returns security events matching the filters, newest first.
+#[utoipa::path(
+    get,
+    path = "/api/admin/audit",
+    params(AuditFilter, AuditPageQuery),
+    responses(
+        (status = 200, description = "Events retrieved successfully", body = AuditPage),
+        (status = 400, description = "Invalid page size"),
+        (status = 401, description = "No token provided or invalid token"),
+        (status = 403, description = "Requires the audit:read permission")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Admin"
+)]
+pub async fn get_audit_events(
+    State(pool): State<Pool<Postgres>>,
+    Query(filter): Query<AuditFilter>,
+    Query(page): Query<AuditPageQuery>,
+) -> Result<Json<AuditPage>, (StatusCode, Json<String>)> {
+    let limit = page.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    if !(1..=MAX_PAGE_SIZE).contains(&limit) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(format!("limit must be between 1 and {}", MAX_PAGE_SIZE)),
+        ));
+    }
+
+    let mut events = list_events(&pool, &filter, page.before_id, limit + 1)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json("Failed to fetch audit events".to_string()),
+            )
+        })?;
+
+    let next_before_id = if events.len() as i64 > limit {
+        events.truncate(limit as usize);
+        events.last().map(|event| event.id)
+    } else {
+        None
+    };
+
+    Ok(Json(AuditPage {
+        events,
+        next_before_id,
+    }))
+}
+
+/// Export the audit log
+///
+/// Downloads events matching the filters in chain order, as JSON lines or CSV.
+#[utoipa::path(
+    get,
+    path = "/api/admin/audit/export",
+    params(AuditFilter, ExportQuery),
+    responses(
+        (status = 200, description = "Export of the matching events"),
+        (status = 401, description = "No token provided or invalid token"),
+        (status = 403, description = "Requires the audit:read permission")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Admin"
+)]
+pub async fn export_audit_events(
+    State(pool): State<Pool<Postgres>>,
+    Query(filter): Query<AuditFilter>,
+    Query(export): Query<ExportQuery>,
+) -> Result<Response, (StatusCode, Json<String>)> {
+    let events = export_events(&pool, &filter).await.map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json("Failed to export audit events".to_string()),
+        )
+    })?;
+
+    let (content_type, extension, body) = match export.format {
+        ExportFormat::Jsonl => {
+            let lines = events
+                .iter()
+                .filter_map(|event| serde_json::to_string(event).ok())
+                .map(|line| line + "\n")
+                .collect::<String>();
+            ("application/x-ndjson", "jsonl", lines)
+        }
+        ExportFormat::Csv => {
+            let mut lines = vec![CSV_HEADER.to_string()];
+            lines.extend(events.iter().map(csv_row));
+            ("text/csv", "csv", lines.join("\n") + "\n")
+        }
+    };
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"audit-events.{}\"", extension),
+            ),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+/// Verify the audit log
+///
+/// Re-computes the hash chain and reports the first event that was altered,
+/// inserted or removed out of order.
+#[utoipa::path(
+    get,
+    path = "/api/admin/audit/verify",
+    responses(
+        (status = 200, description = "Verification result", body = ChainVerification),
+        (status = 401, description = "No token provided or invalid token"),
+        (status = 403, description = "Requires the audit:read permission")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Admin"
+)]
+pub async fn verify_audit_chain(
+    State(pool): State<Pool<Postgres>>,
+) -> Result<Json<ChainVerification>, (StatusCode, Json<String>)> {
+    verify_chain(&pool).await.map(Json).map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json("Failed to verify audit events".to_string()),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::audit::record_event,
+        models::audit::{AuditContext, AuditEventType},
+    };
+    use serde_json::json;
+
+    async fn export(pool: &Pool<Postgres>, format: ExportFormat) -> (String, String) {
+        let response = export_audit_events(
+            State(pool.clone()),
+            Query(AuditFilter::default()),
+            Query(ExportQuery { format }),
+        )
+        .await
+        .unwrap();
+        let content_type = response.headers()[header::CONTENT_TYPE].to_str().unwrap().to_string();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (content_type, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    async fn append_events(pool: &Pool<Postgres>) {
+        let context = AuditContext {
+            actor_id: Some(7),
+            ip: Some("203.0.113.9".to_string()),
+            ..AuditContext::default()
+        };
+        record_event(pool, &context, AuditEventType::LoginSucceeded, Some(7), json!({})).await;
+        record_event(
+            pool,
+            &AuditContext::default(),
+            AuditEventType::LoginFailed,
+            None,
+            json!({ "email": "x@example.com", "reason": "bad \"password\"" }),
+        )
+        .await;
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn exports_events_as_json_lines(pool: Pool<Postgres>) {
+        append_events(&pool).await;
+        let events = export_events(&pool, &AuditFilter::default()).await.unwrap();
+
+        let (content_type, body) = export(&pool, ExportFormat::Jsonl).await;
+        assert_eq!(content_type, "application/x-ndjson");
+        let lines: Vec<serde_json::Value> = body
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["event_type"], "login_succeeded");
+        assert_eq!(lines[0]["actor_id"], 7);
+        assert_eq!(lines[1]["details"]["reason"], "bad \"password\"");
+        assert_eq!(lines[1]["prev_hash"], events[0].hash);
+        assert_eq!(lines[1]["hash"], events[1].hash);
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn exports_events_as_csv(pool: Pool<Postgres>) {
+        append_events(&pool).await;
+        let events = export_events(&pool, &AuditFilter::default()).await.unwrap();
+
+        let (content_type, body) = export(&pool, ExportFormat::Csv).await;
+        assert_eq!(content_type, "text/csv");
+        let lines: Vec<&str> = body.lines().collect();
+        assert_eq!(lines[0], CSV_HEADER);
+        assert_eq!(
+            lines[1],
+            format!(
+                "{},login_succeeded,7,7,203.0.113.9,,,{{}},{},{},{}",
+                events[0].id,
+                events[0].created_at.to_rfc3339(),
+                events[0].prev_hash,
+                events[0].hash
+            )
+        );
+        assert_eq!(
+            lines[2],
+            format!(
+                r#"{},login_failed,,,,,,"{{""email"":""x@example.com"",""reason"":""bad \""password\""""}}",{},{},{}"#,
+                events[1].id,
+                events[1].created_at.to_rfc3339(),
+                events[1].prev_hash,
+                events[1].hash
+            )
+        );
+        assert_eq!(lines.len(), 3);
+    }
+}
@@ -1,16 +1,51 @@
 use axum::{
-    extract::State,
+    extract::{Extension, State},
     http::StatusCode,
     Json,
 };
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use sqlx::{Pool, Postgres};
+use std::sync::Arc;
+use uuid::Uuid;
 use validator::Validate;
+use tracing::Instrument;
 
 use crate::{
-    db::queries::{create_user, get_user_by_email, update_login_activity, verify_password},
-    middleware::auth::create_token,
-    models::user::User,
+    config::config::app_config,
+    db::{
+        audit::record_event,
+        email_verifications::{
+            complete_email_verification, create_email_verification, EMAIL_VERIFICATION_TTL_HOURS,
+        },
+        password_resets::{complete_password_reset, renew_password_reset, PASSWORD_RESET_TTL_MINUTES},
+        queries::{
+            create_user, get_user_by_email, get_user_by_id, update_login_activity, verify_password,
+        },
+        sessions::{assess_login, create_session, disown_login, record_login},
+    },
+    mail::{
+        templates::{render, DEFAULT_LOCALE},
+        Mailer,
+    },
+    middleware::{
+        auth::{create_token, token_ttl, Claims, PASSWORD_CHANGE_SCOPE},
+        client::ClientInfo,
+    },
+    models::{
+        audit::AuditEventType,
+        mail::MailTemplate,
+        role::EffectivePermissions,
+        session::{DisownLoginRequest, LoginAssessment, RevokedSessions},
+        user::{
+            EmailVerificationRequest, PasswordResetRequest, ScopedTokenRequest, TokenResponse, User,
+        },
+    },
+    notify::{
+        login::{alert_unusual_login, FAILED_LOGIN_WINDOW_MINUTES},
+        Notifier,
+    },
+    telemetry::{self, metrics},
 };
 
 #[derive(Debug, Deserialize, Validate)]
@@ -31,15 +66,111 @@ pub struct LoginRequest {
     pub password: String,
 }
 
+/// `auth_method` of sessions started by logging in with a password, or by registering
+const PASSWORD_AUTH: &str = "password";
+const REGISTRATION_AUTH: &str = "registration";
+
+/// Records the successful login in the user's history and starts a session for it
+async fn start_session(
+    pool: &Pool<Postgres>,
+    client: &ClientInfo,
+    user: &User,
+    auth_method: &str,
+) -> Result<Uuid, (StatusCode, Json<String>)> {
+    let ip = client.ip.map(|ip| ip.to_string());
+    let user_agent = client.user_agent.as_deref();
+    if let Err(e) = record_login(pool, user.id, ip.as_deref(), user_agent, auth_method, None).await {
+        tracing::warn!(error = %e, "Failed to record login");
+    }
+
+    let expires_at = chrono::Utc::now() + token_ttl();
+    create_session(pool, user.id, ip.as_deref(), user_agent, auth_method, expires_at)
+        .await
+        .map_err(|e| {
+            tracing::error!(error = %e, "Failed to create session");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json("Failed to create session".to_string()),
+            )
+        })
+}
+
+/// Audits a failed login and, for known accounts, adds it to the user's history
+async fn record_failed_login(
+    pool: &Pool<Postgres>,
+    client: &ClientInfo,
+    email: &str,
+    user_id: Option<i32>,
+    reason: &str,
+) {
+    metrics::login("failure", reason);
+    if let Some(user_id) = user_id {
+        let ip = client.ip.map(|ip| ip.to_string());
+        if let Err(e) = record_login(
+            pool,
+            user_id,
+            ip.as_deref(),
+            client.user_agent.as_deref(),
+            PASSWORD_AUTH,
+            Some(reason),
+        )
+        .await
+        {
+            tracing::warn!(error = %e, "Failed to record login");
+        }
+    }
+
+    record_event(
+        pool,
+        &client.audit(None),
+        AuditEventType::LoginFailed,
+        user_id,
+        json!({ "email": email, "reason": reason }),
+    )
+    .await;
+}
+
 #[derive(Debug, Serialize)]
 pub struct AuthResponse {
     pub token: String,
     pub user: User,
+    /// The token is limited to changing the password until it is changed
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub password_change_required: bool,
+}
+
+/// Mails the user a link confirming their current email address
+pub async fn send_email_verification(pool: &Pool<Postgres>, mailer: &dyn Mailer, user: &User) {
+    let token = match create_email_verification(pool, user.id).await {
+        Ok(token) => token,
+        Err(e) => {
+            tracing::error!(user_id = user.id, error = %e, "Failed to issue email verification");
+            return;
+        }
+    };
+    let verify_url = format!("{}/verify-email?token={}", app_config().server.frontend_url, token);
+    let email = render(
+        MailTemplate::Verification,
+        DEFAULT_LOCALE,
+        &json!({
+            "name": user.firstname,
+            "verify_url": verify_url,
+            "expires_hours": EMAIL_VERIFICATION_TTL_HOURS,
+        }),
+    );
+    let result = match email {
+        Ok(email) => mailer.send(&email.addressed_to(&user.email)).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        tracing::error!(user_id = user.id, error = %e, "Failed to send email verification");
+    }
 }
 
 /// Register a new user
 /// 
 /// Register a new user with the provided details and return a JWT token.
+/// A link confirming the email address is mailed to the user.
 #[utoipa::path(
     post,
     path = "/auth/register",
@@ -53,13 +184,16 @@ pub struct AuthResponse {
 )]
 pub async fn register(
     State(pool): State<Pool<Postgres>>,
+    State(mailer): State<Arc<dyn Mailer>>,
+    client: ClientInfo,
     Json(payload): Json<RegisterRequest>,
 ) -> Result<(StatusCode, Json<AuthResponse>), (StatusCode, Json<String>)> {
-    println!("📝 Processing registration request for email: {}", payload.email);
+    tracing::debug!(email = %telemetry::email(&payload.email), "Processing registration request");
 
     // Validate request
     if let Err(e) = payload.validate() {
-        println!("❌ Registration validation failed: {}", e);
+        tracing::info!(error = %e, "Registration validation failed");
+        metrics::registration("invalid");
         return Err((
             StatusCode::BAD_REQUEST,
             Json(format!("Validation error: {}", e)),
@@ -67,8 +201,9 @@ pub async fn register(
     }
 
     // Check if user already exists
-    if let Ok(_) = get_user_by_email(&pool, &payload.email).await {
-        println!("❌ Registration failed: User already exists with email: {}", payload.email);
+    if get_user_by_email(&pool, &payload.email).await.is_ok() {
+        tracing::info!(email = %telemetry::email(&payload.email), "Registration failed: user already exists");
+        metrics::registration("conflict");
         return Err((
             StatusCode::CONFLICT,
             Json("User with this email already exists".to_string()),
@@ -85,17 +220,35 @@ pub async fn register(
     )
     .await
     .map_err(|e| {
-        println!("❌ Failed to create user in database: {}", e);
+        tracing::error!(error = %e, "Failed to create user in database");
         (
             StatusCode::INTERNAL_SERVER_ERROR,
             Json("Failed to create user".to_string()),
         )
     })?;
 
-    // Create token
-    let token = create_token(&user.email, &user.role);
-    println!("✅ Registration successful for user: {} {}", user.firstname, user.lastname);
-    Ok((StatusCode::CREATED, Json(AuthResponse { token, user })))
+    // Unscoped, so permissions granted later apply without logging in again
+    let session_id = start_session(&pool, &client, &user, REGISTRATION_AUTH).await?;
+    let token = create_token(&user.email, &user.role, None, None, Some(session_id));
+    record_event(
+        &pool,
+        &client.audit(Some(user.id)),
+        AuditEventType::Registered,
+        Some(user.id),
+        json!({ "email": user.email }),
+    )
+    .await;
+    send_email_verification(&pool, mailer.as_ref(), &user).await;
+    tracing::info!(user_id = user.id, "Registration successful");
+    metrics::registration("created");
+    Ok((
+        StatusCode::CREATED,
+        Json(AuthResponse {
+            token,
+            user,
+            password_change_required: false,
+        }),
+    ))
 }
 
 /// Login user
@@ -107,44 +260,483 @@ pub async fn register(
     request_body = LoginRequest,
     responses(
         (status = 200, description = "Login successful", body = AuthResponse),
-        (status = 401, description = "Invalid credentials")
+        (status = 401, description = "Invalid credentials"),
+        (status = 403, description = "Account disabled, or a password reset is required")
     ),
     tag = "Authentication"
 )]
 pub async fn login(
     State(pool): State<Pool<Postgres>>,
+    State(notifier): State<Arc<dyn Notifier>>,
+    State(mailer): State<Arc<dyn Mailer>>,
+    client: ClientInfo,
     Json(payload): Json<LoginRequest>,
 ) -> Result<Json<AuthResponse>, (StatusCode, Json<String>)> {
-    println!("🔐 Processing login request for email: {}", payload.email);
+    tracing::debug!(email = %telemetry::email(&payload.email), "Processing login request");
 
     // Get user
-    let user = get_user_by_email(&pool, &payload.email)
-        .await
-        .map_err(|_| {
-            println!("❌ Login failed: User not found with email: {}", payload.email);
-            (
+    let user = match get_user_by_email(&pool, &payload.email).await {
+        Ok(user) => user,
+        Err(_) => {
+            tracing::info!(email = %telemetry::email(&payload.email), "Login failed: unknown email");
+            record_failed_login(&pool, &client, &payload.email, None, "unknown_email").await;
+            return Err((
                 StatusCode::UNAUTHORIZED,
                 Json("Invalid credentials".to_string()),
-            )
-        })?;
+            ));
+        }
+    };
 
     // Verify password
     if !verify_password(&payload.password, &user.password) {
-        println!("❌ Login failed: Invalid password for user: {}", payload.email);
+        tracing::info!(user_id = user.id, "Login failed: invalid password");
+        record_failed_login(&pool, &client, &payload.email, Some(user.id), "invalid_password").await;
         return Err((
             StatusCode::UNAUTHORIZED,
             Json("Invalid credentials".to_string()),
         ));
     }
 
+    if user.disabled_at.is_some() {
+        tracing::info!(user_id = user.id, "Login failed: account disabled");
+        record_failed_login(&pool, &client, &payload.email, Some(user.id), "account_disabled").await;
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json("Account disabled".to_string()),
+        ));
+    }
+
+    // After a disowned login the password may be known to someone else, so it
+    // only works again once a reset link from the user's mailbox is used
+    if user.password_reset_required {
+        tracing::info!(user_id = user.id, "Login failed: password reset required");
+        record_failed_login(&pool, &client, &payload.email, Some(user.id), "password_reset_required").await;
+        match renew_password_reset(&pool, user.id).await {
+            Ok(Some(token)) => send_password_reset(mailer.as_ref(), &user, token).await,
+            Ok(None) => {}
+            Err(e) => tracing::error!(error = %e, "Failed to renew password reset"),
+        }
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json("Password reset required; use the link sent to your email".to_string()),
+        ));
+    }
+
     // Update login activity
     if let Err(e) = update_login_activity(&pool, user.id).await {
-        println!("⚠️ Failed to update login activity: {}", e);
+        tracing::warn!(error = %e, "Failed to update login activity");
         // Don't return error to user, just log it
     }
 
-    // Create token
-    let token = create_token(&user.email, &user.role);
-    println!("✅ Login successful for user: {} {}", user.firstname, user.lastname);
-    Ok(Json(AuthResponse { token, user }))
+    // A pending password change limits the token to the profile endpoints;
+    // other tokens stay unscoped, so permissions granted later apply to them
+    let password_change_required = user.must_change_password;
+    let scope = password_change_required.then_some(PASSWORD_CHANGE_SCOPE);
+    // Compare against earlier logins before this one joins the history
+    let ip = client.ip.map(|ip| ip.to_string());
+    let assessment = assess_login(
+        &pool,
+        user.id,
+        ip.as_deref(),
+        client.user_agent.as_deref(),
+        FAILED_LOGIN_WINDOW_MINUTES,
+    )
+    .await
+    .unwrap_or_else(|e| {
+        tracing::warn!(error = %e, "Failed to assess login");
+        LoginAssessment::default()
+    });
+    let session_id = start_session(&pool, &client, &user, PASSWORD_AUTH).await?;
+    let token = create_token(&user.email, &user.role, None, scope, Some(session_id));
+    record_event(
+        &pool,
+        &client.audit(Some(user.id)),
+        AuditEventType::LoginSucceeded,
+        Some(user.id),
+        json!({ "password_change_required": password_change_required }),
+    )
+    .await;
+    // Alert in the background so delivery never slows down or fails the login
+    let (alert_pool, alert_user, alert_client) = (pool.clone(), user.clone(), client.clone());
+    tokio::spawn(async move {
+        alert_unusual_login(
+            &alert_pool,
+            notifier,
+            &alert_user,
+            &alert_client,
+            &assessment,
+            session_id,
+        )
+        .await;
+    }.in_current_span());
+    tracing::info!(user_id = user.id, "Login successful");
+    metrics::login("success", "none");
+    Ok(Json(AuthResponse {
+        token,
+        user,
+        password_change_required,
+    }))
+}
+
+/// Mails a reset link to the user. Failures are only logged: the next refused
+/// login mails a new link once this one expires.
+async fn send_password_reset(mailer: &dyn Mailer, user: &User, token: Uuid) {
+    let reset_url = format!("{}/reset-password?token={}", app_config().server.frontend_url, token);
+    let email = render(
+        MailTemplate::PasswordReset,
+        DEFAULT_LOCALE,
+        &json!({
+            "name": user.firstname,
+            "reset_url": reset_url,
+            "expires_minutes": PASSWORD_RESET_TTL_MINUTES,
+        }),
+    );
+    let result = match email {
+        Ok(email) => mailer.send(&email.addressed_to(&user.email)).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        tracing::error!(user_id = user.id, error = %e, "Failed to send password reset");
+    }
+}
+
+/// Disown a login
+///
+/// Redeems the "this wasn't me" link of a login alert: signs out every session
+/// of the account and refuses password login until a new password is chosen
+/// through the reset link mailed to the user.
+#[utoipa::path(
+    post,
+    path = "/auth/not-me",
+    request_body = DisownLoginRequest,
+    responses(
+        (status = 200, description = "Sessions revoked and reset link sent", body = RevokedSessions),
+        (status = 404, description = "Unknown, expired or already used link")
+    ),
+    tag = "Authentication"
+)]
+pub async fn disown(
+    State(pool): State<Pool<Postgres>>,
+    State(mailer): State<Arc<dyn Mailer>>,
+    client: ClientInfo,
+    Json(payload): Json<DisownLoginRequest>,
+) -> Result<Json<RevokedSessions>, (StatusCode, Json<String>)> {
+    let disowned = match disown_login(&pool, &client.audit(None), payload.token).await {
+        Ok(Some(disowned)) => disowned,
+        Ok(None) => {
+            return Err((
+                StatusCode::NOT_FOUND,
+                Json("Invalid or expired link".to_string()),
+            ))
+        }
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to disown login");
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json("Failed to secure account".to_string()),
+            ));
+        }
+    };
+
+    match get_user_by_id(&pool, disowned.user_id).await {
+        Ok(user) => send_password_reset(mailer.as_ref(), &user, disowned.reset_token).await,
+        Err(e) => tracing::error!(error = %e, "Failed to load user for password reset"),
+    }
+    Ok(Json(RevokedSessions {
+        revoked: disowned.sessions_revoked,
+    }))
+}
+
+/// Reset password
+///
+/// Redeems a reset link mailed to the user: sets the new password, lifts the
+/// login block left by a disowned login and clears a pending password change.
+#[utoipa::path(
+    post,
+    path = "/auth/reset-password",
+    request_body = PasswordResetRequest,
+    responses(
+        (status = 204, description = "Password changed"),
+        (status = 400, description = "Invalid input"),
+        (status = 404, description = "Unknown, expired or already used link")
+    ),
+    tag = "Authentication"
+)]
+pub async fn reset_password(
+    State(pool): State<Pool<Postgres>>,
+    client: ClientInfo,
+    Json(payload): Json<PasswordResetRequest>,
+) -> Result<StatusCode, (StatusCode, Json<String>)> {
+    if let Err(e) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(format!("Validation error: {}", e)),
+        ));
+    }
+
+    match complete_password_reset(&pool, &client.audit(None), payload.token, &payload.password).await {
+        Ok(Some(_)) => Ok(StatusCode::NO_CONTENT),
+        Ok(None) => Err((
+            StatusCode::NOT_FOUND,
+            Json("Invalid or expired link".to_string()),
+        )),
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to reset password");
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json("Failed to reset password".to_string()),
+            ))
+        }
+    }
+}
+
+/// Verify email address
+///
+/// Redeems a verification link mailed to the user, marking their email address verified.
+#[utoipa::path(
+    post,
+    path = "/auth/verify-email",
+    request_body = EmailVerificationRequest,
+    responses(
+        (status = 204, description = "Email address verified"),
+        (status = 404, description = "Unknown, expired or already used link, or the email has changed since")
+    ),
+    tag = "Authentication"
+)]
+pub async fn verify_email(
+    State(pool): State<Pool<Postgres>>,
+    client: ClientInfo,
+    Json(payload): Json<EmailVerificationRequest>,
+) -> Result<StatusCode, (StatusCode, Json<String>)> {
+    match complete_email_verification(&pool, &client.audit(None), payload.token).await {
+        Ok(Some(_)) => Ok(StatusCode::NO_CONTENT),
+        Ok(None) => Err((
+            StatusCode::NOT_FOUND,
+            Json("Invalid or expired link".to_string()),
+        )),
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to verify email");
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json("Failed to verify email".to_string()),
+            ))
+        }
+    }
+}
+
+/// Issue a narrowed token
+///
+/// Mints a token limited to the requested scopes, e.g. a read-only token for an
+/// integration. Scopes must be a subset of the calling token's own permissions.
+#[utoipa::path(
+    post,
+    path = "/auth/token",
+    request_body = ScopedTokenRequest,
+    responses(
+        (status = 200, description = "Token issued", body = TokenResponse),
+        (status = 400, description = "No scopes requested"),
+        (status = 401, description = "No token provided or invalid token"),
+        (status = 403, description = "Requested scopes exceed the calling token's permissions")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Authentication"
+)]
+pub async fn issue_scoped_token(
+    State(pool): State<Pool<Postgres>>,
+    Extension(user): Extension<User>,
+    Extension(claims): Extension<Claims>,
+    Extension(permissions): Extension<EffectivePermissions>,
+    client: ClientInfo,
+    Json(payload): Json<ScopedTokenRequest>,
+) -> Result<Json<TokenResponse>, (StatusCode, Json<String>)> {
+    if payload.scopes.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json("At least one scope is required".to_string()),
+        ));
+    }
+
+    let exceeding: Vec<&str> = payload
+        .scopes
+        .iter()
+        .map(String::as_str)
+        .filter(|scope| !permissions.contains(scope))
+        .collect();
+    if !exceeding.is_empty() {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(format!("Scopes not granted to this token: {}", exceeding.join(" "))),
+        ));
+    }
+
+    let scope = payload.scopes.join(" ");
+    tracing::info!(user_id = user.id, %scope, "Issuing scoped token");
+    let token = create_token(&user.email, &user.role, claims.org, Some(&scope), claims.sid);
+    record_event(
+        &pool,
+        &client.audit(Some(user.id)),
+        AuditEventType::TokenIssued,
+        Some(user.id),
+        json!({ "scopes": payload.scopes }),
+    )
+    .await;
+    Ok(Json(TokenResponse { token }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::config::init_for_tests,
+        db::sessions::create_login_alert,
+        mail::memory::InMemoryMailer,
+        notify::memory::InMemoryNotifier,
+        test_support::insert_user,
+    };
+
+    async fn log_in(
+        pool: &Pool<Postgres>,
+        mailer: &InMemoryMailer,
+        password: &str,
+    ) -> Result<Json<AuthResponse>, (StatusCode, Json<String>)> {
+        login(
+            State(pool.clone()),
+            State(Arc::new(InMemoryNotifier::default())),
+            State(Arc::new(mailer.clone())),
+            ClientInfo::default(),
+            Json(LoginRequest {
+                email: "user@example.com".to_string(),
+                password: password.to_string(),
+            }),
+        )
+        .await
+    }
+
+    /// Token of the link in a mailed password reset or email verification
+    fn link_token(text: &str) -> Uuid {
+        let start = text.find("token=").expect("email has a link") + "token=".len();
+        text[start..start + 36].parse().unwrap()
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn disowned_login_requires_a_mailed_reset(pool: Pool<Postgres>) {
+        init_for_tests();
+        let user = insert_user(&pool, "user@example.com", "User").await;
+        let expires_at = chrono::Utc::now() + token_ttl();
+        let session_id = create_session(&pool, user.id, None, None, PASSWORD_AUTH, expires_at)
+            .await
+            .unwrap();
+        let alert = Uuid::new_v4();
+        create_login_alert(&pool, user.id, alert, session_id, &["new_device"], None, None, expires_at)
+            .await
+            .unwrap();
+
+        let mailer = InMemoryMailer::default();
+        let disowned = disown(
+            State(pool.clone()),
+            State(Arc::new(mailer.clone())),
+            ClientInfo::default(),
+            Json(DisownLoginRequest { token: alert }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(disowned.revoked, 1);
+        let sent = mailer.sent();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].to, "user@example.com");
+        let token = link_token(&sent[0].text);
+
+        // The old password no longer logs in, and the pending link is not re-sent
+        let (status, _) = log_in(&pool, &mailer, "password").await.unwrap_err();
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(mailer.sent().len(), 1);
+
+        let reset = |token: Uuid| {
+            reset_password(
+                State(pool.clone()),
+                ClientInfo::default(),
+                Json(PasswordResetRequest {
+                    token,
+                    password: "new-password".to_string(),
+                }),
+            )
+        };
+        assert_eq!(reset(token).await.unwrap(), StatusCode::NO_CONTENT);
+        assert_eq!(reset(token).await.unwrap_err().0, StatusCode::NOT_FOUND);
+
+        let logged_in = log_in(&pool, &mailer, "new-password").await.unwrap();
+        assert!(!logged_in.password_change_required);
+        assert!(!logged_in.user.password_reset_required);
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn refused_login_mails_a_new_link_once_the_last_expired(pool: Pool<Postgres>) {
+        init_for_tests();
+        let user = insert_user(&pool, "user@example.com", "User").await;
+        sqlx::query("UPDATE users SET password_reset_required = TRUE WHERE id = $1")
+            .bind(user.id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let mailer = InMemoryMailer::default();
+        for _ in 0..2 {
+            let (status, _) = log_in(&pool, &mailer, "password").await.unwrap_err();
+            assert_eq!(status, StatusCode::FORBIDDEN);
+        }
+        assert_eq!(mailer.sent().len(), 1);
+
+        sqlx::query("UPDATE password_resets SET expires_at = CURRENT_TIMESTAMP WHERE user_id = $1")
+            .bind(user.id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let (status, _) = log_in(&pool, &mailer, "password").await.unwrap_err();
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let sent = mailer.sent();
+        assert_eq!(sent.len(), 2);
+        assert_ne!(link_token(&sent[0].text), link_token(&sent[1].text));
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn registration_mails_an_email_verification(pool: Pool<Postgres>) {
+        init_for_tests();
+        let mailer = InMemoryMailer::default();
+        let (_, Json(registered)) = register(
+            State(pool.clone()),
+            State(Arc::new(mailer.clone())),
+            ClientInfo::default(),
+            Json(RegisterRequest {
+                firstname: "Ada".to_string(),
+                lastname: "Lovelace".to_string(),
+                email: "ada@example.com".to_string(),
+                password: "password".to_string(),
+            }),
+        )
+        .await
+        .unwrap();
+        assert!(!registered.user.email_verified);
+
+        let sent = mailer.sent();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].to, "ada@example.com");
+        let verify = |token| {
+            verify_email(
+                State(pool.clone()),
+                ClientInfo::default(),
+                Json(EmailVerificationRequest { token }),
+            )
+        };
+        let token = link_token(&sent[0].text);
+        assert_eq!(verify(token).await.unwrap(), StatusCode::NO_CONTENT);
+        assert!(get_user_by_id(&pool, registered.user.id).await.unwrap().email_verified);
+
+        let (status, _) = verify(token).await.unwrap_err();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
 }
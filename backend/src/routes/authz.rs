@@ -0,0 +1,399 @@
+use axum::{
+    extract::{Extension, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde_json::json;
+use sqlx::{Pool, Postgres};
+use std::sync::Arc;
+
+use crate::{
+    authz::{
+        engine::{check, expand, list_objects},
+        schema::AuthzSchema,
+    },
+    db::{
+        audit::record_event,
+        relations::{delete_tuple, list_object_tuples, write_tuple},
+    },
+    middleware::client::ClientInfo,
+    models::{
+        audit::AuditEventType,
+        relation::{
+            CheckRequest, CheckResponse, ExpandNode, ExpandRequest, ListObjectsRequest,
+            ListObjectsResponse, ObjectRef, SubjectRef, TupleQuery, TupleRequest,
+        },
+        role::EffectivePermissions,
+        user::User,
+    },
+};
+
+/// Permission needed to inspect relations of subjects other than yourself
+const READ_RELATIONS: &str = "relations:read";
+
+fn bad_request(message: String) -> (StatusCode, Json<String>) {
+    (StatusCode::BAD_REQUEST, Json(message))
+}
+
+fn parse_object(schema: &AuthzSchema, object: &str, relation: &str) -> Result<ObjectRef, (StatusCode, Json<String>)> {
+    let object: ObjectRef = object.parse().map_err(bad_request)?;
+    if schema.relation(&object.object_type, relation).is_none() {
+        return Err(bad_request(format!(
+            "Unknown relation {} on namespace {}",
+            relation, object.object_type
+        )));
+    }
+    Ok(object)
+}
+
+fn parse_subject(schema: &AuthzSchema, subject: &str) -> Result<SubjectRef, (StatusCode, Json<String>)> {
+    let subject: SubjectRef = subject.parse().map_err(bad_request)?;
+    let namespace = &subject.object.object_type;
+    if !schema.has_namespace(namespace) {
+        return Err(bad_request(format!("Unknown namespace {}", namespace)));
+    }
+    if let Some(relation) = &subject.relation {
+        if schema.relation(namespace, relation).is_none() {
+            return Err(bad_request(format!(
+                "Unknown relation {} on namespace {}",
+                relation, namespace
+            )));
+        }
+    }
+    Ok(subject)
+}
+
+/// Resolves the subject of a query, defaulting to the caller. Asking about
+/// anyone else requires the `relations:read` permission.
+fn resolve_subject(
+    schema: &AuthzSchema,
+    subject: Option<&str>,
+    user: &User,
+    permissions: &EffectivePermissions,
+) -> Result<SubjectRef, (StatusCode, Json<String>)> {
+    let caller = format!("user:{}", user.id);
+    let subject = parse_subject(schema, subject.unwrap_or(&caller))?;
+
+    if subject.to_string() != caller && !permissions.contains(READ_RELATIONS) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(format!("Missing permission: {}", READ_RELATIONS)),
+        ));
+    }
+    Ok(subject)
+}
+
+async fn record_tuple_change(
+    pool: &Pool<Postgres>,
+    admin: &User,
+    client: &ClientInfo,
+    action: &str,
+    object: &ObjectRef,
+    relation: &str,
+    subject: &SubjectRef,
+) {
+    record_event(
+        pool,
+        &client.audit(Some(admin.id)),
+        AuditEventType::AdminAction,
+        None,
+        json!({ "action": action, "tuple": format!("{}#{}@{}", object, relation, subject) }),
+    )
+    .await;
+}
+
+fn engine_error(_: sqlx::Error) -> (StatusCode, Json<String>) {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json("Failed to evaluate relations".to_string()),
+    )
+}
+
+/// Write a relationship tuple
+///
+/// Stores `object#relation@subject`, e.g. `doc:readme#parent@folder:docs` or
+/// `folder:docs#owner@team:core#member`.
+#[utoipa::path(
+    post,
+    path = "/authz/tuples",
+    request_body = TupleRequest,
+    responses(
+        (status = 204, description = "Tuple stored"),
+        (status = 400, description = "Malformed tuple or unknown namespace/relation"),
+        (status = 403, description = "Requires the relations:write permission")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Authorization"
+)]
+pub async fn post_tuple(
+    State(pool): State<Pool<Postgres>>,
+    State(schema): State<Arc<AuthzSchema>>,
+    Extension(admin): Extension<User>,
+    client: ClientInfo,
+    Json(payload): Json<TupleRequest>,
+) -> Result<StatusCode, (StatusCode, Json<String>)> {
+    let object = parse_object(&schema, &payload.object, &payload.relation)?;
+    let subject = parse_subject(&schema, &payload.subject)?;
+
+    write_tuple(&pool, &object, &payload.relation, &subject)
+        .await
+        .map_err(|_| (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json("Failed to store tuple".to_string()),
+        ))?;
+    record_tuple_change(&pool, &admin, &client, "write_tuple", &object, &payload.relation, &subject).await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Delete a relationship tuple
+#[utoipa::path(
+    delete,
+    path = "/authz/tuples",
+    request_body = TupleRequest,
+    responses(
+        (status = 204, description = "Tuple deleted"),
+        (status = 400, description = "Malformed tuple or unknown namespace/relation"),
+        (status = 403, description = "Requires the relations:write permission"),
+        (status = 404, description = "Tuple not found")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Authorization"
+)]
+pub async fn delete_tuple_route(
+    State(pool): State<Pool<Postgres>>,
+    State(schema): State<Arc<AuthzSchema>>,
+    Extension(admin): Extension<User>,
+    client: ClientInfo,
+    Json(payload): Json<TupleRequest>,
+) -> Result<StatusCode, (StatusCode, Json<String>)> {
+    let object = parse_object(&schema, &payload.object, &payload.relation)?;
+    let subject = parse_subject(&schema, &payload.subject)?;
+
+    let deleted = delete_tuple(&pool, &object, &payload.relation, &subject)
+        .await
+        .map_err(|_| (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json("Failed to delete tuple".to_string()),
+        ))?;
+
+    if !deleted {
+        return Err((StatusCode::NOT_FOUND, Json("Tuple not found".to_string())));
+    }
+    record_tuple_change(&pool, &admin, &client, "delete_tuple", &object, &payload.relation, &subject).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// List the tuples stored on an object
+#[utoipa::path(
+    get,
+    path = "/authz/tuples",
+    params(TupleQuery),
+    responses(
+        (status = 200, description = "Tuples stored on the object", body = [TupleRequest]),
+        (status = 400, description = "Malformed object"),
+        (status = 403, description = "Requires the relations:write permission")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Authorization"
+)]
+pub async fn get_tuples(
+    State(pool): State<Pool<Postgres>>,
+    Query(query): Query<TupleQuery>,
+) -> Result<Json<Vec<TupleRequest>>, (StatusCode, Json<String>)> {
+    let object: ObjectRef = query.object.parse().map_err(bad_request)?;
+
+    let tuples = list_object_tuples(&pool, &object)
+        .await
+        .map_err(|_| (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json("Failed to fetch tuples".to_string()),
+        ))?;
+
+    Ok(Json(tuples.into_iter().map(TupleRequest::from).collect()))
+}
+
+/// Check a relation
+///
+/// Returns whether the subject (by default the caller) has the relation on the
+/// object, directly, through a userset, an implied relation or a parent object.
+#[utoipa::path(
+    post,
+    path = "/authz/check",
+    request_body = CheckRequest,
+    responses(
+        (status = 200, description = "Check evaluated", body = CheckResponse),
+        (status = 400, description = "Malformed object/subject or unknown relation"),
+        (status = 403, description = "Checking other subjects requires relations:read")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Authorization"
+)]
+pub async fn post_check(
+    State(pool): State<Pool<Postgres>>,
+    State(schema): State<Arc<AuthzSchema>>,
+    Extension(user): Extension<User>,
+    Extension(permissions): Extension<EffectivePermissions>,
+    Json(payload): Json<CheckRequest>,
+) -> Result<Json<CheckResponse>, (StatusCode, Json<String>)> {
+    let object = parse_object(&schema, &payload.object, &payload.relation)?;
+    let subject = resolve_subject(&schema, payload.subject.as_deref(), &user, &permissions)?;
+
+    let allowed = check(&pool, &schema, &object, &payload.relation, &subject, 0)
+        .await
+        .map_err(engine_error)?;
+
+    Ok(Json(CheckResponse { allowed }))
+}
+
+/// Expand a relation
+///
+/// Returns the tree of subjects and usersets that have the relation on the object.
+#[utoipa::path(
+    post,
+    path = "/authz/expand",
+    request_body = ExpandRequest,
+    responses(
+        (status = 200, description = "Userset tree", body = ExpandNode),
+        (status = 400, description = "Malformed object or unknown relation"),
+        (status = 403, description = "Requires the relations:read permission")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Authorization"
+)]
+pub async fn post_expand(
+    State(pool): State<Pool<Postgres>>,
+    State(schema): State<Arc<AuthzSchema>>,
+    Extension(permissions): Extension<EffectivePermissions>,
+    Json(payload): Json<ExpandRequest>,
+) -> Result<Json<ExpandNode>, (StatusCode, Json<String>)> {
+    if !permissions.contains(READ_RELATIONS) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(format!("Missing permission: {}", READ_RELATIONS)),
+        ));
+    }
+    let object = parse_object(&schema, &payload.object, &payload.relation)?;
+
+    let tree = expand(&pool, &schema, &object, &payload.relation, 0)
+        .await
+        .map_err(engine_error)?;
+
+    Ok(Json(tree))
+}
+
+/// List objects
+///
+/// Returns the objects of a type on which the subject (by default the caller) has the relation.
+#[utoipa::path(
+    post,
+    path = "/authz/list-objects",
+    request_body = ListObjectsRequest,
+    responses(
+        (status = 200, description = "Matching objects", body = ListObjectsResponse),
+        (status = 400, description = "Malformed subject or unknown relation"),
+        (status = 403, description = "Listing for other subjects requires relations:read")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Authorization"
+)]
+pub async fn post_list_objects(
+    State(pool): State<Pool<Postgres>>,
+    State(schema): State<Arc<AuthzSchema>>,
+    Extension(user): Extension<User>,
+    Extension(permissions): Extension<EffectivePermissions>,
+    Json(payload): Json<ListObjectsRequest>,
+) -> Result<Json<ListObjectsResponse>, (StatusCode, Json<String>)> {
+    if schema.relation(&payload.object_type, &payload.relation).is_none() {
+        return Err(bad_request(format!(
+            "Unknown relation {} on namespace {}",
+            payload.relation, payload.object_type
+        )));
+    }
+    let subject = resolve_subject(&schema, payload.subject.as_deref(), &user, &permissions)?;
+
+    let objects = list_objects(&pool, &schema, &payload.object_type, &payload.relation, &subject)
+        .await
+        .map_err(engine_error)?;
+
+    Ok(Json(ListObjectsResponse {
+        objects: objects.iter().map(ToString::to_string).collect(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::insert_user;
+
+    fn tuple(object: &str, relation: &str, subject: &str) -> Json<TupleRequest> {
+        Json(TupleRequest {
+            object: object.to_string(),
+            relation: relation.to_string(),
+            subject: subject.to_string(),
+        })
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn tuple_writes_are_validated_and_audited(pool: Pool<Postgres>) {
+        let admin = insert_user(&pool, "admin@example.com", "Admin").await;
+        let schema = Arc::new(AuthzSchema::parse(include_str!("../../authz_schema.json")).unwrap());
+        let post = |payload| {
+            post_tuple(
+                State(pool.clone()),
+                State(schema.clone()),
+                Extension(admin.clone()),
+                ClientInfo::default(),
+                payload,
+            )
+        };
+        let delete = |payload| {
+            delete_tuple_route(
+                State(pool.clone()),
+                State(schema.clone()),
+                Extension(admin.clone()),
+                ClientInfo::default(),
+                payload,
+            )
+        };
+
+        assert_eq!(post(tuple("doc:readme", "editor", "team:core#member")).await.unwrap(), StatusCode::NO_CONTENT);
+        let (status, _) = post(tuple("doc:readme", "reader", "user:1")).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, _) = delete(tuple("doc:readme", "reader", "user:1")).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _) = delete(tuple("doc:readme", "editor", "team:core#owner")).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(delete(tuple("doc:readme", "editor", "team:core#member")).await.unwrap(), StatusCode::NO_CONTENT);
+        let (status, _) = delete(tuple("doc:readme", "editor", "team:core#member")).await.unwrap_err();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let events: Vec<(Option<i32>, serde_json::Value)> = sqlx::query_as(
+            "SELECT actor_id, details FROM audit_events WHERE event_type = 'admin_action' ORDER BY id",
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(
+            events,
+            [
+                (Some(admin.id), json!({ "action": "write_tuple", "tuple": "doc:readme#editor@team:core#member" })),
+                (Some(admin.id), json!({ "action": "delete_tuple", "tuple": "doc:readme#editor@team:core#member" })),
+            ]
+        );
+    }
+}
@@ -0,0 +1,132 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use sha2::{Digest, Sha256};
+use sqlx::{Pool, Postgres};
+
+use crate::{
+    db::queries::get_user_by_id,
+    imaging::avatar::{initials, Avatar},
+    models::avatar::{AvatarFormat, AvatarQuery, AvatarStyle},
+};
+
+/// Default and allowed edge lengths of generated avatars
+const DEFAULT_AVATAR_SIZE: u32 = 128;
+const MIN_AVATAR_SIZE: u32 = 16;
+const MAX_AVATAR_SIZE: u32 = 512;
+/// Bump when the drawing changes, so clients drop cached avatars
+const AVATAR_VERSION: u32 = 1;
+/// Avatars only change with the user's name, so a day of staleness is fine.
+/// They reveal initials, so shared caches must not keep them.
+const CACHE_CONTROL: &str = "private, max-age=86400";
+
+/// Get a generated avatar
+///
+/// Returns a deterministic avatar for the user, with their initials on a
+/// colour derived from their id or an identicon, as SVG or PNG. Every client
+/// gets the same image for the same user, size and style. Only signed-in
+/// users may fetch avatars, as they reveal which ids exist and their initials.
+#[utoipa::path(
+    get,
+    path = "/api/users/{id}/avatar",
+    params(
+        ("id" = i32, Path, description = "User id"),
+        AvatarQuery
+    ),
+    responses(
+        (status = 200, description = "The avatar image"),
+        (status = 304, description = "Unchanged since the cached copy"),
+        (status = 400, description = "Invalid size"),
+        (status = 401, description = "Unauthorized - Invalid or missing token"),
+        (status = 404, description = "User not found")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Profile"
+)]
+pub async fn get_avatar(
+    State(pool): State<Pool<Postgres>>,
+    Path(user_id): Path<i32>,
+    Query(query): Query<AvatarQuery>,
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, Json<String>)> {
+    let size = query.size.unwrap_or(DEFAULT_AVATAR_SIZE);
+    if !(MIN_AVATAR_SIZE..=MAX_AVATAR_SIZE).contains(&size) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(format!(
+                "size must be between {} and {}",
+                MIN_AVATAR_SIZE, MAX_AVATAR_SIZE
+            )),
+        ));
+    }
+
+    let user = get_user_by_id(&pool, user_id).await.map_err(|e| match e {
+        sqlx::Error::RowNotFound => (StatusCode::NOT_FOUND, Json("User not found".to_string())),
+        _ => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json("Failed to fetch user".to_string()),
+        ),
+    })?;
+
+    let letters = match query.style {
+        AvatarStyle::Initials => initials(&user.firstname, &user.lastname),
+        AvatarStyle::Identicon => None,
+    };
+    let etag = format!(
+        "\"{}\"",
+        &hex::encode(Sha256::digest(format!(
+            "{}:{}:{:?}:{}:{}:{:?}",
+            AVATAR_VERSION,
+            user_id,
+            query.style,
+            letters.as_deref().unwrap_or_default(),
+            size,
+            query.format
+        )))[..32]
+    );
+
+    let cached = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|tags| tags.split(',').any(|tag| tag.trim() == etag || tag.trim() == "*"));
+    if cached {
+        return Ok((
+            StatusCode::NOT_MODIFIED,
+            [(header::ETAG, etag), (header::CACHE_CONTROL, CACHE_CONTROL.to_string())],
+        )
+            .into_response());
+    }
+
+    let avatar = match &letters {
+        Some(letters) => Avatar::initials(user_id, letters),
+        None => Avatar::identicon(user_id),
+    };
+    let (content_type, body) = match query.format {
+        AvatarFormat::Svg => ("image/svg+xml", avatar.to_svg(size).into_bytes()),
+        AvatarFormat::Png => (
+            "image/png",
+            avatar.to_png(size).map_err(|e| {
+                tracing::error!(error = %e, "Failed to render avatar");
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json("Failed to render avatar".to_string()),
+                )
+            })?,
+        ),
+    };
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (header::CACHE_CONTROL, CACHE_CONTROL.to_string()),
+            (header::ETAG, etag),
+        ],
+        body,
+    )
+        .into_response())
+}
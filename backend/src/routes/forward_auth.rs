@@ -0,0 +1,299 @@
+use axum::{
+    extract::{Query, State},
+    http::{header, HeaderMap, HeaderName, HeaderValue, Method, StatusCode, Uri},
+    response::{IntoResponse, Response},
+};
+use axum_extra::headers::{authorization::Bearer, Authorization, Cookie, HeaderMapExt};
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::{Pool, Postgres};
+use std::sync::Arc;
+use utoipa::IntoParams;
+
+use crate::{
+    config::config::app_config,
+    db::roles::get_user_roles,
+    middleware::{
+        auth::{authenticate, check_policies, record_impersonated_request},
+        client::ClientInfo,
+        permissions::PermissionCache,
+    },
+    policy::schema::PolicySet,
+};
+
+/// Optional requirements; each may also be sent as `X-Required-Role` / `X-Required-Permission`
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct VerifyQuery {
+    /// Role the user must hold, directly, through a group or through inheritance
+    pub role: Option<String>,
+    /// Permission the token must carry
+    pub permission: Option<String>,
+}
+
+fn requirement(headers: &HeaderMap, name: &str, query: Option<String>) -> Option<String> {
+    query.or_else(|| {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string())
+    })
+}
+
+/// Method and path of the request being verified: Traefik sends them as
+/// `X-Forwarded-Method` / `X-Forwarded-Uri`, nginx configurations commonly as
+/// `X-Original-Method` / `X-Original-URI`. Without them, the verify request's own.
+fn original_request(headers: &HeaderMap, method: &Method, uri: &Uri) -> (String, String) {
+    let header = |names: [&str; 2]| {
+        names
+            .iter()
+            .find_map(|name| headers.get(*name).and_then(|v| v.to_str().ok()))
+    };
+    let method = header(["x-forwarded-method", "x-original-method"])
+        .map(|method| method.to_uppercase())
+        .unwrap_or_else(|| method.to_string());
+    let path = header(["x-forwarded-uri", "x-original-uri"])
+        .and_then(|uri| uri.parse::<Uri>().ok())
+        .map(|uri| uri.path().to_string())
+        .unwrap_or_else(|| uri.path().to_string());
+    (method, path)
+}
+
+fn unauthorized(message: &str) -> Response {
+    (
+        StatusCode::UNAUTHORIZED,
+        [(header::WWW_AUTHENTICATE, "Bearer")],
+        message.to_string(),
+    )
+        .into_response()
+}
+
+/// Verify a request for a reverse proxy
+///
+/// Forward-auth endpoint for nginx `auth_request` and Traefik `ForwardAuth`.
+/// Accepts the bearer token or, failing that, the session cookie named by
+/// `auth.cookie_name`, and applies the route policies to the original
+/// request named by the proxy's forwarded method and URI headers. On success the
+/// caller's identity is returned in `X-User-Id`, `X-User-Email` and
+/// `X-User-Role` (plus `X-Impersonated-By` for impersonation tokens) for the
+/// proxy to pass upstream, e.g. with nginx:
+///
+/// ```text
+/// location = /_auth {
+///     internal;
+///     proxy_pass http://backend/auth/verify?role=Admin;
+///     proxy_set_header X-Original-URI $request_uri;
+///     proxy_set_header X-Original-Method $request_method;
+/// }
+/// location /admin/ { auth_request /_auth; auth_request_set $user $upstream_http_x_user_email; }
+/// ```
+#[utoipa::path(
+    get,
+    path = "/auth/verify",
+    params(VerifyQuery),
+    responses(
+        (status = 200, description = "Authenticated; identity in X-User-* headers"),
+        (status = 401, description = "Missing, invalid or expired credentials"),
+        (status = 403, description = "Authenticated but lacking the required role or permission, or denied by a route policy")
+    ),
+    tag = "Authentication"
+)]
+#[allow(clippy::too_many_arguments)]
+pub async fn verify(
+    State(pool): State<Pool<Postgres>>,
+    State(permission_cache): State<PermissionCache>,
+    State(policies): State<Arc<PolicySet>>,
+    client: ClientInfo,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    Query(query): Query<VerifyQuery>,
+) -> Response {
+    let token = match headers.typed_get::<Authorization<Bearer>>() {
+        Some(auth) => auth.token().to_string(),
+        None => match headers
+            .typed_get::<Cookie>()
+            .and_then(|cookie| cookie.get(&app_config().auth.cookie_name).map(|v| v.to_string()))
+        {
+            Some(token) => token,
+            None => return unauthorized("Missing credentials"),
+        },
+    };
+
+    let (claims, user, permissions) = match authenticate(&pool, &permission_cache, &token).await {
+        Ok(authenticated) => authenticated,
+        Err((StatusCode::UNAUTHORIZED, message)) => return unauthorized(&message),
+        Err(error) => return error.into_response(),
+    };
+
+    // Route policies apply as they would to the same request sent to the API
+    let (method, path) = original_request(&headers, &method, &uri);
+    let response = async {
+        if let Err(reason) = check_policies(&policies, &claims, &user, &permissions, &client, &method, &path) {
+            return (StatusCode::FORBIDDEN, reason).into_response();
+        }
+
+        if let Some(permission) = requirement(&headers, "x-required-permission", query.permission) {
+            if !permissions.contains(&permission) {
+                return (StatusCode::FORBIDDEN, format!("Missing permission: {}", permission))
+                    .into_response();
+            }
+        }
+
+        if let Some(role) = requirement(&headers, "x-required-role", query.role) {
+            let roles = match get_user_roles(&pool, user.id).await {
+                Ok(roles) => roles,
+                Err(_) => {
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        "Failed to resolve roles".to_string(),
+                    )
+                        .into_response()
+                }
+            };
+            if !roles.contains(&role) {
+                return (StatusCode::FORBIDDEN, format!("Requires role: {}", role)).into_response();
+            }
+        }
+
+        let mut response_headers = HeaderMap::new();
+        let identity = [
+            ("x-user-id", Some(user.id.to_string())),
+            ("x-user-email", Some(user.email.clone())),
+            ("x-user-role", Some(user.role.clone())),
+            ("x-user-org", claims.org.map(|org| org.to_string())),
+            ("x-impersonated-by", claims.act.as_ref().map(|actor| actor.sub.clone())),
+        ];
+        for (name, value) in identity {
+            if let Some(value) = value.and_then(|v| HeaderValue::from_str(&v).ok()) {
+                response_headers.insert(HeaderName::from_static(name), value);
+            }
+        }
+
+        (StatusCode::OK, response_headers).into_response()
+    }
+    .await;
+
+    // The proxied request never reaches `auth_middleware`, so impersonation
+    // is recorded here against the original request
+    let details = json!({
+        "method": method,
+        "path": path,
+        "status": response.status().as_u16(),
+        "via": "forward_auth",
+    });
+    record_impersonated_request(&pool, &client, &claims, user.id, details).await;
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::config::init_for_tests,
+        middleware::auth::{create_impersonation_token, create_token},
+        test_support::insert_user,
+    };
+
+    async fn verify_with(pool: &Pool<Postgres>, headers: HeaderMap) -> Response {
+        verify(
+            State(pool.clone()),
+            State(PermissionCache::default()),
+            State(Arc::new(PolicySet::parse(r#"{ "policies": [] }"#).unwrap())),
+            ClientInfo::default(),
+            Method::GET,
+            "/auth/verify".parse().unwrap(),
+            headers,
+            Query(VerifyQuery { role: None, permission: None }),
+        )
+        .await
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn accepts_the_session_cookie_without_a_bearer_token(pool: Pool<Postgres>) {
+        init_for_tests();
+        let user = insert_user(&pool, "user@example.com", "User").await;
+        let token = create_token(&user.email, &user.role, None, None, None);
+        let cookie = |value: String| {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::COOKIE, HeaderValue::from_str(&value).unwrap());
+            headers
+        };
+
+        let response = verify_with(&pool, cookie(format!("theme=dark; {}={}", app_config().auth.cookie_name, token))).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["x-user-email"], "user@example.com");
+
+        let response = verify_with(&pool, cookie(format!("other={}", token))).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = verify_with(&pool, cookie(format!("{}=garbage", app_config().auth.cookie_name))).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        // A bearer token takes precedence over the cookie
+        let mut headers = cookie(format!("{}={}", app_config().auth.cookie_name, token));
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_static("Bearer garbage"));
+        assert_eq!(verify_with(&pool, headers).await.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn impersonated_requests_are_audited(pool: Pool<Postgres>) {
+        init_for_tests();
+        let admin = insert_user(&pool, "admin@example.com", "Admin").await;
+        let user = insert_user(&pool, "user@example.com", "User").await;
+        let (token, _) = create_impersonation_token(&user, &admin, "profile:read", chrono::Duration::minutes(5));
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_str(&format!("Bearer {}", token)).unwrap());
+        headers.insert("x-original-method", HeaderValue::from_static("GET"));
+        headers.insert("x-original-uri", HeaderValue::from_static("/app/profile"));
+
+        let response = verify_with(&pool, headers).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["x-impersonated-by"], "admin@example.com");
+
+        let (actor_id, target, details): (Option<i32>, Option<i32>, serde_json::Value) = sqlx::query_as(
+            "SELECT actor_id, target_user_id, details FROM audit_events WHERE details->>'action' = 'impersonated_request'",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!((actor_id, target), (Some(admin.id), Some(user.id)));
+        assert_eq!(details["path"], "/app/profile");
+        assert_eq!(details["status"], 200);
+        assert_eq!(details["via"], "forward_auth");
+    }
+
+    #[test]
+    fn original_request_prefers_forwarded_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-method", HeaderValue::from_static("post"));
+        headers.insert("x-forwarded-uri", HeaderValue::from_static("/api/admin/users?page=2"));
+        let own: Uri = "/auth/verify?role=Admin".parse().unwrap();
+
+        let (method, path) = original_request(&headers, &Method::GET, &own);
+        assert_eq!(method, "POST");
+        assert_eq!(path, "/api/admin/users");
+    }
+
+    #[test]
+    fn original_request_reads_nginx_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-original-method", HeaderValue::from_static("DELETE"));
+        headers.insert("x-original-uri", HeaderValue::from_static("/api/org/members/3"));
+        let own: Uri = "/auth/verify".parse().unwrap();
+
+        assert_eq!(
+            original_request(&headers, &Method::GET, &own),
+            ("DELETE".to_string(), "/api/org/members/3".to_string())
+        );
+    }
+
+    #[test]
+    fn original_request_falls_back_to_the_verify_request() {
+        let own: Uri = "/auth/verify".parse().unwrap();
+        assert_eq!(
+            original_request(&HeaderMap::new(), &Method::GET, &own),
+            ("GET".to_string(), "/auth/verify".to_string())
+        );
+    }
+}
\ No newline at end of file
@@ -1,9 +1,30 @@
-use axum::{response::Json};
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::StatusCode,
+    response::Json,
+};
 use serde_json::json;
+use sqlx::{Pool, Postgres};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use crate::{
+    db::queries::pending_migrations,
+    middleware::auth::check_signing_key,
+    models::health::{ComponentCheck, ComponentStatus, Readiness, ReadinessStatus},
+    state::Draining,
+    storage::BlobStore,
+};
 
 /// Health check endpoint
 /// 
-/// Returns a simple status check to verify the API is running.
+/// Returns a simple status check to verify the API is running. It checks no
+/// dependencies; probes should use `/health/live` and `/health/ready`.
 #[utoipa::path(
     get,
     path = "/health",
@@ -17,4 +38,316 @@ pub async fn health_check() -> Json<serde_json::Value> {
         "status": "healthy",
         "timestamp": chrono::Utc::now().to_rfc3339()
     }))
-} 
\ No newline at end of file
+}
+
+/// Longest a single readiness check may take before the component counts as down
+const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+/// Storage key prefix of readiness probe objects, removed right after writing
+const PROBE_PREFIX: &str = "health";
+/// How long readiness results are reused, so frequent probes of the public
+/// endpoint do not query the database and write to storage every time
+const DEFAULT_READINESS_TTL: Duration = Duration::from_secs(5);
+
+type Checks = BTreeMap<String, ComponentCheck>;
+
+/// The latest readiness checks, reused until they are older than the TTL.
+/// Probes arriving while the checks run wait for that run instead of
+/// starting their own.
+#[derive(Clone)]
+pub struct ReadinessCache {
+    latest: Arc<Mutex<Option<(Instant, Checks)>>>,
+    ttl: Duration,
+}
+
+impl Default for ReadinessCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_READINESS_TTL)
+    }
+}
+
+impl ReadinessCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            latest: Arc::default(),
+            ttl,
+        }
+    }
+
+    async fn get_or_run<F>(&self, run: impl FnOnce() -> F) -> Checks
+    where
+        F: Future<Output = Checks>,
+    {
+        let mut latest = self.latest.lock().await;
+        if let Some((checked_at, checks)) = latest.as_ref() {
+            if checked_at.elapsed() < self.ttl {
+                return checks.clone();
+            }
+        }
+        let checks = run().await;
+        *latest = Some((Instant::now(), checks.clone()));
+        checks
+    }
+}
+
+/// Liveness probe
+///
+/// Succeeds while the process can serve requests at all, including while
+/// draining; it checks no dependencies, so an outage elsewhere never gets the
+/// instance restarted.
+#[utoipa::path(
+    get,
+    path = "/health/live",
+    responses(
+        (status = 200, description = "Process is alive", example = json!({"status": "alive"}))
+    ),
+    tag = "Health"
+)]
+pub async fn live() -> Json<serde_json::Value> {
+    Json(json!({ "status": "alive" }))
+}
+
+/// Readiness probe
+///
+/// Checks database connectivity, pending migrations, storage writability and
+/// the token signing key, reporting each component's status and latency.
+/// Results are reused for a few seconds. Returns 503 when any check fails or
+/// the instance is draining for shutdown.
+#[utoipa::path(
+    get,
+    path = "/health/ready",
+    responses(
+        (status = 200, description = "Ready for traffic", body = Readiness),
+        (status = 503, description = "A dependency is down, or the instance is draining", body = Readiness)
+    ),
+    tag = "Health"
+)]
+pub async fn ready(
+    State(pool): State<Pool<Postgres>>,
+    State(blobs): State<Arc<dyn BlobStore>>,
+    State(draining): State<Draining>,
+    State(cache): State<ReadinessCache>,
+) -> (StatusCode, Json<Readiness>) {
+    if draining.is_draining() {
+        let readiness = Readiness {
+            status: ReadinessStatus::Draining,
+            checks: BTreeMap::new(),
+        };
+        return (StatusCode::SERVICE_UNAVAILABLE, Json(readiness));
+    }
+
+    let checks = cache
+        .get_or_run(|| async {
+            let (database, migrations, storage, signing_key) = tokio::join!(
+                timed("database", check_database(&pool)),
+                timed("migrations", check_migrations(&pool)),
+                timed("storage", check_storage(blobs.as_ref())),
+                timed("signing_key", async { check_signing_key() }),
+            );
+            BTreeMap::from([
+                ("database".to_string(), database),
+                ("migrations".to_string(), migrations),
+                ("storage".to_string(), storage),
+                ("signing_key".to_string(), signing_key),
+            ])
+        })
+        .await;
+
+    if checks.values().all(|check| check.status == ComponentStatus::Up) {
+        let readiness = Readiness { status: ReadinessStatus::Ready, checks };
+        (StatusCode::OK, Json(readiness))
+    } else {
+        let readiness = Readiness { status: ReadinessStatus::NotReady, checks };
+        (StatusCode::SERVICE_UNAVAILABLE, Json(readiness))
+    }
+}
+
+/// Runs a check under `CHECK_TIMEOUT`. Failure details are logged rather than
+/// returned, since the probe is public.
+async fn timed(
+    component: &str,
+    check: impl Future<Output = Result<(), String>>,
+) -> ComponentCheck {
+    let started = Instant::now();
+    let outcome = tokio::time::timeout(CHECK_TIMEOUT, check)
+        .await
+        .unwrap_or_else(|_| Err("Timed out".to_string()));
+    let latency_ms = started.elapsed().as_millis() as u64;
+
+    match outcome {
+        Ok(()) => ComponentCheck {
+            status: ComponentStatus::Up,
+            latency_ms,
+            error: None,
+        },
+        Err(e) => {
+            tracing::warn!(component, error = %e, "Readiness check failed");
+            ComponentCheck {
+                status: ComponentStatus::Down,
+                latency_ms,
+                error: Some(format!("{} check failed", component)),
+            }
+        }
+    }
+}
+
+async fn check_database(pool: &Pool<Postgres>) -> Result<(), String> {
+    sqlx::query("SELECT 1")
+        .execute(pool)
+        .await
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+async fn check_migrations(pool: &Pool<Postgres>) -> Result<(), String> {
+    let pending = pending_migrations(pool).await.map_err(|e| e.to_string())?;
+    if pending.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("Pending migrations: {:?}", pending))
+    }
+}
+
+/// Writes and removes a probe object; the photo sweep never looks under its prefix
+async fn check_storage(blobs: &dyn BlobStore) -> Result<(), String> {
+    let key = format!("{}/{}", PROBE_PREFIX, Uuid::new_v4());
+    blobs.put(&key, Bytes::from_static(b"ok"), "text/plain").await?;
+    blobs.delete(&key).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::config::init_for_tests, storage::local::LocalBlobStore, test_support::ScratchDir,
+    };
+    use sqlx::postgres::PgPoolOptions;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    async fn probe(
+        pool: &Pool<Postgres>,
+        blobs: &ScratchDir,
+        draining: &Draining,
+    ) -> (StatusCode, serde_json::Value) {
+        init_for_tests();
+        let (status, Json(readiness)) = ready(
+            State(pool.clone()),
+            State(Arc::new(LocalBlobStore::new(blobs.path())) as Arc<dyn BlobStore>),
+            State(draining.clone()),
+            State(ReadinessCache::new(Duration::ZERO)),
+        )
+        .await;
+        (status, serde_json::to_value(readiness).unwrap())
+    }
+
+    /// A pool whose connections are refused, so database checks fail fast
+    fn unreachable_pool() -> Pool<Postgres> {
+        PgPoolOptions::new()
+            .acquire_timeout(Duration::from_millis(200))
+            .connect_lazy("postgres://postgres@127.0.0.1:1/postgres")
+            .unwrap()
+    }
+
+    async fn run(cache: &ReadinessCache, runs: &AtomicUsize) -> Checks {
+        cache
+            .get_or_run(|| async {
+                runs.fetch_add(1, Ordering::SeqCst);
+                BTreeMap::from([(
+                    "database".to_string(),
+                    ComponentCheck {
+                        status: ComponentStatus::Up,
+                        latency_ms: 1,
+                        error: None,
+                    },
+                )])
+            })
+            .await
+    }
+
+    #[tokio::test]
+    async fn reuses_fresh_results() {
+        let cache = ReadinessCache::default();
+        let runs = AtomicUsize::new(0);
+
+        run(&cache, &runs).await;
+        let checks = run(&cache, &runs).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+        assert_eq!(checks["database"].status, ComponentStatus::Up);
+    }
+
+    #[tokio::test]
+    async fn concurrent_probes_share_one_run() {
+        let cache = ReadinessCache::default();
+        let runs = AtomicUsize::new(0);
+
+        tokio::join!(run(&cache, &runs), run(&cache, &runs), run(&cache, &runs));
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn reruns_expired_results() {
+        let cache = ReadinessCache::new(Duration::ZERO);
+        let runs = AtomicUsize::new(0);
+
+        run(&cache, &runs).await;
+        run(&cache, &runs).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn draining_turns_traffic_away_without_checking() {
+        let blobs = ScratchDir::new();
+        let draining = Draining::default();
+        draining.start();
+
+        let (status, readiness) = probe(&unreachable_pool(), &blobs, &draining).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(readiness["status"], "draining");
+        assert_eq!(readiness["checks"], json!({}));
+    }
+
+    #[tokio::test]
+    async fn a_failing_component_makes_the_instance_unready() {
+        let blobs = ScratchDir::new();
+
+        let (status, readiness) = probe(&unreachable_pool(), &blobs, &Draining::default()).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(readiness["status"], "not_ready");
+        assert_eq!(readiness["checks"]["database"]["status"], "down");
+        // The probe is public, so the cause is only logged
+        assert_eq!(readiness["checks"]["database"]["error"], "database check failed");
+        assert_eq!(readiness["checks"]["migrations"]["status"], "down");
+        assert_eq!(readiness["checks"]["storage"]["status"], "up");
+        assert_eq!(readiness["checks"]["signing_key"]["status"], "up");
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn checks_the_database_migrations_and_storage(pool: Pool<Postgres>) {
+        let blobs = ScratchDir::new();
+        let draining = Draining::default();
+
+        let (status, readiness) = probe(&pool, &blobs, &draining).await;
+        assert_eq!(status, StatusCode::OK, "{}", readiness);
+        assert_eq!(readiness["status"], "ready");
+        let components: Vec<_> = readiness["checks"].as_object().unwrap().keys().cloned().collect();
+        assert_eq!(components, ["database", "migrations", "signing_key", "storage"]);
+        // The storage probe cleans up after itself
+        assert_eq!(std::fs::read_dir(blobs.path().join(PROBE_PREFIX)).unwrap().count(), 0);
+
+        // A migration the database has not applied
+        sqlx::query("DELETE FROM _sqlx_migrations WHERE version = (SELECT MAX(version) FROM _sqlx_migrations)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let (status, readiness) = probe(&pool, &blobs, &draining).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(readiness["checks"]["migrations"]["status"], "down");
+        assert_eq!(readiness["checks"]["database"]["status"], "up");
+
+        // Storage rooted at a file cannot be written
+        let file = ScratchDir::new();
+        std::fs::write(file.path().join(PROBE_PREFIX), b"").unwrap();
+        let (_, readiness) = probe(&pool, &file, &draining).await;
+        assert_eq!(readiness["checks"]["storage"]["status"], "down");
+    }
+}
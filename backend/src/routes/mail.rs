@@ -0,0 +1,90 @@
+use axum::{
+    extract::{Path, Query},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+
+use crate::{
+    mail::templates::{render, resolve_locale, sample_context, LOCALES},
+    models::mail::{EmailPreview, MailTemplate, MailTemplateInfo, PreviewFormat, PreviewQuery},
+};
+
+/// List email templates
+///
+/// Returns every transactional email template and the locales it is translated to.
+#[utoipa::path(
+    get,
+    path = "/api/admin/mail/templates",
+    responses(
+        (status = 200, description = "Templates retrieved successfully", body = [MailTemplateInfo]),
+        (status = 401, description = "No token provided or invalid token"),
+        (status = 403, description = "Requires admin access")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Admin"
+)]
+pub async fn get_mail_templates() -> Json<Vec<MailTemplateInfo>> {
+    Json(
+        MailTemplate::ALL
+            .into_iter()
+            .map(|template| MailTemplateInfo {
+                template,
+                locales: LOCALES.iter().map(|locale| locale.to_string()).collect(),
+            })
+            .collect(),
+    )
+}
+
+/// Preview an email template
+///
+/// Renders the template with sample data. Unsupported locales fall back to the
+/// default one; `format=html` returns the HTML body for viewing in a browser.
+#[utoipa::path(
+    get,
+    path = "/api/admin/mail/templates/{template}/preview",
+    params(
+        ("template" = MailTemplate, Path, description = "Template name"),
+        PreviewQuery
+    ),
+    responses(
+        (status = 200, description = "Rendered template", body = EmailPreview),
+        (status = 401, description = "No token provided or invalid token"),
+        (status = 403, description = "Requires admin access")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Admin"
+)]
+pub async fn preview_mail_template(
+    Path(template): Path<MailTemplate>,
+    Query(query): Query<PreviewQuery>,
+) -> Result<Response, (StatusCode, Json<String>)> {
+    let locale = resolve_locale(query.locale.as_deref());
+    let rendered = render(template, locale, &sample_context(template))
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(e)))?;
+
+    Ok(match query.format {
+        PreviewFormat::Json => Json(EmailPreview {
+            template,
+            locale: locale.to_string(),
+            subject: rendered.subject,
+            text: rendered.text,
+            html: rendered.html,
+        })
+        .into_response(),
+        PreviewFormat::Html => (
+            [(header::CONTENT_TYPE, "text/html; charset=utf-8")],
+            rendered.html,
+        )
+            .into_response(),
+        PreviewFormat::Text => (
+            [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+            rendered.text,
+        )
+            .into_response(),
+    })
+}
@@ -0,0 +1,92 @@
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use metrics_exporter_prometheus::PrometheusHandle;
+use sqlx::{Pool, Postgres};
+use subtle::ConstantTimeEq;
+
+use crate::{
+    config::config::{app_config, Secret},
+    telemetry::metrics::record_pool,
+};
+
+/// Whether the request carries the metrics bearer `token`, compared in
+/// constant time. Without a configured token nothing is authorized.
+fn authorized(headers: &HeaderMap, token: Option<&Secret>) -> bool {
+    let Some(token) = token.filter(|token| !token.expose().is_empty()) else {
+        return false;
+    };
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .is_some_and(|sent| bool::from(sent.as_bytes().ct_eq(token.expose().as_bytes())))
+}
+
+/// Prometheus metrics
+///
+/// Request counts and latencies per route, login, registration and token
+/// validation outcomes, password hashing latency and database pool usage, in
+/// the Prometheus text format. Scrapers authenticate with `metrics.bearer_token`.
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    responses(
+        (status = 200, description = "Metrics in the Prometheus text format", body = String),
+        (status = 401, description = "Missing or wrong metrics bearer token")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Health"
+)]
+pub async fn get_metrics(
+    State(handle): State<PrometheusHandle>,
+    State(pool): State<Pool<Postgres>>,
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, Json<String>)> {
+    if !authorized(&headers, app_config().metrics.bearer_token.as_ref()) {
+        return Err((StatusCode::UNAUTHORIZED, Json("Invalid metrics token".to_string())));
+    }
+
+    record_pool(&pool);
+    Ok((
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        handle.render(),
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bearer(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, value.parse().unwrap());
+        headers
+    }
+
+    fn secret(value: &str) -> Secret {
+        serde_json::from_value(serde_json::json!(value)).unwrap()
+    }
+
+    #[test]
+    fn requires_the_configured_token() {
+        let token = secret("scraper-token");
+        assert!(authorized(&bearer("Bearer scraper-token"), Some(&token)));
+        assert!(!authorized(&bearer("Bearer scraper-toke"), Some(&token)));
+        assert!(!authorized(&bearer("Bearer scraper-token2"), Some(&token)));
+        assert!(!authorized(&bearer("Basic scraper-token"), Some(&token)));
+        assert!(!authorized(&HeaderMap::new(), Some(&token)));
+    }
+
+    #[test]
+    fn refuses_everything_without_a_token() {
+        assert!(!authorized(&bearer("Bearer "), None));
+        assert!(!authorized(&bearer("Bearer "), Some(&secret(""))));
+    }
+}
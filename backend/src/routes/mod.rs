@@ -1,7 +1,33 @@
 pub mod auth;
+pub mod forward_auth;
 pub mod protected;
 
 pub mod profile;
 
 pub mod health;
 
+pub mod roles;
+
+pub mod organizations;
+
+pub mod groups;
+
+pub mod authz;
+
+pub mod policies;
+
+pub mod users;
+
+pub mod audit;
+
+pub mod sessions;
+
+pub mod mail;
+
+pub mod uploads;
+
+pub mod avatars;
+
+pub mod photo_uploads;
+
+pub mod metrics;
@@ -0,0 +1,472 @@
+use axum::{
+    extract::{Extension, Path, State},
+    http::{header, HeaderMap, StatusCode},
+    Json,
+};
+use serde_json::json;
+use sqlx::{Pool, Postgres};
+use std::sync::Arc;
+use uuid::Uuid;
+use validator::Validate;
+use tracing::Instrument;
+
+use crate::{
+    config::config::app_config,
+    db::{
+        audit::record_event,
+        organizations::{
+            accept_invitation, create_invitation, create_organization, get_membership_role,
+            get_organization_name, list_members, list_pending_invitations,
+            list_user_organizations, remove_member, update_member_role, MembershipChange,
+            INVITATION_TTL_DAYS,
+        },
+    },
+    mail::{templates::render, Mailer},
+    middleware::{
+        auth::{create_token, Claims},
+        client::ClientInfo,
+    },
+    models::{
+        audit::AuditEventType,
+        mail::MailTemplate,
+        organization::{
+            ActiveOrganization, CreateOrganizationRequest, Invitation, InvitationRequest, Member,
+            MemberRoleRequest, Organization, OrganizationMembership, OrgRole,
+        },
+        user::{TokenResponse, User},
+    },
+};
+
+fn is_valid_slug(slug: &str) -> bool {
+    slug.chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+}
+
+/// Create an organization
+///
+/// Creates a new organization with the authenticated user as its Owner.
+#[utoipa::path(
+    post,
+    path = "/api/orgs",
+    request_body = CreateOrganizationRequest,
+    responses(
+        (status = 201, description = "Organization created successfully", body = Organization),
+        (status = 400, description = "Invalid input"),
+        (status = 401, description = "No token provided or invalid token"),
+        (status = 409, description = "Slug already taken")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Organizations"
+)]
+pub async fn create_org(
+    State(pool): State<Pool<Postgres>>,
+    Extension(user): Extension<User>,
+    Json(payload): Json<CreateOrganizationRequest>,
+) -> Result<(StatusCode, Json<Organization>), (StatusCode, Json<String>)> {
+    if let Err(e) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(format!("Validation error: {}", e)),
+        ));
+    }
+    if !is_valid_slug(&payload.slug) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json("Slug may only contain lowercase letters, digits and dashes".to_string()),
+        ));
+    }
+
+    let organization = create_organization(&pool, &payload.name, &payload.slug, user.id)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(db) if db.is_unique_violation() => (
+                StatusCode::CONFLICT,
+                Json("Organization slug already taken".to_string()),
+            ),
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json("Failed to create organization".to_string()),
+            ),
+        })?;
+
+    Ok((StatusCode::CREATED, Json(organization)))
+}
+
+/// List my organizations
+///
+/// Returns every organization the authenticated user belongs to, with their role in it.
+#[utoipa::path(
+    get,
+    path = "/api/orgs",
+    responses(
+        (status = 200, description = "Organizations retrieved successfully", body = [OrganizationMembership]),
+        (status = 401, description = "No token provided or invalid token")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Organizations"
+)]
+pub async fn list_my_orgs(
+    State(pool): State<Pool<Postgres>>,
+    Extension(user): Extension<User>,
+) -> Result<Json<Vec<OrganizationMembership>>, (StatusCode, Json<String>)> {
+    let organizations = list_user_organizations(&pool, user.id)
+        .await
+        .map_err(|_| (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json("Failed to fetch organizations".to_string()),
+        ))?;
+
+    Ok(Json(organizations))
+}
+
+/// Switch active organization
+///
+/// Returns a new token whose `org` claim selects the given organization.
+/// Organization-scoped endpoints operate on this organization only.
+#[utoipa::path(
+    post,
+    path = "/api/orgs/{id}/switch",
+    params(
+        ("id" = i32, Path, description = "Organization id")
+    ),
+    responses(
+        (status = 200, description = "Token issued for the organization", body = TokenResponse),
+        (status = 401, description = "No token provided or invalid token"),
+        (status = 403, description = "Not a member of the organization")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Organizations"
+)]
+pub async fn switch_org(
+    State(pool): State<Pool<Postgres>>,
+    Extension(user): Extension<User>,
+    Extension(claims): Extension<Claims>,
+    Path(organization_id): Path<i32>,
+) -> Result<Json<TokenResponse>, (StatusCode, Json<String>)> {
+    let membership = get_membership_role(&pool, organization_id, user.id)
+        .await
+        .map_err(|_| (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json("Failed to resolve organization membership".to_string()),
+        ))?;
+
+    if membership.is_none() {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json("Not a member of this organization".to_string()),
+        ));
+    }
+
+    let token = create_token(
+        &user.email,
+        &user.role,
+        Some(organization_id),
+        claims.scope.as_deref(),
+        claims.sid,
+    );
+    Ok(Json(TokenResponse { token }))
+}
+
+/// List members of the active organization
+#[utoipa::path(
+    get,
+    path = "/api/org/members",
+    responses(
+        (status = 200, description = "Members retrieved successfully", body = [Member]),
+        (status = 401, description = "No token provided or invalid token"),
+        (status = 403, description = "No active organization or not a member")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Organizations"
+)]
+pub async fn get_members(
+    State(pool): State<Pool<Postgres>>,
+    Extension(org): Extension<ActiveOrganization>,
+) -> Result<Json<Vec<Member>>, (StatusCode, Json<String>)> {
+    let members = list_members(&pool, org.id)
+        .await
+        .map_err(|_| (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json("Failed to fetch members".to_string()),
+        ))?;
+
+    Ok(Json(members))
+}
+
+/// Maps a refused membership change to its response
+fn membership_change_result(change: MembershipChange) -> Result<OrgRole, (StatusCode, Json<String>)> {
+    match change {
+        MembershipChange::Applied(previous) => Ok(previous),
+        MembershipChange::NotMember => Err((StatusCode::NOT_FOUND, Json("Member not found".to_string()))),
+        MembershipChange::OwnerRequired => Err((
+            StatusCode::FORBIDDEN,
+            Json("Only owners can grant or revoke the Owner role".to_string()),
+        )),
+        MembershipChange::LastOwner => Err((
+            StatusCode::BAD_REQUEST,
+            Json("An organization must keep at least one owner".to_string()),
+        )),
+    }
+}
+
+/// Change a member's role in the active organization
+#[utoipa::path(
+    put,
+    path = "/api/org/members/{user_id}",
+    params(
+        ("user_id" = i32, Path, description = "Member user id")
+    ),
+    request_body = MemberRoleRequest,
+    responses(
+        (status = 204, description = "Role updated"),
+        (status = 400, description = "Would leave the organization without an owner"),
+        (status = 403, description = "Requires organization Admin role"),
+        (status = 404, description = "Member not found")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Organizations"
+)]
+pub async fn put_member_role(
+    State(pool): State<Pool<Postgres>>,
+    Extension(org): Extension<ActiveOrganization>,
+    Extension(admin): Extension<User>,
+    client: ClientInfo,
+    Path(user_id): Path<i32>,
+    Json(payload): Json<MemberRoleRequest>,
+) -> Result<StatusCode, (StatusCode, Json<String>)> {
+    let change = update_member_role(&pool, org.id, admin.id, user_id, payload.role)
+        .await
+        .map_err(|_| (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json("Failed to update member role".to_string()),
+        ))?;
+    let current = membership_change_result(change)?;
+    record_event(
+        &pool,
+        &client.audit(Some(admin.id)),
+        AuditEventType::RoleChanged,
+        Some(user_id),
+        json!({
+            "action": "change_org_role",
+            "organization_id": org.id,
+            "from": current,
+            "to": payload.role,
+        }),
+    )
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Remove a member from the active organization
+#[utoipa::path(
+    delete,
+    path = "/api/org/members/{user_id}",
+    params(
+        ("user_id" = i32, Path, description = "Member user id")
+    ),
+    responses(
+        (status = 204, description = "Member removed"),
+        (status = 400, description = "Would leave the organization without an owner"),
+        (status = 403, description = "Requires organization Admin role"),
+        (status = 404, description = "Member not found")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Organizations"
+)]
+pub async fn delete_member(
+    State(pool): State<Pool<Postgres>>,
+    Extension(org): Extension<ActiveOrganization>,
+    Extension(admin): Extension<User>,
+    client: ClientInfo,
+    Path(user_id): Path<i32>,
+) -> Result<StatusCode, (StatusCode, Json<String>)> {
+    let change = remove_member(&pool, org.id, admin.id, user_id)
+        .await
+        .map_err(|_| (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json("Failed to remove member".to_string()),
+        ))?;
+    let current = membership_change_result(change)?;
+    record_event(
+        &pool,
+        &client.audit(Some(admin.id)),
+        AuditEventType::RoleChanged,
+        Some(user_id),
+        json!({ "action": "remove_org_member", "organization_id": org.id, "from": current }),
+    )
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Emails an invitation link, in the inviter's language, to the invitee
+async fn send_invitation(
+    pool: &Pool<Postgres>,
+    mailer: &dyn Mailer,
+    invitation: &Invitation,
+    inviter: &User,
+    locale: &str,
+) -> Result<(), String> {
+    let organization = get_organization_name(pool, invitation.organization_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    let email = render(
+        MailTemplate::Invitation,
+        locale,
+        &json!({
+            "inviter": format!("{} {}", inviter.firstname, inviter.lastname),
+            "organization": organization,
+            "role": invitation.role,
+            "accept_url": format!("{}/invitations/{}", app_config().server.frontend_url, invitation.token),
+            "expires_days": INVITATION_TTL_DAYS,
+        }),
+    )?;
+    mailer.send(&email.addressed_to(&invitation.email)).await
+}
+
+/// Invite someone to the active organization
+///
+/// The invitee is emailed a link to accept while logged in with the invited
+/// email address; the returned token can also be shared directly.
+#[utoipa::path(
+    post,
+    path = "/api/org/invitations",
+    request_body = InvitationRequest,
+    responses(
+        (status = 201, description = "Invitation created", body = Invitation),
+        (status = 400, description = "Invalid input"),
+        (status = 403, description = "Requires organization Admin role")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Organizations"
+)]
+pub async fn invite(
+    State(pool): State<Pool<Postgres>>,
+    State(mailer): State<Arc<dyn Mailer>>,
+    Extension(org): Extension<ActiveOrganization>,
+    Extension(user): Extension<User>,
+    headers: HeaderMap,
+    Json(payload): Json<InvitationRequest>,
+) -> Result<(StatusCode, Json<Invitation>), (StatusCode, Json<String>)> {
+    if let Err(e) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(format!("Validation error: {}", e)),
+        ));
+    }
+    if payload.role == OrgRole::Owner && org.role != OrgRole::Owner {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json("Only owners can grant or revoke the Owner role".to_string()),
+        ));
+    }
+
+    let invitation = create_invitation(&pool, org.id, &payload.email, payload.role, user.id)
+        .await
+        .map_err(|_| (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json("Failed to create invitation".to_string()),
+        ))?;
+
+    let locale = headers
+        .get(header::ACCEPT_LANGUAGE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    let sent = invitation.clone();
+    tokio::spawn(async move {
+        if let Err(e) = send_invitation(&pool, mailer.as_ref(), &sent, &user, &locale).await {
+            tracing::error!(invitation_id = sent.id, error = %e, "Failed to email invitation");
+        }
+    }.in_current_span());
+
+    Ok((StatusCode::CREATED, Json(invitation)))
+}
+
+/// List pending invitations of the active organization
+#[utoipa::path(
+    get,
+    path = "/api/org/invitations",
+    responses(
+        (status = 200, description = "Pending invitations", body = [Invitation]),
+        (status = 403, description = "Requires organization Admin role")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Organizations"
+)]
+pub async fn get_invitations(
+    State(pool): State<Pool<Postgres>>,
+    Extension(org): Extension<ActiveOrganization>,
+) -> Result<Json<Vec<Invitation>>, (StatusCode, Json<String>)> {
+    let invitations = list_pending_invitations(&pool, org.id)
+        .await
+        .map_err(|_| (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json("Failed to fetch invitations".to_string()),
+        ))?;
+
+    Ok(Json(invitations))
+}
+
+/// Accept an invitation
+///
+/// Joins the organization and returns a token with it selected as the active organization.
+#[utoipa::path(
+    post,
+    path = "/api/invitations/{token}/accept",
+    params(
+        ("token" = String, Path, description = "Invitation token")
+    ),
+    responses(
+        (status = 200, description = "Invitation accepted", body = TokenResponse),
+        (status = 401, description = "No token provided or invalid token"),
+        (status = 404, description = "Invitation not found, expired or addressed to another email")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Organizations"
+)]
+pub async fn accept(
+    State(pool): State<Pool<Postgres>>,
+    Extension(user): Extension<User>,
+    Extension(claims): Extension<Claims>,
+    Path(token): Path<Uuid>,
+) -> Result<Json<TokenResponse>, (StatusCode, Json<String>)> {
+    let organization_id = accept_invitation(&pool, token, user.id, &user.email)
+        .await
+        .map_err(|_| (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json("Failed to accept invitation".to_string()),
+        ))?
+        .ok_or((
+            StatusCode::NOT_FOUND,
+            Json("Invitation not found or expired".to_string()),
+        ))?;
+
+    let token = create_token(
+        &user.email,
+        &user.role,
+        Some(organization_id),
+        claims.scope.as_deref(),
+        claims.sid,
+    );
+    Ok(Json(TokenResponse { token }))
+}
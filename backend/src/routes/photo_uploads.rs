@@ -0,0 +1,610 @@
+use axum::{
+    body::Bytes,
+    extract::{Extension, Path, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use chrono::{Duration, Utc};
+use sha2::{Digest, Sha256};
+use sqlx::{Pool, Postgres};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::{
+    db::photo_uploads::{
+        append_photo_upload_chunk, count_pending_photo_uploads, create_photo_upload,
+        get_photo_upload, take_photo_upload,
+    },
+    imaging::MAX_PHOTO_BYTES,
+    models::{
+        photo_upload::{CreatePhotoUploadRequest, PhotoUpload},
+        user::User,
+    },
+    routes::profile::save_photo,
+    storage::{photos::STAGING_PREFIX, BlobStore},
+};
+
+/// Largest chunk accepted by a single PATCH
+pub const MAX_CHUNK_BYTES: usize = 2 * 1024 * 1024;
+/// How long an upload may take before it is discarded
+const UPLOAD_TTL_HOURS: i64 = 24;
+/// Open uploads per user, so abandoned ones cannot pile up staged data
+const MAX_PENDING_UPLOADS: i64 = 5;
+
+fn not_found() -> (StatusCode, Json<String>) {
+    (StatusCode::NOT_FOUND, Json("Upload not found".to_string()))
+}
+
+fn internal_error(message: &str) -> (StatusCode, Json<String>) {
+    (StatusCode::INTERNAL_SERVER_ERROR, Json(message.to_string()))
+}
+
+fn is_sha256_hex(value: &str) -> bool {
+    value.len() == 64 && value.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Deletes staged chunks, logging failures
+async fn discard_chunks(blobs: &dyn BlobStore, keys: &[String]) {
+    for key in keys {
+        if let Err(e) = blobs.delete(key).await {
+            tracing::warn!(error = %e, "Failed to delete upload chunk");
+        }
+    }
+}
+
+/// Start a resumable photo upload
+///
+/// Declares the photo's size and SHA-256; its bytes are then sent in order
+/// with PATCH requests and the upload completed once all have arrived.
+/// Incomplete uploads expire after a day.
+#[utoipa::path(
+    post,
+    path = "/api/profile/photo/uploads",
+    request_body = CreatePhotoUploadRequest,
+    responses(
+        (status = 201, description = "Upload started", body = PhotoUpload),
+        (status = 400, description = "Invalid size or checksum"),
+        (status = 401, description = "Unauthorized - Invalid or missing token"),
+        (status = 429, description = "Too many uploads in progress")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Profile"
+)]
+pub async fn create_upload(
+    State(pool): State<Pool<Postgres>>,
+    Extension(user): Extension<User>,
+    Json(payload): Json<CreatePhotoUploadRequest>,
+) -> Result<(StatusCode, Json<PhotoUpload>), (StatusCode, Json<String>)> {
+    if payload.size <= 0 || payload.size > MAX_PHOTO_BYTES as i64 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(format!("size must be between 1 and {} bytes", MAX_PHOTO_BYTES)),
+        ));
+    }
+    if !is_sha256_hex(&payload.sha256) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json("sha256 must be 64 hex characters".to_string()),
+        ));
+    }
+
+    let pending = count_pending_photo_uploads(&pool, user.id)
+        .await
+        .map_err(|_| internal_error("Failed to start upload"))?;
+    if pending >= MAX_PENDING_UPLOADS {
+        return Err((
+            StatusCode::TOO_MANY_REQUESTS,
+            Json("Too many uploads in progress".to_string()),
+        ));
+    }
+
+    let expires_at = Utc::now() + Duration::hours(UPLOAD_TTL_HOURS);
+    let sha256 = payload.sha256.to_lowercase();
+    create_photo_upload(&pool, user.id, payload.size, &sha256, expires_at)
+        .await
+        .map(|upload| (StatusCode::CREATED, Json(upload)))
+        .map_err(|_| internal_error("Failed to start upload"))
+}
+
+/// Get a resumable photo upload
+///
+/// Returns how many bytes were received, which is where a resumed upload continues.
+#[utoipa::path(
+    get,
+    path = "/api/profile/photo/uploads/{id}",
+    params(
+        ("id" = Uuid, Path, description = "Upload id")
+    ),
+    responses(
+        (status = 200, description = "Upload state", body = PhotoUpload),
+        (status = 401, description = "Unauthorized - Invalid or missing token"),
+        (status = 404, description = "Upload not found or expired")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Profile"
+)]
+pub async fn get_upload_state(
+    State(pool): State<Pool<Postgres>>,
+    Extension(user): Extension<User>,
+    Path(upload_id): Path<Uuid>,
+) -> Result<Json<PhotoUpload>, (StatusCode, Json<String>)> {
+    get_photo_upload(&pool, user.id, upload_id)
+        .await
+        .map_err(|_| internal_error("Failed to fetch upload"))?
+        .map(Json)
+        .ok_or_else(not_found)
+}
+
+/// Send a chunk of a resumable photo upload
+///
+/// The raw bytes in the body must start at the `Upload-Offset` header, which
+/// has to equal the bytes received so far. An optional `Upload-Checksum:
+/// sha256 <hex>` header is verified against the chunk.
+#[utoipa::path(
+    patch,
+    path = "/api/profile/photo/uploads/{id}",
+    params(
+        ("id" = Uuid, Path, description = "Upload id"),
+        ("Upload-Offset" = i64, Header, description = "Offset of the chunk within the photo"),
+        ("Upload-Checksum" = Option<String>, Header, description = "`sha256 <hex>` of the chunk")
+    ),
+    request_body(content = Vec<u8>, content_type = "application/offset+octet-stream"),
+    responses(
+        (status = 200, description = "Chunk stored", body = PhotoUpload),
+        (status = 400, description = "Missing offset, empty chunk or checksum mismatch"),
+        (status = 401, description = "Unauthorized - Invalid or missing token"),
+        (status = 404, description = "Upload not found or expired"),
+        (status = 409, description = "Offset does not match the bytes received"),
+        (status = 413, description = "Chunk too large, or past the declared size")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Profile"
+)]
+pub async fn patch_upload(
+    State(pool): State<Pool<Postgres>>,
+    State(blobs): State<Arc<dyn BlobStore>>,
+    Extension(user): Extension<User>,
+    Path(upload_id): Path<Uuid>,
+    headers: HeaderMap,
+    chunk: Bytes,
+) -> Result<Json<PhotoUpload>, (StatusCode, Json<String>)> {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+    let offset = header("upload-offset")
+        .and_then(|v| v.parse::<i64>().ok())
+        .ok_or((
+            StatusCode::BAD_REQUEST,
+            Json("Upload-Offset header is required".to_string()),
+        ))?;
+    if chunk.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, Json("Chunk is empty".to_string())));
+    }
+    if let Some(checksum) = header("upload-checksum") {
+        let expected = checksum
+            .strip_prefix("sha256 ")
+            .filter(|hex| is_sha256_hex(hex))
+            .ok_or((
+                StatusCode::BAD_REQUEST,
+                Json("Upload-Checksum must be `sha256 <hex>`".to_string()),
+            ))?;
+        if !hex::encode(Sha256::digest(&chunk)).eq_ignore_ascii_case(expected) {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json("Chunk checksum mismatch".to_string()),
+            ));
+        }
+    }
+
+    let upload = get_photo_upload(&pool, user.id, upload_id)
+        .await
+        .map_err(|_| internal_error("Failed to fetch upload"))?
+        .ok_or_else(not_found)?;
+    if offset != upload.received {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(format!("Upload-Offset must be {}", upload.received)),
+        ));
+    }
+    let length = chunk.len() as i64;
+    if offset + length > upload.size {
+        return Err((
+            StatusCode::PAYLOAD_TOO_LARGE,
+            Json(format!("Chunk exceeds the declared size of {} bytes", upload.size)),
+        ));
+    }
+
+    // A unique key per attempt, so a concurrent retry at the same offset cannot overwrite it
+    let key = format!("{}/{}/{:012}-{}", STAGING_PREFIX, upload_id, offset, Uuid::new_v4());
+    blobs
+        .put(&key, chunk, "application/octet-stream")
+        .await
+        .map_err(|e| {
+            tracing::error!(error = %e, "Failed to stage upload chunk");
+            internal_error("Failed to store chunk")
+        })?;
+
+    match append_photo_upload_chunk(&pool, user.id, upload_id, offset, length, &key).await {
+        Ok(Some(upload)) => Ok(Json(upload)),
+        Ok(None) => {
+            discard_chunks(blobs.as_ref(), &[key]).await;
+            Err((
+                StatusCode::CONFLICT,
+                Json("Upload-Offset no longer matches the bytes received".to_string()),
+            ))
+        }
+        Err(_) => {
+            discard_chunks(blobs.as_ref(), &[key]).await;
+            Err(internal_error("Failed to store chunk"))
+        }
+    }
+}
+
+/// Complete a resumable photo upload
+///
+/// Verifies the assembled photo against the declared SHA-256 and makes it the
+/// profile photo, exactly like a direct upload.
+#[utoipa::path(
+    post,
+    path = "/api/profile/photo/uploads/{id}/complete",
+    params(
+        ("id" = Uuid, Path, description = "Upload id")
+    ),
+    responses(
+        (status = 200, description = "Photo uploaded successfully"),
+        (status = 400, description = "Not a supported image, or too small"),
+        (status = 401, description = "Unauthorized - Invalid or missing token"),
+        (status = 404, description = "Upload not found or expired"),
+        (status = 409, description = "Not every byte has been received"),
+        (status = 422, description = "Checksum mismatch; the upload is discarded")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Profile"
+)]
+pub async fn complete_upload(
+    State(pool): State<Pool<Postgres>>,
+    State(blobs): State<Arc<dyn BlobStore>>,
+    Extension(user): Extension<User>,
+    Path(upload_id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<String>)> {
+    let pending = get_photo_upload(&pool, user.id, upload_id)
+        .await
+        .map_err(|_| internal_error("Failed to fetch upload"))?
+        .ok_or_else(not_found)?;
+    if pending.received < pending.size {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(format!("Received {} of {} bytes", pending.received, pending.size)),
+        ));
+    }
+
+    let (upload, keys) = take_photo_upload(&pool, user.id, upload_id, true)
+        .await
+        .map_err(|_| internal_error("Failed to complete upload"))?
+        .ok_or_else(not_found)?;
+
+    let mut data = Vec::with_capacity(upload.size as usize);
+    for key in &keys {
+        match blobs.get(key).await {
+            Ok(Some(chunk)) => data.extend_from_slice(&chunk.data),
+            Ok(None) | Err(_) => {
+                discard_chunks(blobs.as_ref(), &keys).await;
+                return Err(internal_error("Failed to read uploaded chunks"));
+            }
+        }
+    }
+    discard_chunks(blobs.as_ref(), &keys).await;
+
+    if data.len() as i64 != upload.size || hex::encode(Sha256::digest(&data)) != upload.sha256 {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json("Checksum mismatch; start a new upload".to_string()),
+        ));
+    }
+    save_photo(&pool, blobs.as_ref(), &user, Bytes::from(data)).await
+}
+
+/// Abort a resumable photo upload
+#[utoipa::path(
+    delete,
+    path = "/api/profile/photo/uploads/{id}",
+    params(
+        ("id" = Uuid, Path, description = "Upload id")
+    ),
+    responses(
+        (status = 204, description = "Upload discarded"),
+        (status = 401, description = "Unauthorized - Invalid or missing token"),
+        (status = 404, description = "Upload not found")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Profile"
+)]
+pub async fn delete_upload(
+    State(pool): State<Pool<Postgres>>,
+    State(blobs): State<Arc<dyn BlobStore>>,
+    Extension(user): Extension<User>,
+    Path(upload_id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, Json<String>)> {
+    let (_, keys) = take_photo_upload(&pool, user.id, upload_id, false)
+        .await
+        .map_err(|_| internal_error("Failed to discard upload"))?
+        .ok_or_else(not_found)?;
+    discard_chunks(blobs.as_ref(), &keys).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::queries::get_user_by_id,
+        storage::{local::LocalBlobStore, photos::expire_photo_uploads},
+        test_support::{insert_user, ScratchDir},
+    };
+    use image::{codecs::png::PngEncoder, ExtendedColorType, ImageEncoder, Rgb, RgbImage};
+
+    struct Fixture {
+        pool: Pool<Postgres>,
+        blobs: Arc<dyn BlobStore>,
+        user: User,
+        _dir: ScratchDir,
+    }
+
+    async fn fixture(pool: Pool<Postgres>) -> Fixture {
+        let dir = ScratchDir::new();
+        let user = insert_user(&pool, "user@example.com", "User").await;
+        Fixture {
+            pool,
+            blobs: Arc::new(LocalBlobStore::new(dir.path())),
+            user,
+            _dir: dir,
+        }
+    }
+
+    fn photo() -> Vec<u8> {
+        let image = RgbImage::from_pixel(40, 40, Rgb([200, 80, 40]));
+        let mut out = Vec::new();
+        PngEncoder::new(&mut out)
+            .write_image(&image, 40, 40, ExtendedColorType::Rgb8)
+            .unwrap();
+        out
+    }
+
+    fn sha256(data: &[u8]) -> String {
+        hex::encode(Sha256::digest(data))
+    }
+
+    async fn start(f: &Fixture, data: &[u8]) -> Result<PhotoUpload, StatusCode> {
+        create_upload(
+            State(f.pool.clone()),
+            Extension(f.user.clone()),
+            Json(CreatePhotoUploadRequest {
+                size: data.len() as i64,
+                sha256: sha256(data),
+            }),
+        )
+        .await
+        .map(|(_, Json(upload))| upload)
+        .map_err(|(status, _)| status)
+    }
+
+    async fn send(
+        f: &Fixture,
+        upload_id: Uuid,
+        offset: usize,
+        chunk: &[u8],
+        checksum: Option<String>,
+    ) -> Result<PhotoUpload, StatusCode> {
+        let mut headers = HeaderMap::new();
+        headers.insert("upload-offset", offset.to_string().parse().unwrap());
+        if let Some(checksum) = checksum {
+            headers.insert("upload-checksum", checksum.parse().unwrap());
+        }
+        patch_upload(
+            State(f.pool.clone()),
+            State(f.blobs.clone()),
+            Extension(f.user.clone()),
+            Path(upload_id),
+            headers,
+            Bytes::copy_from_slice(chunk),
+        )
+        .await
+        .map(|Json(upload)| upload)
+        .map_err(|(status, _)| status)
+    }
+
+    async fn complete(f: &Fixture, upload_id: Uuid) -> Result<serde_json::Value, StatusCode> {
+        complete_upload(
+            State(f.pool.clone()),
+            State(f.blobs.clone()),
+            Extension(f.user.clone()),
+            Path(upload_id),
+        )
+        .await
+        .map(|Json(body)| body)
+        .map_err(|(status, _)| status)
+    }
+
+    async fn staged(f: &Fixture) -> usize {
+        f.blobs.list(&format!("{}/", STAGING_PREFIX)).await.unwrap().len()
+    }
+
+    #[test]
+    fn checks_sha256_hex() {
+        assert!(is_sha256_hex(&sha256(b"photo")));
+        assert!(is_sha256_hex(&sha256(b"photo").to_uppercase()));
+        assert!(!is_sha256_hex(&"0".repeat(63)));
+        assert!(!is_sha256_hex(&"g".repeat(64)));
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn chunks_complete_into_the_profile_photo(pool: Pool<Postgres>) {
+        let f = fixture(pool).await;
+        let data = photo();
+        let (first, second) = data.split_at(data.len() / 2);
+
+        let upload = start(&f, &data).await.unwrap();
+        let sent = send(&f, upload.id, 0, first, Some(format!("sha256 {}", sha256(first))))
+            .await
+            .unwrap();
+        assert_eq!(sent.received, first.len() as i64);
+
+        // A resumed client asks where to continue
+        let Json(state) = get_upload_state(State(f.pool.clone()), Extension(f.user.clone()), Path(upload.id))
+            .await
+            .unwrap();
+        assert_eq!(state.received, first.len() as i64);
+        assert_eq!(complete(&f, upload.id).await.unwrap_err(), StatusCode::CONFLICT);
+
+        send(&f, upload.id, first.len(), second, None).await.unwrap();
+        let body = complete(&f, upload.id).await.unwrap();
+
+        let user = get_user_by_id(&f.pool, f.user.id).await.unwrap();
+        assert_eq!(user.profile_picture.as_deref(), body["url"].as_str());
+        for variant in body["variants"].as_array().unwrap() {
+            let key = variant.as_str().unwrap().strip_prefix("/uploads/").unwrap();
+            assert!(f.blobs.get(key).await.unwrap().is_some());
+        }
+        assert_eq!(staged(&f).await, 0);
+        assert_eq!(complete(&f, upload.id).await.unwrap_err(), StatusCode::NOT_FOUND);
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn rejects_chunks_that_do_not_fit(pool: Pool<Postgres>) {
+        let f = fixture(pool).await;
+        let data = photo();
+        let upload = start(&f, &data).await.unwrap();
+
+        assert_eq!(send(&f, upload.id, 1, &data[1..], None).await.unwrap_err(), StatusCode::CONFLICT);
+        assert_eq!(
+            send(&f, upload.id, 0, &[data.as_slice(), b"x"].concat(), None).await.unwrap_err(),
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+        assert_eq!(
+            send(&f, upload.id, 0, &data, Some(format!("sha256 {}", sha256(b"other")))).await.unwrap_err(),
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            send(&f, upload.id, 0, &data, Some("md5 abc".to_string())).await.unwrap_err(),
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(send(&f, upload.id, 0, &[], None).await.unwrap_err(), StatusCode::BAD_REQUEST);
+        assert_eq!(send(&f, Uuid::new_v4(), 0, &data, None).await.unwrap_err(), StatusCode::NOT_FOUND);
+        assert_eq!(staged(&f).await, 0);
+
+        // A retry of an accepted chunk no longer matches the offset
+        send(&f, upload.id, 0, &data[..10], None).await.unwrap();
+        assert_eq!(send(&f, upload.id, 0, &data[..10], None).await.unwrap_err(), StatusCode::CONFLICT);
+        assert_eq!(staged(&f).await, 1);
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn checksum_mismatch_discards_the_upload(pool: Pool<Postgres>) {
+        let f = fixture(pool).await;
+        let data = photo();
+        let (_, Json(upload)) = create_upload(
+            State(f.pool.clone()),
+            Extension(f.user.clone()),
+            Json(CreatePhotoUploadRequest {
+                size: data.len() as i64,
+                sha256: sha256(b"something else"),
+            }),
+        )
+        .await
+        .unwrap();
+
+        send(&f, upload.id, 0, &data, None).await.unwrap();
+        assert_eq!(complete(&f, upload.id).await.unwrap_err(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(staged(&f).await, 0);
+        assert_eq!(complete(&f, upload.id).await.unwrap_err(), StatusCode::NOT_FOUND);
+        assert!(get_user_by_id(&f.pool, f.user.id).await.unwrap().profile_picture.is_none());
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn uploads_belong_to_their_user(pool: Pool<Postgres>) {
+        let f = fixture(pool).await;
+        let data = photo();
+        let upload = start(&f, &data).await.unwrap();
+        send(&f, upload.id, 0, &data[..10], None).await.unwrap();
+
+        let other = Fixture {
+            user: insert_user(&f.pool, "other@example.com", "User").await,
+            pool: f.pool.clone(),
+            blobs: f.blobs.clone(),
+            _dir: ScratchDir::new(),
+        };
+        assert_eq!(send(&other, upload.id, 10, &data[10..], None).await.unwrap_err(), StatusCode::NOT_FOUND);
+        assert_eq!(complete(&other, upload.id).await.unwrap_err(), StatusCode::NOT_FOUND);
+        let deleted = delete_upload(
+            State(other.pool.clone()),
+            State(other.blobs.clone()),
+            Extension(other.user.clone()),
+            Path(upload.id),
+        )
+        .await;
+        assert_eq!(deleted.unwrap_err().0, StatusCode::NOT_FOUND);
+
+        let deleted = delete_upload(
+            State(f.pool.clone()),
+            State(f.blobs.clone()),
+            Extension(f.user.clone()),
+            Path(upload.id),
+        )
+        .await;
+        assert_eq!(deleted.unwrap(), StatusCode::NO_CONTENT);
+        assert_eq!(staged(&f).await, 0);
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn limits_pending_uploads(pool: Pool<Postgres>) {
+        let f = fixture(pool).await;
+        let data = photo();
+        for _ in 0..MAX_PENDING_UPLOADS {
+            start(&f, &data).await.unwrap();
+        }
+        assert_eq!(start(&f, &data).await.unwrap_err(), StatusCode::TOO_MANY_REQUESTS);
+
+        let invalid = create_upload(
+            State(f.pool.clone()),
+            Extension(f.user.clone()),
+            Json(CreatePhotoUploadRequest {
+                size: MAX_PHOTO_BYTES as i64 + 1,
+                sha256: sha256(&data),
+            }),
+        )
+        .await;
+        assert_eq!(invalid.unwrap_err().0, StatusCode::BAD_REQUEST);
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn expired_uploads_are_discarded(pool: Pool<Postgres>) {
+        let f = fixture(pool).await;
+        let data = photo();
+        let upload = start(&f, &data).await.unwrap();
+        send(&f, upload.id, 0, &data[..10], None).await.unwrap();
+        sqlx::query("UPDATE photo_uploads SET expires_at = CURRENT_TIMESTAMP - INTERVAL '1 minute'")
+            .execute(&f.pool)
+            .await
+            .unwrap();
+
+        assert_eq!(send(&f, upload.id, 10, &data[10..], None).await.unwrap_err(), StatusCode::NOT_FOUND);
+        assert_eq!(expire_photo_uploads(&f.pool, f.blobs.as_ref()).await.unwrap(), 1);
+        assert_eq!(staged(&f).await, 0);
+        // Expired uploads no longer count against the limit
+        assert_eq!(count_pending_photo_uploads(&f.pool, f.user.id).await.unwrap(), 0);
+    }
+}
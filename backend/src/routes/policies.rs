@@ -0,0 +1,105 @@
+use axum::{
+    extract::{Extension, State},
+    http::StatusCode,
+    Json,
+};
+use serde::Deserialize;
+use sqlx::{Pool, Postgres};
+use std::net::IpAddr;
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+use crate::{
+    db::queries::get_user_by_id,
+    middleware::{auth::Claims, client::ClientInfo, permissions::PermissionCache},
+    models::user::User,
+    policy::{
+        engine::{attributes, evaluate, PolicyDecision, PolicyRequest},
+        schema::PolicySet,
+    },
+};
+
+/// Request payload for explaining a policy decision
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ExplainRequest {
+    /// HTTP method of the hypothetical request
+    pub method: String,
+    /// Path of the hypothetical request, e.g. `/api/admin`
+    pub path: String,
+    /// User making the request; defaults to the caller
+    pub user_id: Option<i32>,
+    /// Client IP; defaults to the caller's
+    pub ip: Option<String>,
+    /// Active organization; defaults to the caller's token
+    pub organization: Option<i32>,
+}
+
+/// Explain a policy decision
+///
+/// Evaluates the loaded policies for a hypothetical request without enforcing
+/// them, returning every applicable policy, whether it matched and the
+/// attributes it was evaluated against.
+#[utoipa::path(
+    post,
+    path = "/api/admin/policies/explain",
+    request_body = ExplainRequest,
+    responses(
+        (status = 200, description = "Policy decision", body = PolicyDecision),
+        (status = 400, description = "Invalid IP address"),
+        (status = 403, description = "Requires the policies:read permission"),
+        (status = 404, description = "User not found")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Admin"
+)]
+pub async fn explain(
+    State(pool): State<Pool<Postgres>>,
+    State(permission_cache): State<PermissionCache>,
+    State(policies): State<Arc<PolicySet>>,
+    Extension(caller): Extension<User>,
+    Extension(claims): Extension<Claims>,
+    client: ClientInfo,
+    Json(payload): Json<ExplainRequest>,
+) -> Result<Json<PolicyDecision>, (StatusCode, Json<String>)> {
+    let user = match payload.user_id {
+        Some(user_id) if user_id != caller.id => get_user_by_id(&pool, user_id)
+            .await
+            .map_err(|_| (StatusCode::NOT_FOUND, Json("User not found".to_string())))?,
+        _ => caller,
+    };
+
+    let ip = match payload.ip.as_deref() {
+        Some(ip) => Some(ip.parse::<IpAddr>().map_err(|_| (
+            StatusCode::BAD_REQUEST,
+            Json(format!("Invalid IP address: {}", ip)),
+        ))?),
+        None => client.ip,
+    };
+
+    let permissions = permission_cache
+        .get_or_load(&pool, &user)
+        .await
+        .map_err(|_| (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json("Failed to resolve permissions".to_string()),
+        ))?;
+
+    let method = payload.method.to_uppercase();
+    let decision = evaluate(
+        &policies,
+        &method,
+        &payload.path,
+        attributes(&PolicyRequest {
+            user: &user,
+            permissions: &permissions,
+            method: &method,
+            path: &payload.path,
+            ip,
+            organization: payload.organization.or(claims.org),
+        }),
+    );
+
+    Ok(Json(decision))
+}
@@ -1,26 +1,35 @@
 use axum::{
-    extract::{State, Multipart, FromRequest},
+    extract::{Extension, State, Multipart, FromRequest},
     http::StatusCode,
     Json, body::Bytes,
 };
-use axum_extra::{
-    headers::{authorization::Bearer, Authorization},
-    TypedHeader,
-};
+use serde_json::json;
 use sqlx::{Pool, Postgres};
 use validator::Validate;
-use std::str::FromStr;
 use std::default::Default;
 use uuid::Uuid;
-use std::path::Path;
-use tokio::fs;
+use std::sync::Arc;
 
 use crate::{
-    db::queries::{get_user_by_email, update_user_profile},
-    middleware::auth::decode_token,
-    models::user::{User, ProfileUpdateRequest},
+    db::{
+        audit::record_event,
+        queries::{clear_profile_picture, update_user_profile},
+    },
+    imaging::{process_photo, PhotoError},
+    mail::Mailer,
+    middleware::{auth::Claims, client::ClientInfo},
+    routes::auth::send_email_verification,
+    models::{
+        audit::AuditEventType,
+        user::{User, ProfileUpdateRequest},
+    },
+    storage::{
+        photos::{release_photo, PHOTO_PREFIX},
+        BlobStore,
+    },
 };
 
+
 /// Get user profile
 /// 
 /// Returns the profile information for the authenticated user.
@@ -37,27 +46,15 @@ use crate::{
     tag = "Profile"
 )]
 pub async fn get_profile(
-    State(pool): State<Pool<Postgres>>,
-    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Extension(user): Extension<User>,
 ) -> Result<Json<User>, (StatusCode, Json<String>)> {
-    // Verify token and get claims
-    let claims = decode_token(auth.token())
-        .map_err(|_| (StatusCode::UNAUTHORIZED, Json("Invalid token".to_string())))?;
-
-    // Get user from database
-    let user = get_user_by_email(&pool, &claims.sub)
-        .await
-        .map_err(|_| (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json("Failed to fetch user profile".to_string()),
-        ))?;
-
     Ok(Json(user))
 }
 
 /// Update user profile
 /// 
-/// Update the authenticated user's profile information.
+/// Update the authenticated user's profile information. Changing the email
+/// address marks it unverified and mails a link confirming the new one.
 #[utoipa::path(
     put,
     path = "/profile/update",
@@ -65,31 +62,25 @@ pub async fn get_profile(
     responses(
         (status = 200, description = "Profile updated successfully", body = User),
         (status = 400, description = "Invalid input"),
-        (status = 401, description = "Unauthorized - Invalid or missing token")
+        (status = 401, description = "Unauthorized - Invalid or missing token"),
+        (status = 403, description = "Email or password change while impersonating")
     ),
     security(
         ("bearer_auth" = [])
     ),
     tag = "Profile"
 )]
+#[allow(clippy::too_many_arguments)]
 pub async fn update_profile(
     State(pool): State<Pool<Postgres>>,
-    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    State(blobs): State<Arc<dyn BlobStore>>,
+    State(mailer): State<Arc<dyn Mailer>>,
+    Extension(current_user): Extension<User>,
+    Extension(claims): Extension<Claims>,
+    client: ClientInfo,
     content_type: axum::http::HeaderMap,
-    mut request: axum::extract::Request<axum::body::Body>,
+    request: axum::extract::Request<axum::body::Body>,
 ) -> Result<Json<User>, (StatusCode, Json<String>)> {
-    // Verify token and get claims
-    let claims = decode_token(auth.token())
-        .map_err(|_| (StatusCode::UNAUTHORIZED, Json("Invalid token".to_string())))?;
-
-    // Get current user
-    let current_user = get_user_by_email(&pool, &claims.sub)
-        .await
-        .map_err(|_| (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json("Failed to fetch user profile".to_string()),
-        ))?;
-
     // Parse the request based on content type
     let payload = if content_type
         .get("content-type")
@@ -142,6 +133,14 @@ pub async fn update_profile(
         ))?
     };
 
+    // Credentials stay out of reach of administrators acting as the user
+    if claims.act.is_some() && (payload.email.is_some() || payload.password.is_some()) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json("Email and password cannot be changed while impersonating".to_string()),
+        ));
+    }
+
     // Validate request
     if let Err(e) = payload.validate() {
         return Err((
@@ -165,20 +164,141 @@ pub async fn update_profile(
         StatusCode::INTERNAL_SERVER_ERROR,
         Json("Failed to update profile".to_string()),
     ))?;
+    if let Some(previous) = &current_user.profile_picture {
+        if updated_user.profile_picture.as_ref() != Some(previous) {
+            release_photo(&pool, blobs.as_ref(), previous).await;
+        }
+    }
+
+    // Audit the change; credential changes get their own events
+    let actor = claims.act.as_ref().map_or(current_user.id, |actor| actor.id);
+    let context = client.audit(Some(actor));
+    let fields: Vec<&str> = [
+        ("firstname", payload.firstname.is_some()),
+        ("lastname", payload.lastname.is_some()),
+        ("profile_picture", payload.profile_picture.is_some()),
+    ]
+    .into_iter()
+    .filter_map(|(field, changed)| changed.then_some(field))
+    .collect();
+    if !fields.is_empty() {
+        record_event(
+            &pool,
+            &context,
+            AuditEventType::ProfileUpdated,
+            Some(current_user.id),
+            json!({ "fields": fields }),
+        )
+        .await;
+    }
+    if updated_user.email != current_user.email {
+        record_event(
+            &pool,
+            &context,
+            AuditEventType::EmailChanged,
+            Some(current_user.id),
+            json!({ "from": current_user.email, "to": updated_user.email }),
+        )
+        .await;
+        send_email_verification(&pool, mailer.as_ref(), &updated_user).await;
+    }
+    if payload.password.is_some() {
+        record_event(
+            &pool,
+            &context,
+            AuditEventType::PasswordChanged,
+            Some(current_user.id),
+            json!({}),
+        )
+        .await;
+    }
 
     Ok(Json(updated_user))
 }
 
+/// Turns an uploaded photo into the user's profile picture: processes it into
+/// variants, stores them and releases the previous photo
+pub async fn save_photo(
+    pool: &Pool<Postgres>,
+    blobs: &dyn BlobStore,
+    current_user: &User,
+    data: Bytes,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<String>)> {
+    // The type comes from the content, so the file name is ignored
+    let variants = tokio::task::spawn_blocking(move || process_photo(&data))
+        .await
+        .map_err(|_| (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json("Failed to process photo".to_string()),
+        ))?
+        .map_err(|e| match e {
+            PhotoError::Rejected(message) => (StatusCode::BAD_REQUEST, Json(message)),
+            PhotoError::Failed(message) => {
+                tracing::error!(error = %message, "Failed to process photo");
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json("Failed to process photo".to_string()),
+                )
+            }
+        })?;
+
+    // Store under a fresh set so cached copies of the old photo never go stale
+    let set = format!("{}/{}", PHOTO_PREFIX, Uuid::new_v4());
+    let mut urls = Vec::with_capacity(variants.len());
+    for variant in variants {
+        let key = format!("{}/{}", set, variant.file_name());
+        blobs
+            .put(&key, variant.data.into(), variant.content_type)
+            .await
+            .map_err(|e| {
+                tracing::error!(error = %e, "Failed to store photo");
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json("Failed to save file".to_string()),
+                )
+            })?;
+        urls.push(format!("/uploads/{}", key));
+    }
+
+    // Update user's profile_picture in database
+    let file_url = format!("/uploads/{}", set);
+    update_user_profile(
+        pool,
+        current_user.id,
+        None,
+        None,
+        None,
+        None,
+        Some(&file_url),
+    )
+    .await
+    .map_err(|_| (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json("Failed to update profile picture".to_string()),
+    ))?;
+    if let Some(previous) = &current_user.profile_picture {
+        release_photo(pool, blobs, previous).await;
+    }
+
+    Ok(Json(serde_json::json!({
+        "url": file_url,
+        "variants": urls
+    })))
+}
+
 /// Upload profile photo
 /// 
-/// Upload a new profile photo for the authenticated user.
+/// Upload a new profile photo for the authenticated user. The image is
+/// re-encoded without metadata into square 64, 256 and 512 pixel variants, as
+/// JPEG and WebP; `profile_picture` becomes the URL of that variant set.
 #[utoipa::path(
     post,
     path = "/profile/photo",
     responses(
         (status = 200, description = "Photo uploaded successfully"),
-        (status = 400, description = "Invalid file format"),
-        (status = 401, description = "Unauthorized - Invalid or missing token")
+        (status = 400, description = "Not a supported image, or too small"),
+        (status = 401, description = "Unauthorized - Invalid or missing token"),
+        (status = 413, description = "Photo too large")
     ),
     security(
         ("bearer_auth" = [])
@@ -187,89 +307,72 @@ pub async fn update_profile(
 )]
 pub async fn upload_photo(
     State(pool): State<Pool<Postgres>>,
-    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    State(blobs): State<Arc<dyn BlobStore>>,
+    Extension(current_user): Extension<User>,
     mut multipart: Multipart,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<String>)> {
-    // Verify token and get claims
-    let claims = decode_token(auth.token())
-        .map_err(|_| (StatusCode::UNAUTHORIZED, Json("Invalid token".to_string())))?;
-
-    // Get current user
-    let current_user = get_user_by_email(&pool, &claims.sub)
-        .await
-        .map_err(|_| (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json("Failed to fetch user profile".to_string()),
-        ))?;
-
     // Process the uploaded file
     while let Some(field) = multipart.next_field().await.map_err(|e| (
         StatusCode::BAD_REQUEST,
         Json(format!("Error processing form data: {}", e)),
     ))? {
         if field.name() == Some("photo") {
-            let file_name = field.file_name()
-                .ok_or((StatusCode::BAD_REQUEST, Json("No filename provided".to_string())))?
-                .to_string();
-
-            // Validate file type
-            let extension = Path::new(&file_name)
-                .extension()
-                .and_then(|ext| ext.to_str())
-                .ok_or((StatusCode::BAD_REQUEST, Json("Invalid file format".to_string())))?
-                .to_lowercase();
-
-            if !["jpg", "jpeg", "png", "gif"].contains(&extension.as_str()) {
-                return Err((
-                    StatusCode::BAD_REQUEST,
-                    Json("Invalid file format. Allowed formats: jpg, jpeg, png, gif".to_string()),
-                ));
-            }
-
-            // Read file data
+            // Read file data; the route's body limit bounds its size
             let data = field.bytes().await.map_err(|e| (
-                StatusCode::INTERNAL_SERVER_ERROR,
+                StatusCode::PAYLOAD_TOO_LARGE,
                 Json(format!("Failed to read file data: {}", e)),
             ))?;
 
-            // Generate unique filename
-            let unique_filename = format!("{}.{}", Uuid::new_v4(), extension);
-            let upload_path = Path::new("backend/uploads").join(&unique_filename);
-
-            // Create uploads directory if it doesn't exist
-            fs::create_dir_all("backend/uploads").await.map_err(|e| (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(format!("Failed to create upload directory: {}", e)),
-            ))?;
-
-            // Save file
-            fs::write(&upload_path, data).await.map_err(|e| (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(format!("Failed to save file: {}", e)),
-            ))?;
-
-            // Update user's profile_picture in database
-            let file_url = format!("/uploads/{}", unique_filename);
-            update_user_profile(
-                &pool,
-                current_user.id,
-                None,
-                None,
-                None,
-                None,
-                Some(&file_url),
-            )
-            .await
-            .map_err(|_| (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json("Failed to update profile picture".to_string()),
-            ))?;
-
-            return Ok(Json(serde_json::json!({
-                "url": file_url
-            })));
+            return save_photo(&pool, blobs.as_ref(), &current_user, data).await;
         }
     }
 
     Err((StatusCode::BAD_REQUEST, Json("No photo file found in request".to_string())))
-} 
\ No newline at end of file
+}
+
+/// Delete profile photo
+///
+/// Removes the authenticated user's profile photo and deletes its files.
+#[utoipa::path(
+    delete,
+    path = "/api/profile/photo",
+    responses(
+        (status = 204, description = "Photo removed"),
+        (status = 401, description = "Unauthorized - Invalid or missing token"),
+        (status = 404, description = "No profile photo")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Profile"
+)]
+pub async fn delete_photo(
+    State(pool): State<Pool<Postgres>>,
+    State(blobs): State<Arc<dyn BlobStore>>,
+    Extension(current_user): Extension<User>,
+    Extension(claims): Extension<Claims>,
+    client: ClientInfo,
+) -> Result<StatusCode, (StatusCode, Json<String>)> {
+    let Some(previous) = &current_user.profile_picture else {
+        return Err((StatusCode::NOT_FOUND, Json("No profile photo".to_string())));
+    };
+
+    clear_profile_picture(&pool, current_user.id)
+        .await
+        .map_err(|_| (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json("Failed to remove profile picture".to_string()),
+        ))?;
+    release_photo(&pool, blobs.as_ref(), previous).await;
+
+    let actor = claims.act.as_ref().map_or(current_user.id, |actor| actor.id);
+    record_event(
+        &pool,
+        &client.audit(Some(actor)),
+        AuditEventType::ProfileUpdated,
+        Some(current_user.id),
+        json!({ "fields": ["profile_picture"] }),
+    )
+    .await;
+    Ok(StatusCode::NO_CONTENT)
+}
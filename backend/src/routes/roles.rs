@@ -0,0 +1,171 @@
+use axum::{
+    extract::{Extension, Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde_json::json;
+use sqlx::{Pool, Postgres};
+use validator::Validate;
+
+use crate::{
+    db::{
+        audit::record_event,
+        roles::{get_effective_permissions, get_role_lineage, get_role_permissions, list_roles, upsert_role},
+    },
+    middleware::{client::ClientInfo, permissions::PermissionCache},
+    models::{
+        audit::AuditEventType,
+        role::{RoleDefinition, RoleResponse, RoleUpsertRequest},
+        user::User,
+    },
+};
+
+async fn to_response(
+    pool: &Pool<Postgres>,
+    role: RoleDefinition,
+) -> Result<RoleResponse, sqlx::Error> {
+    let permissions = get_role_permissions(pool, &role.name).await?;
+    let mut effective_permissions: Vec<String> =
+        get_effective_permissions(pool, &role.name).await?.into_iter().collect();
+    effective_permissions.sort();
+
+    Ok(RoleResponse {
+        role,
+        permissions,
+        effective_permissions,
+    })
+}
+
+/// List roles
+///
+/// Returns every role with its direct and inherited permissions.
+#[utoipa::path(
+    get,
+    path = "/api/admin/roles",
+    responses(
+        (status = 200, description = "Roles retrieved successfully", body = [RoleResponse]),
+        (status = 401, description = "No token provided or invalid token"),
+        (status = 403, description = "Requires the roles:write permission")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Admin"
+)]
+pub async fn get_roles(
+    State(pool): State<Pool<Postgres>>,
+) -> Result<Json<Vec<RoleResponse>>, (StatusCode, Json<String>)> {
+    let internal_error = |_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json("Failed to fetch roles".to_string()),
+        )
+    };
+
+    let mut roles = Vec::new();
+    for role in list_roles(&pool).await.map_err(internal_error)? {
+        roles.push(to_response(&pool, role).await.map_err(internal_error)?);
+    }
+
+    Ok(Json(roles))
+}
+
+/// Create or update a role
+///
+/// Sets the role's parent and replaces its direct permissions. Cached
+/// permissions are invalidated so the change applies on the next request.
+#[utoipa::path(
+    put,
+    path = "/api/admin/roles/{name}",
+    params(
+        ("name" = String, Path, description = "Role name")
+    ),
+    request_body = RoleUpsertRequest,
+    responses(
+        (status = 200, description = "Role saved successfully", body = RoleResponse),
+        (status = 400, description = "Invalid input or inheritance cycle"),
+        (status = 401, description = "No token provided or invalid token"),
+        (status = 403, description = "Requires the roles:write permission")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Admin"
+)]
+pub async fn put_role(
+    State(pool): State<Pool<Postgres>>,
+    State(permission_cache): State<PermissionCache>,
+    Extension(admin): Extension<User>,
+    client: ClientInfo,
+    Path(name): Path<String>,
+    Json(payload): Json<RoleUpsertRequest>,
+) -> Result<Json<RoleResponse>, (StatusCode, Json<String>)> {
+    if let Err(e) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(format!("Validation error: {}", e)),
+        ));
+    }
+
+    if name.is_empty() || name.len() > 50 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json("Role name must be 1-50 characters".to_string()),
+        ));
+    }
+
+    if let Some(parent) = payload.parent.as_deref() {
+        let lineage = get_role_lineage(&pool, parent).await.map_err(|_| (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json("Failed to resolve parent role".to_string()),
+        ))?;
+
+        if lineage.is_empty() {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(format!("Unknown parent role: {}", parent)),
+            ));
+        }
+        if lineage.iter().any(|ancestor| ancestor == &name) {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(format!("Role {} cannot inherit from its own descendant {}", name, parent)),
+            ));
+        }
+    }
+
+    let role = upsert_role(
+        &pool,
+        &name,
+        payload.parent.as_deref(),
+        payload.description.as_deref(),
+        &payload.permissions,
+    )
+    .await
+    .map_err(|_| (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json("Failed to save role".to_string()),
+    ))?;
+
+    permission_cache.invalidate_all();
+    record_event(
+        &pool,
+        &client.audit(Some(admin.id)),
+        AuditEventType::RoleChanged,
+        None,
+        json!({
+            "action": "upsert_role",
+            "role": name,
+            "parent": payload.parent,
+            "permissions": payload.permissions,
+        }),
+    )
+    .await;
+
+    let response = to_response(&pool, role).await.map_err(|_| (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json("Failed to fetch role".to_string()),
+    ))?;
+
+    Ok(Json(response))
+}
@@ -0,0 +1,307 @@
+use axum::{
+    extract::{Extension, Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde_json::json;
+use sqlx::{Pool, Postgres};
+use uuid::Uuid;
+
+use crate::{
+    db::{
+        audit::record_event,
+        sessions::{list_active_sessions, list_login_history, revoke_other_sessions, revoke_session},
+    },
+    middleware::{auth::Claims, client::ClientInfo},
+    models::{
+        audit::AuditEventType,
+        session::{LoginHistoryQuery, LoginRecord, RevokedSessions, SessionResponse},
+        user::User,
+    },
+};
+
+/// Default and maximum number of login attempts returned
+const DEFAULT_HISTORY_SIZE: i64 = 20;
+const MAX_HISTORY_SIZE: i64 = 100;
+
+/// Get login history
+///
+/// Returns recent login attempts against the authenticated user's account,
+/// successful or not, with where they came from.
+#[utoipa::path(
+    get,
+    path = "/api/profile/logins",
+    params(LoginHistoryQuery),
+    responses(
+        (status = 200, description = "Login history retrieved successfully", body = [LoginRecord]),
+        (status = 400, description = "Invalid limit"),
+        (status = 401, description = "Unauthorized - Invalid or missing token")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Profile"
+)]
+pub async fn get_login_history(
+    State(pool): State<Pool<Postgres>>,
+    Extension(user): Extension<User>,
+    Query(query): Query<LoginHistoryQuery>,
+) -> Result<Json<Vec<LoginRecord>>, (StatusCode, Json<String>)> {
+    let limit = query.limit.unwrap_or(DEFAULT_HISTORY_SIZE);
+    if !(1..=MAX_HISTORY_SIZE).contains(&limit) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(format!("limit must be between 1 and {}", MAX_HISTORY_SIZE)),
+        ));
+    }
+
+    list_login_history(&pool, user.id, limit)
+        .await
+        .map(Json)
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json("Failed to fetch login history".to_string()),
+            )
+        })
+}
+
+/// List active sessions
+///
+/// Returns the authenticated user's signed-in sessions, most recently used
+/// first, marking the one making the request.
+#[utoipa::path(
+    get,
+    path = "/api/profile/sessions",
+    responses(
+        (status = 200, description = "Sessions retrieved successfully", body = [SessionResponse]),
+        (status = 401, description = "Unauthorized - Invalid or missing token")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Profile"
+)]
+pub async fn get_sessions(
+    State(pool): State<Pool<Postgres>>,
+    Extension(user): Extension<User>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<Vec<SessionResponse>>, (StatusCode, Json<String>)> {
+    let sessions = list_active_sessions(&pool, user.id).await.map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json("Failed to fetch sessions".to_string()),
+        )
+    })?;
+
+    Ok(Json(
+        sessions
+            .into_iter()
+            .map(|session| SessionResponse {
+                current: claims.sid == Some(session.id),
+                session,
+            })
+            .collect(),
+    ))
+}
+
+/// Revoke a session
+///
+/// Signs the session out; every token issued for it stops working. Revoking
+/// the current session logs out.
+#[utoipa::path(
+    delete,
+    path = "/api/profile/sessions/{id}",
+    params(
+        ("id" = Uuid, Path, description = "Session id")
+    ),
+    responses(
+        (status = 204, description = "Session revoked"),
+        (status = 401, description = "Unauthorized - Invalid or missing token"),
+        (status = 404, description = "No such active session")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Profile"
+)]
+pub async fn delete_session(
+    State(pool): State<Pool<Postgres>>,
+    Extension(user): Extension<User>,
+    client: ClientInfo,
+    Path(session_id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, Json<String>)> {
+    let revoked = revoke_session(&pool, user.id, session_id).await.map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json("Failed to revoke session".to_string()),
+        )
+    })?;
+    if !revoked {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json("Session not found".to_string()),
+        ));
+    }
+
+    record_event(
+        &pool,
+        &client.audit(Some(user.id)),
+        AuditEventType::TokenRevoked,
+        Some(user.id),
+        json!({ "session_id": session_id }),
+    )
+    .await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Revoke all other sessions
+///
+/// Signs out every session except the one making the request.
+#[utoipa::path(
+    delete,
+    path = "/api/profile/sessions",
+    responses(
+        (status = 200, description = "Other sessions revoked", body = RevokedSessions),
+        (status = 401, description = "Unauthorized - Invalid or missing token")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Profile"
+)]
+pub async fn delete_other_sessions(
+    State(pool): State<Pool<Postgres>>,
+    Extension(user): Extension<User>,
+    Extension(claims): Extension<Claims>,
+    client: ClientInfo,
+) -> Result<Json<RevokedSessions>, (StatusCode, Json<String>)> {
+    let revoked = revoke_other_sessions(&pool, user.id, claims.sid)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json("Failed to revoke sessions".to_string()),
+            )
+        })?;
+
+    if revoked > 0 {
+        record_event(
+            &pool,
+            &client.audit(Some(user.id)),
+            AuditEventType::TokenRevoked,
+            Some(user.id),
+            json!({ "sessions": revoked, "kept": claims.sid }),
+        )
+        .await;
+    }
+    Ok(Json(RevokedSessions { revoked }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::config::init_for_tests,
+        db::sessions::create_session,
+        middleware::{
+            auth::{authenticate, create_token, decode_token, token_ttl},
+            permissions::PermissionCache,
+        },
+        test_support::insert_user,
+    };
+
+    async fn sign_in(pool: &Pool<Postgres>, user: &User) -> (Uuid, String) {
+        let expires_at = chrono::Utc::now() + token_ttl();
+        let session_id = create_session(pool, user.id, None, Some("curl/8.4.0"), "password", expires_at)
+            .await
+            .unwrap();
+        (session_id, create_token(&user.email, &user.role, None, None, Some(session_id)))
+    }
+
+    async fn status_of(pool: &Pool<Postgres>, token: &str) -> StatusCode {
+        match authenticate(pool, &PermissionCache::default(), token).await {
+            Ok(_) => StatusCode::OK,
+            Err((status, _)) => status,
+        }
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn revoking_other_sessions_keeps_the_current_one(pool: Pool<Postgres>) {
+        init_for_tests();
+        let user = insert_user(&pool, "user@example.com", "User").await;
+        let (current, current_token) = sign_in(&pool, &user).await;
+        let (_, laptop_token) = sign_in(&pool, &user).await;
+        let (_, phone_token) = sign_in(&pool, &user).await;
+
+        let Json(revoked) = delete_other_sessions(
+            State(pool.clone()),
+            Extension(user.clone()),
+            Extension(decode_token(&current_token).unwrap()),
+            ClientInfo::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(revoked.revoked, 2);
+
+        let sessions = list_active_sessions(&pool, user.id).await.unwrap();
+        assert_eq!(sessions.iter().map(|session| session.id).collect::<Vec<_>>(), [current]);
+        assert_eq!(status_of(&pool, &current_token).await, StatusCode::OK);
+        assert_eq!(status_of(&pool, &laptop_token).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(status_of(&pool, &phone_token).await, StatusCode::UNAUTHORIZED);
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn revoked_sessions_reject_their_tokens(pool: Pool<Postgres>) {
+        init_for_tests();
+        let user = insert_user(&pool, "user@example.com", "User").await;
+        let (session, token) = sign_in(&pool, &user).await;
+        assert_eq!(status_of(&pool, &token).await, StatusCode::OK);
+
+        let revoke = || {
+            delete_session(
+                State(pool.clone()),
+                Extension(user.clone()),
+                ClientInfo::default(),
+                Path(session),
+            )
+        };
+        assert_eq!(revoke().await.unwrap(), StatusCode::NO_CONTENT);
+        assert_eq!(status_of(&pool, &token).await, StatusCode::UNAUTHORIZED);
+        let (status, _) = revoke().await.unwrap_err();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn sessions_of_other_users_cannot_be_revoked(pool: Pool<Postgres>) {
+        init_for_tests();
+        let user = insert_user(&pool, "user@example.com", "User").await;
+        let other = insert_user(&pool, "other@example.com", "User").await;
+        let (others_session, others_token) = sign_in(&pool, &other).await;
+        let (_, token) = sign_in(&pool, &user).await;
+
+        let (status, _) = delete_session(
+            State(pool.clone()),
+            Extension(user.clone()),
+            ClientInfo::default(),
+            Path(others_session),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let Json(revoked) = delete_other_sessions(
+            State(pool.clone()),
+            Extension(user.clone()),
+            Extension(decode_token(&token).unwrap()),
+            ClientInfo::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(revoked.revoked, 0);
+        assert_eq!(status_of(&pool, &others_token).await, StatusCode::OK);
+    }
+}
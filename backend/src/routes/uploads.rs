@@ -0,0 +1,93 @@
+use axum::{
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Redirect, Response},
+    Json,
+};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::{
+    config::config::app_config,
+    imaging::DEFAULT_PHOTO_SIZE,
+    storage::{
+        is_valid_key,
+        photos::{is_photo_set, STAGING_PREFIX},
+        BlobStore,
+    },
+};
+
+/// Stored keys are never overwritten, so their content can be cached indefinitely
+const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+
+/// Get an uploaded file
+///
+/// Serves a file such as a profile photo from storage, or redirects to a
+/// short-lived presigned storage URL when `storage.presigned_photos` is enabled. A
+/// photo variant set redirects to its default variant.
+#[utoipa::path(
+    get,
+    path = "/uploads/{key}",
+    params(
+        ("key" = String, Path, description = "Storage key, e.g. photos/<uuid>.png")
+    ),
+    responses(
+        (status = 200, description = "File content"),
+        (status = 307, description = "Redirect to a presigned storage URL"),
+        (status = 308, description = "Redirect from a photo set to its default variant"),
+        (status = 404, description = "No such file")
+    ),
+    tag = "Profile"
+)]
+pub async fn get_upload(
+    State(blobs): State<Arc<dyn BlobStore>>,
+    Path(key): Path<String>,
+) -> Result<Response, (StatusCode, Json<String>)> {
+    // Chunks of unfinished resumable uploads are never served
+    if !is_valid_key(&key) || key.split('/').next() == Some(STAGING_PREFIX) {
+        return Err((StatusCode::NOT_FOUND, Json("File not found".to_string())));
+    }
+    if is_photo_set(&key) {
+        let variant = format!("/uploads/{}/{}.webp", key, DEFAULT_PHOTO_SIZE);
+        return Ok(([(header::CACHE_CONTROL, IMMUTABLE)], Redirect::permanent(&variant)).into_response());
+    }
+
+    let config = &app_config().storage;
+    if config.presigned_photos {
+        let ttl = config.presigned_url_ttl_seconds;
+        match blobs.presigned_url(&key, Duration::from_secs(ttl)).await {
+            Ok(Some(url)) => {
+                // Let clients reuse the redirect while the URL is still comfortably valid
+                let cache = format!("private, max-age={}", ttl / 2);
+                return Ok(([(header::CACHE_CONTROL, cache)], Redirect::temporary(&url)).into_response());
+            }
+            Ok(None) => {}
+            Err(e) => tracing::warn!(%key, error = %e, "Failed to presign upload, serving it directly"),
+        }
+    }
+
+    let blob = blobs
+        .get(&key)
+        .await
+        .map_err(|e| {
+            tracing::error!(%key, error = %e, "Failed to read upload");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json("Failed to read file".to_string()),
+            )
+        })?
+        .ok_or((StatusCode::NOT_FOUND, Json("File not found".to_string())))?;
+
+    let content_type = blob
+        .content_type
+        .unwrap_or_else(|| "application/octet-stream".to_string());
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type),
+            (header::CACHE_CONTROL, IMMUTABLE.to_string()),
+            (header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+        ],
+        blob.data,
+    )
+        .into_response())
+}
@@ -0,0 +1,628 @@
+use axum::{
+    extract::{Extension, Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde_json::json;
+use sqlx::{Pool, Postgres};
+use validator::Validate;
+
+use crate::{
+    config::config::app_config,
+    db::{
+        queries::get_user_by_id,
+        roles::{get_user_permissions, get_user_roles},
+        users::{
+            change_user_role, count_users, delete_user, disable_user, enable_user,
+            get_user_summary, list_admin_actions, list_users, log_admin_action,
+            require_password_change, UserCursor, UserDeletion, MAX_PAGE_SIZE,
+        },
+    },
+    middleware::{
+        auth::create_impersonation_token, client::ClientInfo, permissions::PermissionCache,
+    },
+    models::{
+        admin::{
+            AccountActionRequest, ChangeRoleRequest, ImpersonationRequest,
+            ImpersonationResponse, UserCount, UserDetail, UserFilter, UserListQuery, UserPage,
+            UserSummary,
+        },
+        audit::AuditEvent,
+        user::User,
+    },
+};
+
+/// Default page size of the user list
+const DEFAULT_PAGE_SIZE: i64 = 50;
+
+/// List users
+///
+/// Returns one page of users matching the filters. Pages are keyset-paginated:
+/// pass `next_cursor` back as `cursor`, keeping the same `sort` and filters.
+#[utoipa::path(
+    get,
+    path = "/api/admin/users",
+    params(UserFilter, UserListQuery),
+    responses(
+        (status = 200, description = "Users retrieved successfully", body = UserPage),
+        (status = 400, description = "Invalid cursor or page size"),
+        (status = 401, description = "No token provided or invalid token"),
+        (status = 403, description = "Requires the users:read permission")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Admin"
+)]
+pub async fn get_users(
+    State(pool): State<Pool<Postgres>>,
+    Query(filter): Query<UserFilter>,
+    Query(query): Query<UserListQuery>,
+) -> Result<Json<UserPage>, (StatusCode, Json<String>)> {
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    if !(1..=MAX_PAGE_SIZE).contains(&limit) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(format!("limit must be between 1 and {}", MAX_PAGE_SIZE)),
+        ));
+    }
+
+    let cursor = query
+        .cursor
+        .as_deref()
+        .map(|cursor| UserCursor::decode(cursor, query.sort))
+        .transpose()
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(e)))?;
+
+    let page = list_users(&pool, &filter, query.sort, query.order, limit, cursor.as_ref())
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json("Failed to fetch users".to_string()),
+            )
+        })?;
+
+    Ok(Json(page))
+}
+
+/// Count users
+///
+/// Returns the number of users matching the filters, in total and per role.
+#[utoipa::path(
+    get,
+    path = "/api/admin/users/count",
+    params(UserFilter),
+    responses(
+        (status = 200, description = "Users counted successfully", body = UserCount),
+        (status = 401, description = "No token provided or invalid token"),
+        (status = 403, description = "Requires the users:read permission")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Admin"
+)]
+pub async fn get_user_count(
+    State(pool): State<Pool<Postgres>>,
+    Query(filter): Query<UserFilter>,
+) -> Result<Json<UserCount>, (StatusCode, Json<String>)> {
+    let by_role = count_users(&pool, &filter).await.map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json("Failed to count users".to_string()),
+        )
+    })?;
+
+    Ok(Json(UserCount {
+        total: by_role.iter().map(|r| r.count).sum(),
+        by_role,
+    }))
+}
+
+/// Get a user
+///
+/// Returns the user with the roles and permissions they currently resolve to.
+#[utoipa::path(
+    get,
+    path = "/api/admin/users/{id}",
+    params(
+        ("id" = i32, Path, description = "User id")
+    ),
+    responses(
+        (status = 200, description = "User retrieved successfully", body = UserDetail),
+        (status = 401, description = "No token provided or invalid token"),
+        (status = 403, description = "Requires the users:read permission"),
+        (status = 404, description = "User not found")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Admin"
+)]
+pub async fn get_user_detail(
+    State(pool): State<Pool<Postgres>>,
+    Path(user_id): Path<i32>,
+) -> Result<Json<UserDetail>, (StatusCode, Json<String>)> {
+    let fetch_error = |_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json("Failed to fetch user".to_string()),
+        )
+    };
+
+    let user = get_user_summary(&pool, user_id)
+        .await
+        .map_err(fetch_error)?
+        .ok_or((StatusCode::NOT_FOUND, Json("User not found".to_string())))?;
+
+    let mut roles: Vec<String> = get_user_roles(&pool, user_id)
+        .await
+        .map_err(fetch_error)?
+        .into_iter()
+        .collect();
+    roles.sort();
+    let mut permissions: Vec<String> = get_user_permissions(&pool, user_id)
+        .await
+        .map_err(fetch_error)?
+        .into_iter()
+        .collect();
+    permissions.sort();
+
+    Ok(Json(UserDetail {
+        user,
+        roles,
+        permissions,
+    }))
+}
+
+fn validation_error(e: validator::ValidationErrors) -> (StatusCode, Json<String>) {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(format!("Validation error: {}", e)),
+    )
+}
+
+fn reject_self(admin: &User, user_id: i32) -> Result<(), (StatusCode, Json<String>)> {
+    if admin.id == user_id {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json("Administrators cannot perform this action on their own account".to_string()),
+        ));
+    }
+    Ok(())
+}
+
+fn not_found() -> (StatusCode, Json<String>) {
+    (StatusCode::NOT_FOUND, Json("User not found".to_string()))
+}
+
+fn update_error(_: sqlx::Error) -> (StatusCode, Json<String>) {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json("Failed to update user".to_string()),
+    )
+}
+
+/// Disable a user
+///
+/// Disabled accounts are rejected on their next request and cannot log in.
+#[utoipa::path(
+    post,
+    path = "/api/admin/users/{id}/disable",
+    params(
+        ("id" = i32, Path, description = "User id")
+    ),
+    request_body = AccountActionRequest,
+    responses(
+        (status = 200, description = "User disabled", body = UserSummary),
+        (status = 400, description = "Invalid input or own account"),
+        (status = 403, description = "Requires the users:write permission"),
+        (status = 404, description = "User not found")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Admin"
+)]
+pub async fn disable(
+    State(pool): State<Pool<Postgres>>,
+    Extension(admin): Extension<User>,
+    client: ClientInfo,
+    Path(user_id): Path<i32>,
+    Json(payload): Json<AccountActionRequest>,
+) -> Result<Json<UserSummary>, (StatusCode, Json<String>)> {
+    payload.validate().map_err(validation_error)?;
+    reject_self(&admin, user_id)?;
+
+    disable_user(&pool, &client.audit(Some(admin.id)), user_id, payload.reason.as_deref())
+        .await
+        .map_err(update_error)?
+        .map(Json)
+        .ok_or_else(not_found)
+}
+
+/// Enable a user
+///
+/// Re-enables a disabled account.
+#[utoipa::path(
+    post,
+    path = "/api/admin/users/{id}/enable",
+    params(
+        ("id" = i32, Path, description = "User id")
+    ),
+    request_body = AccountActionRequest,
+    responses(
+        (status = 200, description = "User enabled", body = UserSummary),
+        (status = 400, description = "Invalid input"),
+        (status = 403, description = "Requires the users:write permission"),
+        (status = 404, description = "User not found")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Admin"
+)]
+pub async fn enable(
+    State(pool): State<Pool<Postgres>>,
+    Extension(admin): Extension<User>,
+    client: ClientInfo,
+    Path(user_id): Path<i32>,
+    Json(payload): Json<AccountActionRequest>,
+) -> Result<Json<UserSummary>, (StatusCode, Json<String>)> {
+    payload.validate().map_err(validation_error)?;
+
+    enable_user(&pool, &client.audit(Some(admin.id)), user_id, payload.reason.as_deref())
+        .await
+        .map_err(update_error)?
+        .map(Json)
+        .ok_or_else(not_found)
+}
+
+/// Force a password reset
+///
+/// The user's next login yields a token that can only change their password.
+#[utoipa::path(
+    post,
+    path = "/api/admin/users/{id}/force-password-reset",
+    params(
+        ("id" = i32, Path, description = "User id")
+    ),
+    request_body = AccountActionRequest,
+    responses(
+        (status = 200, description = "Password change required at next login", body = UserSummary),
+        (status = 400, description = "Invalid input"),
+        (status = 403, description = "Requires the users:write permission"),
+        (status = 404, description = "User not found")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Admin"
+)]
+pub async fn force_password_reset(
+    State(pool): State<Pool<Postgres>>,
+    Extension(admin): Extension<User>,
+    client: ClientInfo,
+    Path(user_id): Path<i32>,
+    Json(payload): Json<AccountActionRequest>,
+) -> Result<Json<UserSummary>, (StatusCode, Json<String>)> {
+    payload.validate().map_err(validation_error)?;
+
+    require_password_change(&pool, &client.audit(Some(admin.id)), user_id, payload.reason.as_deref())
+        .await
+        .map_err(update_error)?
+        .map(Json)
+        .ok_or_else(not_found)
+}
+
+/// Change a user's role
+///
+/// The previous and new role are recorded in the user's audit trail.
+#[utoipa::path(
+    put,
+    path = "/api/admin/users/{id}/role",
+    params(
+        ("id" = i32, Path, description = "User id")
+    ),
+    request_body = ChangeRoleRequest,
+    responses(
+        (status = 200, description = "Role changed", body = UserSummary),
+        (status = 400, description = "Invalid input, unknown role or own account"),
+        (status = 403, description = "Requires the users:write permission"),
+        (status = 404, description = "User not found")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Admin"
+)]
+pub async fn put_user_role(
+    State(pool): State<Pool<Postgres>>,
+    State(permission_cache): State<PermissionCache>,
+    Extension(admin): Extension<User>,
+    client: ClientInfo,
+    Path(user_id): Path<i32>,
+    Json(payload): Json<ChangeRoleRequest>,
+) -> Result<Json<UserSummary>, (StatusCode, Json<String>)> {
+    payload.validate().map_err(validation_error)?;
+    reject_self(&admin, user_id)?;
+
+    let user = change_user_role(
+        &pool,
+        &client.audit(Some(admin.id)),
+        user_id,
+        &payload.role,
+        payload.reason.as_deref(),
+    )
+    .await
+    .map_err(|e| match e {
+        sqlx::Error::Database(db) if db.is_foreign_key_violation() => (
+            StatusCode::BAD_REQUEST,
+            Json(format!("Unknown role: {}", payload.role)),
+        ),
+        e => update_error(e),
+    })?
+    .ok_or_else(not_found)?;
+
+    permission_cache.invalidate_user(user_id);
+    Ok(Json(user))
+}
+
+/// Delete a user
+///
+/// Removes the account with its memberships and relationship tuples. The
+/// audit trail is kept. Refused while the user is an organization's only Owner.
+#[utoipa::path(
+    delete,
+    path = "/api/admin/users/{id}",
+    params(
+        ("id" = i32, Path, description = "User id")
+    ),
+    responses(
+        (status = 204, description = "User deleted"),
+        (status = 400, description = "Own account"),
+        (status = 403, description = "Requires the users:write permission"),
+        (status = 404, description = "User not found"),
+        (status = 409, description = "Only owner of an organization")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Admin"
+)]
+pub async fn delete_user_by_id(
+    State(pool): State<Pool<Postgres>>,
+    State(permission_cache): State<PermissionCache>,
+    Extension(admin): Extension<User>,
+    client: ClientInfo,
+    Path(user_id): Path<i32>,
+) -> Result<StatusCode, (StatusCode, Json<String>)> {
+    reject_self(&admin, user_id)?;
+
+    let deletion = delete_user(&pool, &client.audit(Some(admin.id)), user_id).await.map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json("Failed to delete user".to_string()),
+        )
+    })?;
+    match deletion {
+        UserDeletion::Deleted => {}
+        UserDeletion::NotFound => return Err(not_found()),
+        UserDeletion::LastOwner(organizations) => {
+            return Err((
+                StatusCode::CONFLICT,
+                Json(format!(
+                    "User is the only owner of organizations {:?}; transfer ownership first",
+                    organizations
+                )),
+            ))
+        }
+    }
+
+    permission_cache.invalidate_user(user_id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Get a user's audit trail
+///
+/// Returns the administrative actions and role changes applied to the
+/// account, newest first, from the audit log.
+#[utoipa::path(
+    get,
+    path = "/api/admin/users/{id}/actions",
+    params(
+        ("id" = i32, Path, description = "User id")
+    ),
+    responses(
+        (status = 200, description = "Actions retrieved successfully", body = [AuditEvent]),
+        (status = 403, description = "Requires the users:read permission")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Admin"
+)]
+pub async fn get_user_actions(
+    State(pool): State<Pool<Postgres>>,
+    Path(user_id): Path<i32>,
+) -> Result<Json<Vec<AuditEvent>>, (StatusCode, Json<String>)> {
+    list_admin_actions(&pool, user_id)
+        .await
+        .map(Json)
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json("Failed to fetch actions".to_string()),
+            )
+        })
+}
+
+/// Impersonate a user
+///
+/// Mints a short-lived token acting as the user, for support staff to see the
+/// app as they do. The token carries an `act` claim naming the administrator;
+/// it cannot change the user's credentials or mint further tokens, and every
+/// request made with it is recorded in the user's audit trail.
+#[utoipa::path(
+    post,
+    path = "/api/admin/users/{id}/impersonate",
+    params(
+        ("id" = i32, Path, description = "User id")
+    ),
+    request_body = ImpersonationRequest,
+    responses(
+        (status = 200, description = "Impersonation token issued", body = ImpersonationResponse),
+        (status = 400, description = "Invalid input or own account"),
+        (status = 403, description = "Requires the users:impersonate permission, or the target is an administrator"),
+        (status = 404, description = "User not found"),
+        (status = 409, description = "User is disabled")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Admin"
+)]
+pub async fn impersonate(
+    State(pool): State<Pool<Postgres>>,
+    State(permission_cache): State<PermissionCache>,
+    Extension(admin): Extension<User>,
+    client: ClientInfo,
+    Path(user_id): Path<i32>,
+    Json(payload): Json<ImpersonationRequest>,
+) -> Result<Json<ImpersonationResponse>, (StatusCode, Json<String>)> {
+    payload.validate().map_err(validation_error)?;
+    reject_self(&admin, user_id)?;
+
+    let fetch_error = |_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json("Failed to fetch user".to_string()),
+        )
+    };
+    let user = match get_user_by_id(&pool, user_id).await {
+        Ok(user) => user,
+        Err(sqlx::Error::RowNotFound) => return Err(not_found()),
+        Err(e) => return Err(fetch_error(e)),
+    };
+    if user.disabled_at.is_some() {
+        return Err((
+            StatusCode::CONFLICT,
+            Json("Disabled users cannot be impersonated".to_string()),
+        ));
+    }
+
+    // Impersonating another administrator would escalate beyond support access
+    let permissions = permission_cache
+        .get_or_load(&pool, &user)
+        .await
+        .map_err(fetch_error)?;
+    if permissions.contains("admin:access") || permissions.contains("users:impersonate") {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json("Administrators cannot be impersonated".to_string()),
+        ));
+    }
+
+    let ttl = chrono::Duration::minutes(app_config().auth.impersonation_ttl_minutes);
+    let (token, exp) = create_impersonation_token(&user, &admin, &permissions.to_scope(), ttl);
+    let expires_at = chrono::DateTime::from_timestamp(exp as i64, 0).unwrap_or_default();
+
+    log_admin_action(
+        &pool,
+        &client.audit(Some(admin.id)),
+        user.id,
+        "impersonate",
+        json!({ "reason": payload.reason, "expires_at": expires_at }),
+    )
+    .await
+    .map_err(update_error)?;
+    tracing::warn!(admin_id = admin.id, user_id = user.id, %expires_at, "Admin impersonating user");
+
+    let user = get_user_summary(&pool, user_id)
+        .await
+        .map_err(fetch_error)?
+        .ok_or_else(not_found)?;
+
+    Ok(Json(ImpersonationResponse {
+        token,
+        expires_at,
+        user,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::config::init_for_tests, middleware::auth::decode_token, test_support::insert_user,
+    };
+
+    async fn impersonate_as(
+        pool: &Pool<Postgres>,
+        admin: &User,
+        user_id: i32,
+    ) -> Result<Json<ImpersonationResponse>, (StatusCode, Json<String>)> {
+        impersonate(
+            State(pool.clone()),
+            State(PermissionCache::default()),
+            Extension(admin.clone()),
+            ClientInfo::default(),
+            Path(user_id),
+            Json(ImpersonationRequest { reason: "Ticket #42".to_string() }),
+        )
+        .await
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn administrators_cannot_be_impersonated(pool: Pool<Postgres>) {
+        init_for_tests();
+        let admin = insert_user(&pool, "admin@example.com", "Admin").await;
+        let other_admin = insert_user(&pool, "other-admin@example.com", "Admin").await;
+
+        let (status, Json(message)) = impersonate_as(&pool, &admin, other_admin.id).await.unwrap_err();
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(message, "Administrators cannot be impersonated");
+        let (status, _) = impersonate_as(&pool, &admin, admin.id).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let events: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM audit_events WHERE details->>'action' = 'impersonate'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(events, 0);
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn impersonation_tokens_name_the_administrator(pool: Pool<Postgres>) {
+        init_for_tests();
+        let admin = insert_user(&pool, "admin@example.com", "Admin").await;
+        let user = insert_user(&pool, "user@example.com", "User").await;
+
+        let Json(response) = impersonate_as(&pool, &admin, user.id).await.unwrap();
+        assert_eq!(response.user.id, user.id);
+        let claims = decode_token(&response.token).unwrap();
+        assert_eq!(claims.sub, "user@example.com");
+        let actor = claims.act.unwrap();
+        assert_eq!((actor.id, actor.sub.as_str()), (admin.id, "admin@example.com"));
+
+        let (actor_id, target, details): (Option<i32>, Option<i32>, serde_json::Value) = sqlx::query_as(
+            "SELECT actor_id, target_user_id, details FROM audit_events WHERE details->>'action' = 'impersonate'",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!((actor_id, target), (Some(admin.id), Some(user.id)));
+        assert_eq!(details["reason"], "Ticket #42");
+
+        sqlx::query("UPDATE users SET disabled_at = NOW() WHERE id = $1")
+            .bind(user.id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let (status, _) = impersonate_as(&pool, &admin, user.id).await.unwrap_err();
+        assert_eq!(status, StatusCode::CONFLICT);
+    }
+}
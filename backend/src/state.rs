@@ -0,0 +1,54 @@
+use axum::extract::FromRef;
+use metrics_exporter_prometheus::PrometheusHandle;
+use sqlx::{Pool, Postgres};
+use std::sync::Arc;
+use tokio::sync::watch;
+
+use crate::{
+    authz::schema::AuthzSchema, mail::Mailer, middleware::permissions::PermissionCache,
+    notify::Notifier, policy::schema::PolicySet, routes::health::ReadinessCache,
+    storage::BlobStore,
+};
+
+/// Shared application state; handlers extract the individual parts via `State<T>`
+#[derive(Clone, FromRef)]
+pub struct AppState {
+    pub pool: Pool<Postgres>,
+    pub permissions: PermissionCache,
+    pub authz_schema: Arc<AuthzSchema>,
+    pub policies: Arc<PolicySet>,
+    pub mailer: Arc<dyn Mailer>,
+    pub notifier: Arc<dyn Notifier>,
+    pub blobs: Arc<dyn BlobStore>,
+    pub metrics: PrometheusHandle,
+    pub draining: Draining,
+    pub readiness: ReadinessCache,
+}
+
+/// Raised once shutdown begins, so readiness turns away new traffic while
+/// in-flight requests finish
+#[derive(Clone)]
+pub struct Draining(Arc<watch::Sender<bool>>);
+
+impl Default for Draining {
+    fn default() -> Self {
+        Self(Arc::new(watch::Sender::new(false)))
+    }
+}
+
+impl Draining {
+    pub fn start(&self) {
+        self.0.send_replace(true);
+    }
+
+    pub fn is_draining(&self) -> bool {
+        *self.0.borrow()
+    }
+
+    /// Resolves once draining has started
+    pub async fn wait(&self) {
+        let mut draining = self.0.subscribe();
+        // The sender lives in `self`, so the channel cannot close while waiting
+        let _ = draining.wait_for(|draining| *draining).await;
+    }
+}
@@ -0,0 +1,186 @@
+use axum::{async_trait, body::Bytes};
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use super::{content_type_for, is_valid_key, Blob, BlobInfo, BlobStore};
+
+/// Stores objects as files under a local directory. Only suitable for a single
+/// instance with a persistent disk.
+pub struct LocalBlobStore {
+    root: PathBuf,
+}
+
+impl LocalBlobStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        LocalBlobStore { root: root.into() }
+    }
+
+    fn path(&self, key: &str) -> Result<PathBuf, String> {
+        if !is_valid_key(key) {
+            return Err(format!("Invalid storage key: {}", key));
+        }
+        Ok(self.root.join(key))
+    }
+}
+
+#[async_trait]
+impl BlobStore for LocalBlobStore {
+    async fn put(&self, key: &str, data: Bytes, _content_type: &str) -> Result<(), String> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        tokio::fs::write(&path, data)
+            .await
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Blob>, String> {
+        let path = self.path(key)?;
+        match tokio::fs::read(&path).await {
+            Ok(data) => Ok(Some(Blob {
+                data: Bytes::from(data),
+                content_type: Some(content_type_for(key).to_string()),
+            })),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), String> {
+        let path = self.path(key)?;
+        match tokio::fs::remove_file(&path).await {
+            Err(e) if e.kind() != ErrorKind::NotFound => {
+                Err(format!("Failed to delete {}: {}", path.display(), e))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<BlobInfo>, String> {
+        let mut blobs = Vec::new();
+        let mut pending = vec![(self.root.clone(), String::new())];
+        while let Some((dir, dir_key)) = pending.pop() {
+            let mut entries = match tokio::fs::read_dir(&dir).await {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == ErrorKind::NotFound => continue,
+                Err(e) => return Err(format!("Failed to list {}: {}", dir.display(), e)),
+            };
+            while let Some(entry) = entries
+                .next_entry()
+                .await
+                .map_err(|e| format!("Failed to list {}: {}", dir.display(), e))?
+            {
+                let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+                    continue;
+                };
+                let key = format!("{}{}", dir_key, name);
+                let metadata = entry
+                    .metadata()
+                    .await
+                    .map_err(|e| format!("Failed to stat {}: {}", key, e))?;
+                if metadata.is_dir() {
+                    // Only descend into directories that can contain matching keys
+                    let dir_prefix = format!("{}/", key);
+                    if dir_prefix.starts_with(prefix) || prefix.starts_with(&dir_prefix) {
+                        pending.push((entry.path(), dir_prefix));
+                    }
+                } else if key.starts_with(prefix) && is_valid_key(&key) {
+                    blobs.push(BlobInfo {
+                        key,
+                        last_modified: metadata.modified().ok().map(Into::into),
+                    });
+                }
+            }
+        }
+        Ok(blobs)
+    }
+
+    async fn presigned_url(&self, _key: &str, _ttl: Duration) -> Result<Option<String>, String> {
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::ScratchDir;
+
+    fn keys(mut blobs: Vec<BlobInfo>) -> Vec<String> {
+        blobs.sort_by(|a, b| a.key.cmp(&b.key));
+        blobs.into_iter().map(|blob| blob.key).collect()
+    }
+
+    #[tokio::test]
+    async fn stores_reads_and_deletes_objects() {
+        let dir = ScratchDir::new();
+        let store = LocalBlobStore::new(dir.path());
+
+        store.put("avatars/1/256.webp", Bytes::from_static(b"webp"), "image/webp").await.unwrap();
+        let blob = store.get("avatars/1/256.webp").await.unwrap().expect("object exists");
+        assert_eq!(blob.data, Bytes::from_static(b"webp"));
+        assert_eq!(blob.content_type.as_deref(), Some("image/webp"));
+
+        store.delete("avatars/1/256.webp").await.unwrap();
+        assert!(store.get("avatars/1/256.webp").await.unwrap().is_none());
+        // Deleting again is not an error
+        store.delete("avatars/1/256.webp").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn missing_objects_are_none() {
+        let dir = ScratchDir::new();
+        let store = LocalBlobStore::new(dir.path());
+        assert!(store.get("missing.jpg").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn lists_keys_under_a_prefix() {
+        let dir = ScratchDir::new();
+        let store = LocalBlobStore::new(dir.path());
+        for key in ["legacy.jpg", "avatars/1/64.jpg", "avatars/1/256.webp", "avatars/2/64.jpg", "staging/x"] {
+            store.put(key, Bytes::from_static(b"x"), "image/jpeg").await.unwrap();
+        }
+
+        assert_eq!(
+            keys(store.list("avatars/1/").await.unwrap()),
+            ["avatars/1/256.webp", "avatars/1/64.jpg"]
+        );
+        assert_eq!(
+            keys(store.list("avatars/").await.unwrap()),
+            ["avatars/1/256.webp", "avatars/1/64.jpg", "avatars/2/64.jpg"]
+        );
+        assert_eq!(keys(store.list("").await.unwrap()).len(), 5);
+        assert!(store.list("avatars/1/").await.unwrap()[0].last_modified.is_some());
+    }
+
+    #[tokio::test]
+    async fn listing_a_missing_root_is_empty() {
+        let dir = ScratchDir::new();
+        let store = LocalBlobStore::new(dir.path().join("not-created"));
+        assert!(store.list("").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn refuses_keys_escaping_the_root() {
+        let dir = ScratchDir::new();
+        let store = LocalBlobStore::new(dir.path().join("root"));
+        std::fs::write(dir.path().join("outside.txt"), b"secret").unwrap();
+
+        assert!(store.get("../outside.txt").await.is_err());
+        assert!(store.put("../outside.txt", Bytes::new(), "text/plain").await.is_err());
+        assert!(store.delete("../outside.txt").await.is_err());
+        assert!(store.put("/etc/passwd", Bytes::new(), "text/plain").await.is_err());
+        assert_eq!(std::fs::read(dir.path().join("outside.txt")).unwrap(), b"secret");
+    }
+
+    #[tokio::test]
+    async fn has_no_presigned_urls() {
+        let dir = ScratchDir::new();
+        let store = LocalBlobStore::new(dir.path());
+        assert!(store.presigned_url("a.jpg", Duration::from_secs(60)).await.unwrap().is_none());
+    }
+}
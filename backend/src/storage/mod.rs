@@ -0,0 +1,123 @@
+pub mod local;
+pub mod photos;
+pub mod s3;
+
+use axum::{async_trait, body::Bytes};
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::config::config::{app_config, StorageBackend};
+
+/// A stored object
+#[derive(Debug, Clone)]
+pub struct Blob {
+    pub data: Bytes,
+    pub content_type: Option<String>,
+}
+
+/// A stored object's key and age, as listed
+#[derive(Debug, Clone)]
+pub struct BlobInfo {
+    pub key: String,
+    pub last_modified: Option<DateTime<Utc>>,
+}
+
+/// Object storage for uploaded files, addressed by `/`-separated keys
+#[async_trait]
+pub trait BlobStore: Send + Sync {
+    async fn put(&self, key: &str, data: Bytes, content_type: &str) -> Result<(), String>;
+    /// The object, or `None` if there is none under `key`
+    async fn get(&self, key: &str) -> Result<Option<Blob>, String>;
+    /// Removes the object; deleting a missing key is not an error
+    async fn delete(&self, key: &str) -> Result<(), String>;
+    /// Every object whose key starts with `prefix`
+    async fn list(&self, prefix: &str) -> Result<Vec<BlobInfo>, String>;
+    /// A URL granting temporary read access without going through the API,
+    /// or `None` when the backend cannot issue one
+    async fn presigned_url(&self, key: &str, ttl: Duration) -> Result<Option<String>, String>;
+}
+
+/// Whether `key` is safe to use as a storage key: relative, without `..`
+/// segments, and limited to URL-safe characters
+pub fn is_valid_key(key: &str) -> bool {
+    !key.is_empty()
+        && key.split('/').all(|segment| {
+            !segment.is_empty()
+                && segment != "."
+                && segment != ".."
+                && segment
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        })
+}
+
+/// Content type of the files we store, from the key's extension
+pub fn content_type_for(key: &str) -> &'static str {
+    match key.rsplit('.').next().map(|ext| ext.to_lowercase()).as_deref() {
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("png") => "image/png",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("svg") => "image/svg+xml",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Builds the store selected by `storage.backend`
+pub fn from_config() -> Arc<dyn BlobStore> {
+    let config = &app_config().storage;
+    match config.backend {
+        StorageBackend::Local => Arc::new(local::LocalBlobStore::new(config.path.clone())),
+        StorageBackend::S3 => {
+            Arc::new(s3::S3BlobStore::from_config(&config.s3).expect("Invalid S3 storage configuration"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_relative_url_safe_keys() {
+        for key in ["photo.jpg", "avatars/42/256.webp", "staging/abc-DEF_1.bin", "a/b/c/.hidden"] {
+            assert!(is_valid_key(key), "{} should be valid", key);
+        }
+    }
+
+    #[test]
+    fn rejects_traversal_and_empty_segments() {
+        for key in [
+            "",
+            "..",
+            ".",
+            "../secret",
+            "avatars/../../etc/passwd",
+            "avatars/./1.jpg",
+            "avatars/..",
+            "/absolute.jpg",
+            "trailing/",
+            "double//slash.jpg",
+        ] {
+            assert!(!is_valid_key(key), "{:?} should be rejected", key);
+        }
+    }
+
+    #[test]
+    fn rejects_characters_outside_the_safe_set() {
+        for key in ["back\\slash", "space here.jpg", "query?.jpg", "caf\u{e9}.jpg", "nul\0.jpg", "c:/file"] {
+            assert!(!is_valid_key(key), "{:?} should be rejected", key);
+        }
+    }
+
+    #[test]
+    fn content_type_follows_the_extension() {
+        assert_eq!(content_type_for("a/photo.JPG"), "image/jpeg");
+        assert_eq!(content_type_for("a/photo.jpeg"), "image/jpeg");
+        assert_eq!(content_type_for("256.webp"), "image/webp");
+        assert_eq!(content_type_for("avatar.svg"), "image/svg+xml");
+        assert_eq!(content_type_for("upload"), "application/octet-stream");
+        assert_eq!(content_type_for("archive.tar.gz"), "application/octet-stream");
+    }
+}
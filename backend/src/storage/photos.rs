@@ -0,0 +1,250 @@
+use chrono::{Duration, Utc};
+use sqlx::{Pool, Postgres};
+use std::collections::HashSet;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use super::{is_valid_key, BlobStore};
+use crate::{
+    db::{
+        photo_uploads::{delete_expired_photo_uploads, list_photo_upload_chunk_keys},
+        queries::{count_profile_picture_references, list_profile_pictures},
+    },
+    imaging::PHOTO_SIZES,
+};
+
+/// Storage key prefix of profile photo variant sets
+pub const PHOTO_PREFIX: &str = "photos";
+/// Storage key prefix of resumable upload chunks awaiting completion
+pub const STAGING_PREFIX: &str = "staging";
+/// Extensions every variant set holds, for each size
+const PHOTO_EXTENSIONS: [&str; 2] = ["jpg", "webp"];
+/// Extensions single-file uploads were accepted with, before variant sets
+const LEGACY_EXTENSIONS: [&str; 4] = ["jpg", "jpeg", "png", "gif"];
+/// Uploads younger than this are never swept, so a photo being stored is not
+/// collected before its user row points at it
+const SWEEP_GRACE_MINUTES: i64 = 60;
+
+/// Whether `key` names a whole photo variant set, `photos/<id>`, rather than a file
+pub fn is_photo_set(key: &str) -> bool {
+    matches!(key.split_once('/'), Some((PHOTO_PREFIX, id)) if !id.contains(['/', '.']))
+}
+
+/// Whether `key` is a single-file photo from before variant sets, stored at
+/// the root as `<uuid>.<ext>`
+pub fn is_legacy_photo(key: &str) -> bool {
+    match key.rsplit_once('.') {
+        Some((stem, extension)) => {
+            LEGACY_EXTENSIONS.contains(&extension) && !stem.contains('/') && Uuid::parse_str(stem).is_ok()
+        }
+        None => false,
+    }
+}
+
+/// Storage keys behind a `profile_picture` URL; empty for URLs we do not host
+pub fn photo_keys(url: &str) -> Vec<String> {
+    let Some(key) = url.strip_prefix("/uploads/").filter(|key| is_valid_key(key)) else {
+        return Vec::new();
+    };
+    if !is_photo_set(key) {
+        // A single file, as uploaded before variant sets
+        return vec![key.to_string()];
+    }
+    PHOTO_SIZES
+        .iter()
+        .flat_map(|size| PHOTO_EXTENSIONS.iter().map(move |ext| format!("{}/{}.{}", key, size, ext)))
+        .collect()
+}
+
+/// Deletes the photo at `url` once no user points at it any more.
+/// Failures are logged; the sweep retries them.
+pub async fn release_photo(pool: &Pool<Postgres>, blobs: &dyn BlobStore, url: &str) {
+    match count_profile_picture_references(pool, url).await {
+        Ok(0) => {}
+        Ok(_) => return,
+        Err(e) => {
+            tracing::warn!(error = %e, "Failed to check photo references");
+            return;
+        }
+    }
+    for key in photo_keys(url) {
+        if let Err(e) = blobs.delete(&key).await {
+            tracing::warn!(error = %e, "Failed to delete photo");
+        }
+    }
+}
+
+/// Deletes stored photos no user points at, and staged upload chunks no
+/// upload holds, returning how many files were removed. Keys outside the
+/// photo and staging prefixes, other than legacy photos, are left alone.
+pub async fn sweep_orphaned_photos(
+    pool: &Pool<Postgres>,
+    blobs: &dyn BlobStore,
+) -> Result<usize, String> {
+    let referenced: HashSet<String> = list_profile_pictures(pool)
+        .await
+        .map_err(|e| format!("Failed to list profile pictures: {}", e))?
+        .iter()
+        .flat_map(|url| photo_keys(url))
+        .collect();
+    let staged: HashSet<String> = list_photo_upload_chunk_keys(pool)
+        .await
+        .map_err(|e| format!("Failed to list upload chunks: {}", e))?
+        .into_iter()
+        .collect();
+
+    let cutoff = Utc::now() - Duration::minutes(SWEEP_GRACE_MINUTES);
+    let photo_prefix = format!("{}/", PHOTO_PREFIX);
+    let staging_prefix = format!("{}/", STAGING_PREFIX);
+    let mut removed = 0;
+    for blob in blobs.list("").await? {
+        let orphaned = if blob.key.starts_with(&photo_prefix) || is_legacy_photo(&blob.key) {
+            !referenced.contains(&blob.key)
+        } else if blob.key.starts_with(&staging_prefix) {
+            // Chunks whose upload completed or failed before its row was written
+            !staged.contains(&blob.key)
+        } else {
+            false
+        };
+        let settled = !matches!(blob.last_modified, Some(modified) if modified >= cutoff);
+        if orphaned && settled {
+            blobs.delete(&blob.key).await?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// Deletes expired resumable uploads and their chunks, returning how many chunks were removed
+pub async fn expire_photo_uploads(
+    pool: &Pool<Postgres>,
+    blobs: &dyn BlobStore,
+) -> Result<usize, String> {
+    let keys = delete_expired_photo_uploads(pool)
+        .await
+        .map_err(|e| format!("Failed to expire uploads: {}", e))?;
+    for key in &keys {
+        if let Err(e) = blobs.delete(key).await {
+            tracing::warn!(error = %e, "Failed to delete upload chunk");
+        }
+    }
+    Ok(keys.len())
+}
+
+/// Runs `sweep_orphaned_photos` and `expire_photo_uploads` every
+/// `interval_minutes`, starting now
+pub fn spawn_photo_sweeper(pool: Pool<Postgres>, blobs: Arc<dyn BlobStore>, interval_minutes: u64) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(interval_minutes * 60));
+        loop {
+            interval.tick().await;
+            match sweep_orphaned_photos(&pool, blobs.as_ref()).await {
+                Ok(0) => {}
+                Ok(removed) => tracing::info!(removed, "Removed orphaned photo files"),
+                Err(e) => tracing::error!(error = %e, "Photo sweep failed"),
+            }
+            match expire_photo_uploads(&pool, blobs.as_ref()).await {
+                Ok(0) => {}
+                Ok(removed) => tracing::info!(removed, "Removed chunks of expired photo uploads"),
+                Err(e) => tracing::error!(error = %e, "Upload expiry failed"),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::photo_uploads::{append_photo_upload_chunk, create_photo_upload},
+        storage::local::LocalBlobStore,
+        test_support::{insert_user, ScratchDir},
+    };
+    use axum::body::Bytes;
+    use std::time::{Duration as StdDuration, SystemTime};
+
+    const LEGACY: &str = "0b7d5c7e-3f0a-4c4e-9a55-1d2f3e4a5b6c.png";
+
+    #[test]
+    fn recognises_legacy_photo_names() {
+        assert!(is_legacy_photo(LEGACY));
+        assert!(is_legacy_photo("0b7d5c7e-3f0a-4c4e-9a55-1d2f3e4a5b6c.jpeg"));
+        assert!(!is_legacy_photo("0b7d5c7e-3f0a-4c4e-9a55-1d2f3e4a5b6c.webp"));
+        assert!(!is_legacy_photo("robots.txt"));
+        assert!(!is_legacy_photo("logo.png"));
+        assert!(!is_legacy_photo("photos/0b7d5c7e-3f0a-4c4e-9a55-1d2f3e4a5b6c.png"));
+        assert!(!is_legacy_photo("0b7d5c7e-3f0a-4c4e-9a55-1d2f3e4a5b6c"));
+    }
+
+    #[test]
+    fn photo_keys_cover_every_variant() {
+        let keys = photo_keys("/uploads/photos/abc");
+        assert_eq!(keys.len(), PHOTO_SIZES.len() * PHOTO_EXTENSIONS.len());
+        assert!(keys.contains(&"photos/abc/64.jpg".to_string()));
+        assert!(keys.contains(&"photos/abc/512.webp".to_string()));
+
+        assert_eq!(photo_keys(&format!("/uploads/{}", LEGACY)), [LEGACY]);
+        assert!(photo_keys("https://example.com/me.png").is_empty());
+        assert!(photo_keys("/uploads/../secret").is_empty());
+    }
+
+    #[test]
+    fn photo_sets_are_single_segments_under_the_prefix() {
+        assert!(is_photo_set("photos/abc"));
+        assert!(!is_photo_set("photos/abc/64.jpg"));
+        assert!(!is_photo_set("photos/abc.jpg"));
+        assert!(!is_photo_set("staging/abc"));
+    }
+
+    async fn store(blobs: &LocalBlobStore, root: &std::path::Path, key: &str, age_minutes: u64) {
+        blobs.put(key, Bytes::from_static(b"x"), "image/jpeg").await.unwrap();
+        let modified = SystemTime::now() - StdDuration::from_secs(age_minutes * 60);
+        std::fs::File::options()
+            .write(true)
+            .open(root.join(key))
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn sweep_only_collects_unreferenced_photos_and_chunks(pool: Pool<Postgres>) {
+        let dir = ScratchDir::new();
+        let blobs = LocalBlobStore::new(dir.path());
+        let user = insert_user(&pool, "user@example.com", "User").await;
+        sqlx::query("UPDATE users SET profile_picture = '/uploads/photos/kept' WHERE id = $1")
+            .bind(user.id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let upload = create_photo_upload(&pool, user.id, 10, &"0".repeat(64), Utc::now() + Duration::hours(1))
+            .await
+            .unwrap();
+        append_photo_upload_chunk(&pool, user.id, upload.id, 0, 1, "staging/held/000000000000-a")
+            .await
+            .unwrap()
+            .unwrap();
+
+        let kept = [
+            "photos/kept/64.jpg",
+            "photos/kept/512.webp",
+            "staging/held/000000000000-a",
+            "robots.txt",
+            "logo.png",
+            "backups/0b7d5c7e-3f0a-4c4e-9a55-1d2f3e4a5b6c.png",
+        ];
+        let removed = ["photos/gone/64.jpg", LEGACY, "staging/done/000000000000-b"];
+        for key in kept.iter().chain(&removed) {
+            store(&blobs, dir.path(), key, 120).await;
+        }
+        store(&blobs, dir.path(), "photos/fresh/64.jpg", 0).await;
+
+        assert_eq!(sweep_orphaned_photos(&pool, &blobs).await.unwrap(), removed.len());
+        let mut remaining: Vec<String> = blobs.list("").await.unwrap().into_iter().map(|blob| blob.key).collect();
+        remaining.sort();
+        let mut expected: Vec<String> = kept.iter().chain(&["photos/fresh/64.jpg"]).map(|key| key.to_string()).collect();
+        expected.sort();
+        assert_eq!(remaining, expected);
+    }
+}
@@ -0,0 +1,298 @@
+use axum::{async_trait, body::Bytes};
+use chrono::{DateTime, Utc};
+use s3::{creds::Credentials, Bucket, Region};
+use std::time::Duration;
+
+use super::{is_valid_key, Blob, BlobInfo, BlobStore};
+use crate::config::config::S3Config;
+
+/// Stores objects in an S3-compatible bucket.
+///
+/// Setting `storage.s3.endpoint` targets another provider or a local stand-in such as
+/// MinIO (`http://localhost:9000`), addressed path-style.
+pub struct S3BlobStore {
+    bucket: Bucket,
+}
+
+impl S3BlobStore {
+    pub fn from_config(config: &S3Config) -> Result<Self, String> {
+        let region = match &config.endpoint {
+            Some(endpoint) => Region::Custom {
+                region: config.region.clone(),
+                endpoint: endpoint.clone(),
+            },
+            None => config
+                .region
+                .parse()
+                .map_err(|e| format!("Invalid storage.s3.region: {}", e))?,
+        };
+        let credentials = Credentials::new(
+            config.access_key_id.as_deref(),
+            config.secret_access_key.as_ref().map(|key| key.expose()),
+            None,
+            None,
+            None,
+        )
+        .map_err(|e| format!("Invalid S3 credentials: {}", e))?;
+
+        let bucket_name = config.bucket.as_deref().ok_or("storage.s3.bucket must be set")?;
+        let bucket = Bucket::new(bucket_name, region, credentials)
+            .map_err(|e| format!("Invalid S3 bucket: {}", e))?;
+        // Custom endpoints rarely have wildcard DNS for virtual-hosted buckets
+        let bucket = if config.endpoint.is_some() {
+            bucket.with_path_style()
+        } else {
+            bucket
+        };
+        Ok(S3BlobStore { bucket })
+    }
+
+    fn check_key(key: &str) -> Result<(), String> {
+        if is_valid_key(key) {
+            Ok(())
+        } else {
+            Err(format!("Invalid storage key: {}", key))
+        }
+    }
+}
+
+#[async_trait]
+impl BlobStore for S3BlobStore {
+    async fn put(&self, key: &str, data: Bytes, content_type: &str) -> Result<(), String> {
+        Self::check_key(key)?;
+        let response = self
+            .bucket
+            .put_object_with_content_type(key, &data, content_type)
+            .await
+            .map_err(|e| format!("Failed to upload {}: {}", key, e))?;
+        match response.status_code() {
+            200..=299 => Ok(()),
+            status => Err(format!("Failed to upload {}: HTTP {}", key, status)),
+        }
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Blob>, String> {
+        Self::check_key(key)?;
+        let response = self
+            .bucket
+            .get_object(key)
+            .await
+            .map_err(|e| format!("Failed to download {}: {}", key, e))?;
+        match response.status_code() {
+            200..=299 => Ok(Some(Blob {
+                content_type: response.headers().get("content-type").cloned(),
+                data: response.bytes().clone(),
+            })),
+            404 => Ok(None),
+            status => Err(format!("Failed to download {}: HTTP {}", key, status)),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), String> {
+        Self::check_key(key)?;
+        let response = self
+            .bucket
+            .delete_object(key)
+            .await
+            .map_err(|e| format!("Failed to delete {}: {}", key, e))?;
+        match response.status_code() {
+            200..=299 | 404 => Ok(()),
+            status => Err(format!("Failed to delete {}: HTTP {}", key, status)),
+        }
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<BlobInfo>, String> {
+        let pages = self
+            .bucket
+            .list(prefix.to_string(), None)
+            .await
+            .map_err(|e| format!("Failed to list {}: {}", prefix, e))?;
+        Ok(pages
+            .into_iter()
+            .flat_map(|page| page.contents)
+            .map(|object| BlobInfo {
+                last_modified: DateTime::parse_from_rfc3339(&object.last_modified)
+                    .ok()
+                    .map(|time| time.with_timezone(&Utc)),
+                key: object.key,
+            })
+            .collect())
+    }
+
+    async fn presigned_url(&self, key: &str, ttl: Duration) -> Result<Option<String>, String> {
+        Self::check_key(key)?;
+        self.bucket
+            .presign_get(key, ttl.as_secs() as u32, None)
+            .map(Some)
+            .map_err(|e| format!("Failed to presign {}: {}", key, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::config::Secret;
+    use axum::{
+        extract::{Query, State},
+        http::{HeaderMap, Method, StatusCode, Uri},
+        response::{IntoResponse, Response},
+        Router,
+    };
+    use std::{
+        collections::{BTreeMap, HashMap},
+        sync::{Arc, Mutex},
+    };
+    use tokio::net::TcpListener;
+
+    const BUCKET: &str = "photos";
+
+    /// Objects by key, with their content type
+    type Objects = Arc<Mutex<BTreeMap<String, (Bytes, String)>>>;
+
+    /// Just enough of the S3 API, path-style like MinIO, to back the store:
+    /// object PUT/GET/DELETE and ListObjectsV2. Unsigned requests are refused.
+    async fn fake_s3(
+        State(objects): State<Objects>,
+        method: Method,
+        uri: Uri,
+        Query(query): Query<HashMap<String, String>>,
+        headers: HeaderMap,
+        body: Bytes,
+    ) -> Response {
+        let signed = headers
+            .get("authorization")
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.starts_with("AWS4-HMAC-SHA256 Credential=minio/"));
+        if !signed {
+            return StatusCode::FORBIDDEN.into_response();
+        }
+        let Some(path) = uri.path().strip_prefix(&format!("/{}", BUCKET)) else {
+            return StatusCode::NOT_FOUND.into_response();
+        };
+        let key = path.trim_start_matches('/').to_string();
+        let mut objects = objects.lock().unwrap();
+
+        match (method, key.is_empty()) {
+            (Method::GET, true) => {
+                let prefix = query.get("prefix").cloned().unwrap_or_default();
+                let contents: String = objects
+                    .iter()
+                    .filter(|(key, _)| key.starts_with(&prefix))
+                    .map(|(key, (data, _))| {
+                        format!(
+                            "<Contents><Key>{}</Key><LastModified>2025-07-01T12:00:00.000Z</LastModified>\
+                             <ETag>\"etag\"</ETag><Size>{}</Size><StorageClass>STANDARD</StorageClass></Contents>",
+                            key,
+                            data.len()
+                        )
+                    })
+                    .collect();
+                let xml = format!(
+                    "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+                     <ListBucketResult><Name>{}</Name><Prefix>{}</Prefix><MaxKeys>1000</MaxKeys>\
+                     <IsTruncated>false</IsTruncated>{}</ListBucketResult>",
+                    BUCKET, prefix, contents
+                );
+                ([("content-type", "application/xml")], xml).into_response()
+            }
+            (Method::PUT, false) => {
+                let content_type = headers
+                    .get("content-type")
+                    .and_then(|v| v.to_str().ok())
+                    .unwrap_or_default()
+                    .to_string();
+                objects.insert(key, (body, content_type));
+                StatusCode::OK.into_response()
+            }
+            (Method::GET, false) => match objects.get(&key) {
+                Some((data, content_type)) => {
+                    ([("content-type", content_type.clone())], data.clone()).into_response()
+                }
+                None => StatusCode::NOT_FOUND.into_response(),
+            },
+            (Method::DELETE, false) => {
+                objects.remove(&key);
+                StatusCode::NO_CONTENT.into_response()
+            }
+            _ => StatusCode::METHOD_NOT_ALLOWED.into_response(),
+        }
+    }
+
+    /// Starts the stand-in on a local port and a store pointed at it
+    async fn start() -> (S3BlobStore, Objects, String) {
+        let objects = Objects::default();
+        let app = Router::new().fallback(fake_s3).with_state(objects.clone());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let store = S3BlobStore::from_config(&S3Config {
+            bucket: Some(BUCKET.to_string()),
+            region: "us-east-1".to_string(),
+            endpoint: Some(endpoint.clone()),
+            access_key_id: Some("minio".to_string()),
+            secret_access_key: Some(serde_json::from_str::<Secret>("\"minio-secret\"").unwrap()),
+        })
+        .unwrap();
+        (store, objects, endpoint)
+    }
+
+    #[tokio::test]
+    async fn stores_reads_and_deletes_objects() {
+        let (store, objects, _) = start().await;
+
+        store.put("avatars/1/256.webp", Bytes::from_static(b"webp"), "image/webp").await.unwrap();
+        assert_eq!(
+            objects.lock().unwrap().get("avatars/1/256.webp"),
+            Some(&(Bytes::from_static(b"webp"), "image/webp".to_string()))
+        );
+
+        let blob = store.get("avatars/1/256.webp").await.unwrap().expect("object exists");
+        assert_eq!(blob.data, Bytes::from_static(b"webp"));
+        assert_eq!(blob.content_type.as_deref(), Some("image/webp"));
+
+        store.delete("avatars/1/256.webp").await.unwrap();
+        assert!(objects.lock().unwrap().is_empty());
+        assert!(store.get("avatars/1/256.webp").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn lists_keys_under_a_prefix() {
+        let (store, _, _) = start().await;
+        for key in ["legacy.jpg", "avatars/1/64.jpg", "avatars/1/256.webp", "avatars/2/64.jpg"] {
+            store.put(key, Bytes::from_static(b"x"), "image/jpeg").await.unwrap();
+        }
+
+        let listed = store.list("avatars/1/").await.unwrap();
+        let keys: Vec<&str> = listed.iter().map(|blob| blob.key.as_str()).collect();
+        assert_eq!(keys, ["avatars/1/256.webp", "avatars/1/64.jpg"]);
+        let expected = DateTime::parse_from_rfc3339("2025-07-01T12:00:00Z").unwrap();
+        assert_eq!(listed[0].last_modified, Some(expected.with_timezone(&Utc)));
+        assert_eq!(store.list("").await.unwrap().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn presigns_path_style_urls() {
+        let (store, _, endpoint) = start().await;
+
+        let url = store
+            .presigned_url("avatars/1/256.webp", Duration::from_secs(300))
+            .await
+            .unwrap()
+            .expect("S3 presigns");
+        assert!(url.starts_with(&format!("{}/{}/avatars/1/256.webp?", endpoint, BUCKET)), "{}", url);
+        assert!(url.contains("X-Amz-Expires=300"), "{}", url);
+        assert!(url.contains("X-Amz-Signature="), "{}", url);
+    }
+
+    #[tokio::test]
+    async fn refuses_invalid_keys_before_any_request() {
+        let (store, objects, _) = start().await;
+
+        assert!(store.put("../escape", Bytes::new(), "text/plain").await.is_err());
+        assert!(store.get("a//b").await.is_err());
+        assert!(store.delete("").await.is_err());
+        assert!(store.presigned_url("../escape", Duration::from_secs(60)).await.is_err());
+        assert!(objects.lock().unwrap().is_empty());
+    }
+}
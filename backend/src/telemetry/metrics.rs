@@ -0,0 +1,80 @@
+use axum::{
+    extract::{MatchedPath, Request},
+    middleware::Next,
+    response::Response,
+};
+use metrics::{counter, gauge, histogram, Label};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+use sqlx::{Pool, Postgres};
+use std::time::Instant;
+
+/// Latency buckets in seconds, from a cache hit up to a slow bcrypt hash
+const LATENCY_BUCKETS: [f64; 12] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+/// Installs the global Prometheus recorder; the handle renders the scrape output
+pub fn install() -> PrometheusHandle {
+    PrometheusBuilder::new()
+        .set_buckets_for_metric(Matcher::Suffix("_seconds".to_string()), &LATENCY_BUCKETS)
+        .expect("latency buckets are not empty")
+        .install_recorder()
+        .expect("Failed to install the metrics recorder")
+}
+
+/// Counts requests and their latency by route template, method and status,
+/// and names the request span after the route. Applied as a route layer, so
+/// the matched template keeps path parameters such as user ids out of the
+/// labels.
+pub async fn track_http(request: Request, next: Next) -> Response {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+    let method = request.method().to_string();
+    let started = Instant::now();
+
+    let response = next.run(request).await;
+    super::record_route(&method, &route, response.status().as_u16());
+
+    let labels = vec![
+        Label::new("route", route),
+        Label::new("method", method),
+        Label::new("status", response.status().as_u16().to_string()),
+    ];
+    counter!("http_requests_total", labels.clone()).increment(1);
+    histogram!("http_request_duration_seconds", labels).record(started.elapsed().as_secs_f64());
+    response
+}
+
+/// Publishes connection pool usage; called on each scrape
+pub fn record_pool(pool: &Pool<Postgres>) {
+    let size = pool.size();
+    let idle = pool.num_idle() as u32;
+    gauge!("db_pool_connections", "state" => "idle").set(idle as f64);
+    gauge!("db_pool_connections", "state" => "in_use").set(size.saturating_sub(idle) as f64);
+    gauge!("db_pool_max_connections").set(pool.options().get_max_connections() as f64);
+}
+
+/// Login attempts; `reason` is `none` on success and the failure reason otherwise
+pub fn login(outcome: &'static str, reason: &str) {
+    counter!("auth_logins_total", "outcome" => outcome, "reason" => reason.to_string()).increment(1);
+}
+
+/// Registration attempts by result: `created`, `invalid` or `conflict`
+pub fn registration(result: &'static str) {
+    counter!("auth_registrations_total", "result" => result).increment(1);
+}
+
+/// Bearer token checks by result: `valid` or why the token was refused
+pub fn token_validation(result: &'static str) {
+    counter!("auth_token_validations_total", "result" => result).increment(1);
+}
+
+/// Runs a bcrypt `operation` (`hash` or `verify`) in its own span, recording how long it took
+pub fn time_password<T>(operation: &'static str, f: impl FnOnce() -> T) -> T {
+    let started = Instant::now();
+    let result = tracing::info_span!("bcrypt", operation).in_scope(f);
+    histogram!("password_hash_duration_seconds", "operation" => operation)
+        .record(started.elapsed().as_secs_f64());
+    result
+}
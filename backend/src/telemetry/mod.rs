@@ -0,0 +1,75 @@
+pub mod metrics;
+pub mod otel;
+
+use axum::{body::Body, http::Request};
+use sha2::{Digest, Sha256};
+use tower_http::request_id::RequestId;
+use tracing::Span;
+use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer};
+
+use crate::config::config::{LogConfig, LogFormat, OtelConfig};
+
+/// Installs the global subscriber writing logs to stdout in the configured
+/// format, and exporting spans over OTLP when enabled
+pub fn init(log: &LogConfig, otel: &OtelConfig) {
+    let filter = EnvFilter::try_new(&log.level).expect("log.level is validated at startup");
+    let output = match log.format {
+        LogFormat::Json => fmt::layer()
+            .json()
+            .flatten_event(true)
+            .with_current_span(true)
+            .with_span_list(false)
+            .boxed(),
+        LogFormat::Text => fmt::layer().boxed(),
+    };
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(output)
+        .with(otel::layer(otel))
+        .init();
+}
+
+/// Stands in for an email address in logs: a short hash that ties entries for
+/// the same address together without revealing it
+pub fn email(address: &str) -> String {
+    let digest = Sha256::digest(address.trim().to_lowercase().as_bytes());
+    format!("sha256:{}", &hex::encode(digest)[..12])
+}
+
+/// Opens the span every log line of a request is written in, continuing the
+/// caller's trace from a `traceparent` header. Only the path is recorded, since
+/// query strings can carry tokens; the route and `user_id` are filled in once
+/// known.
+pub fn request_span(request: &Request<Body>) -> Span {
+    let request_id = request
+        .extensions()
+        .get::<RequestId>()
+        .and_then(|id| id.header_value().to_str().ok())
+        .unwrap_or_default();
+    let span = tracing::info_span!(
+        "request",
+        method = %request.method(),
+        path = request.uri().path(),
+        request_id,
+        user_id = tracing::field::Empty,
+        http.route = tracing::field::Empty,
+        http.response.status_code = tracing::field::Empty,
+        otel.name = tracing::field::Empty,
+        otel.kind = "server",
+    );
+    otel::continue_trace(&span, request.headers());
+    span
+}
+
+/// Names the current request's span after the route that handled it
+pub fn record_route(method: &str, route: &str, status: u16) {
+    let span = Span::current();
+    span.record("http.route", route);
+    span.record("http.response.status_code", status);
+    span.record("otel.name", format!("{} {}", method, route));
+}
+
+/// Attaches the authenticated user to the current request's span
+pub fn record_user(user_id: i32) {
+    Span::current().record("user_id", user_id);
+}
@@ -0,0 +1,184 @@
+use axum::http::HeaderMap;
+use opentelemetry::{global, propagation::Extractor, trace::TracerProvider as _, KeyValue};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::{
+    export::trace::SpanExporter,
+    propagation::TraceContextPropagator,
+    runtime,
+    trace::{Config, Sampler, Tracer, TracerProvider},
+    Resource,
+};
+use tracing::Span;
+use tracing_opentelemetry::{OpenTelemetryLayer, OpenTelemetrySpanExt};
+use tracing_subscriber::registry::LookupSpan;
+
+use crate::config::config::OtelConfig;
+
+/// Builds a provider batching finished spans into `exporter`. The OTLP
+/// exporter is used in production; tests can pass an in-memory one.
+pub fn tracer_provider<E>(exporter: E, config: &OtelConfig) -> TracerProvider
+where
+    E: SpanExporter + 'static,
+{
+    TracerProvider::builder()
+        .with_batch_exporter(exporter, runtime::Tokio)
+        .with_config(
+            Config::default()
+                .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(
+                    config.sample_ratio,
+                ))))
+                .with_resource(Resource::new([KeyValue::new(
+                    "service.name",
+                    config.service_name.clone(),
+                )])),
+        )
+        .build()
+}
+
+/// Installs `provider` globally along with W3C trace context propagation,
+/// returning the layer that turns `tracing` spans into OpenTelemetry spans
+pub fn install<S>(provider: TracerProvider) -> OpenTelemetryLayer<S, Tracer>
+where
+    S: tracing::Subscriber + for<'span> LookupSpan<'span>,
+{
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    let tracer = provider.tracer(env!("CARGO_PKG_NAME"));
+    global::set_tracer_provider(provider);
+    tracing_opentelemetry::layer().with_tracer(tracer)
+}
+
+/// The OTLP export layer, when `otel.enabled` is set
+pub fn layer<S>(config: &OtelConfig) -> Option<OpenTelemetryLayer<S, Tracer>>
+where
+    S: tracing::Subscriber + for<'span> LookupSpan<'span>,
+{
+    if !config.enabled {
+        return None;
+    }
+    let exporter = opentelemetry_otlp::new_exporter()
+        .tonic()
+        .with_endpoint(&config.endpoint)
+        .build_span_exporter()
+        .expect("Failed to build the OTLP span exporter");
+    Some(install(tracer_provider(exporter, config)))
+}
+
+/// Flushes spans still waiting in the batch before the process exits
+pub fn shutdown() {
+    global::shutdown_tracer_provider();
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|v| v.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|key| key.as_str()).collect()
+    }
+}
+
+/// Continues the caller's trace when the request carries a `traceparent` header
+pub fn continue_trace(span: &Span, headers: &HeaderMap) {
+    let parent = global::get_text_map_propagator(|propagator| propagator.extract(&HeaderExtractor(headers)));
+    span.set_parent(parent);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::config::init_for_tests,
+        mail::memory::InMemoryMailer,
+        middleware::client::ClientInfo,
+        notify::memory::InMemoryNotifier,
+        routes::auth::{login, LoginRequest},
+        telemetry::{metrics::track_http, request_span},
+        test_support::insert_user,
+    };
+    use axum::{
+        body::Body,
+        extract::State,
+        http::{Request, StatusCode},
+        middleware::from_fn,
+        routing::post,
+        Json, Router,
+    };
+    use opentelemetry::trace::{SpanId, TraceId};
+    use opentelemetry_sdk::testing::trace::InMemorySpanExporter;
+    use sqlx::{Pool, Postgres};
+    use std::sync::Arc;
+    use tower::ServiceExt;
+    use tower_http::trace::TraceLayer;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+    const PARENT_ID: &str = "00f067aa0ba902b7";
+
+    #[sqlx::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn exports_request_spans_under_the_callers_trace(pool: Pool<Postgres>) {
+        init_for_tests();
+        insert_user(&pool, "user@example.com", "User").await;
+        let exporter = InMemorySpanExporter::default();
+        // New traces are never sampled, so only the caller's decision gets spans exported
+        let config = OtelConfig {
+            sample_ratio: 0.0,
+            ..OtelConfig::default()
+        };
+        let provider = tracer_provider(exporter.clone(), &config);
+        let subscriber = tracing_subscriber::registry().with(install(provider.clone()));
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let app = Router::new()
+            .route(
+                "/auth/login",
+                post(move |client: ClientInfo, payload: Json<LoginRequest>| {
+                    login(
+                        State(pool.clone()),
+                        State(Arc::new(InMemoryNotifier::default())),
+                        State(Arc::new(InMemoryMailer::default())),
+                        client,
+                        payload,
+                    )
+                }),
+            )
+            .route_layer(from_fn(track_http))
+            .layer(TraceLayer::new_for_http().make_span_with(request_span));
+        let request = |traceparent: Option<String>| {
+            let mut request = Request::post("/auth/login").header("content-type", "application/json");
+            if let Some(traceparent) = traceparent {
+                request = request.header("traceparent", traceparent);
+            }
+            request
+                .body(Body::from(r#"{"email":"user@example.com","password":"password"}"#))
+                .unwrap()
+        };
+        // The request span ends once the response is dropped
+        let traced = app
+            .clone()
+            .oneshot(request(Some(format!("00-{}-{}-01", TRACE_ID, PARENT_ID))))
+            .await
+            .unwrap()
+            .status();
+        assert_eq!(traced, StatusCode::OK);
+        let untraced = app.oneshot(request(None)).await.unwrap().status();
+        assert_eq!(untraced, StatusCode::OK);
+
+        // The batch exporter runs on this runtime, so wait for it off the test's thread
+        tokio::task::spawn_blocking(move || provider.force_flush()).await.unwrap();
+        let spans = exporter.get_finished_spans().unwrap();
+        let trace_id = TraceId::from_hex(TRACE_ID).unwrap();
+        assert!(spans.iter().all(|span| span.span_context.trace_id() == trace_id));
+
+        let named = |name: &str| spans.iter().find(|span| span.name == name);
+        let handler = named("POST /auth/login").expect("handler span is exported");
+        assert_eq!(handler.parent_span_id, SpanId::from_hex(PARENT_ID).unwrap());
+        let query = named("db.get_user_by_email").expect("db span is exported");
+        assert_eq!(query.parent_span_id, handler.span_context.span_id());
+        let bcrypt = named("bcrypt").expect("bcrypt span is exported");
+        assert_eq!(bcrypt.parent_span_id, handler.span_context.span_id());
+    }
+}
@@ -0,0 +1,74 @@
+//! Fixtures shared by the database-backed tests.
+//!
+//! Those tests are `#[ignore]`d so the suite runs without a database; run them
+//! with `DATABASE_URL` pointing at a scratch server and `cargo test -- --ignored`.
+//! `#[sqlx::test]` creates a fresh, migrated database for each test.
+
+use axum::extract::FromRef;
+use sqlx::{Pool, Postgres};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::{middleware::permissions::PermissionCache, models::user::User, policy::schema::PolicySet};
+
+/// bcrypt hash of `password`, at the minimum cost so fixtures stay fast
+pub fn password_hash(password: &str) -> String {
+    bcrypt::hash(password, 4).unwrap()
+}
+
+/// Inserts a user with `role` and the password `password`
+pub async fn insert_user(pool: &Pool<Postgres>, email: &str, role: &str) -> User {
+    sqlx::query_as::<_, User>(
+        r#"
+        INSERT INTO users (firstname, lastname, email, password, role)
+        VALUES ('Test', 'User', $1, $2, $3)
+        RETURNING *
+        "#,
+    )
+    .bind(email)
+    .bind(password_hash("password"))
+    .bind(role)
+    .fetch_one(pool)
+    .await
+    .unwrap()
+}
+
+/// The parts of `AppState` that `auth_middleware` extracts, with no route
+/// policies, for layering it onto test routers
+#[derive(Clone, FromRef)]
+pub struct AuthState {
+    pub pool: Pool<Postgres>,
+    pub permissions: PermissionCache,
+    pub policies: Arc<PolicySet>,
+}
+
+impl AuthState {
+    pub fn new(pool: &Pool<Postgres>) -> Self {
+        AuthState {
+            pool: pool.clone(),
+            permissions: PermissionCache::default(),
+            policies: Arc::new(PolicySet::parse(r#"{ "policies": [] }"#).unwrap()),
+        }
+    }
+}
+
+/// A fresh directory under the system temp dir, removed when dropped
+pub struct ScratchDir(PathBuf);
+
+impl ScratchDir {
+    pub fn new() -> Self {
+        let path = std::env::temp_dir().join(format!("auth_api-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&path).unwrap();
+        ScratchDir(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}